@@ -0,0 +1,175 @@
+#![deny(clippy::all)]
+#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+#![allow(clippy::cast_precision_loss)]
+#![allow(clippy::cast_possible_truncation)]
+#![allow(clippy::float_cmp)]
+
+use std::path::{Path, PathBuf};
+
+use rusty_paint::{
+    canvas::{Canvas, PhysicalSize},
+    event_log::EventLog,
+    export,
+    paper::Paper,
+    script::Script,
+    timelapse::{Timelapse, TimelapseFormat},
+};
+
+const USAGE: &str = "\
+usage: render <log|rhai> [options]
+
+Replays an event log recorded with `winit --record <log>`, or runs a .rhai
+drawing script, without a window.
+
+options:
+    --output <png|svg|ora|psd>
+                          painting to write, default rusty_paint.png, an svg
+                          has the strokes as paths, ora and psd paper and paint
+                          layers
+    --size <w>x<h>        resolution, default the recorded window size or 512
+    --paper <png>         paper grain, as used when recording
+    --height-map          also write the paint thickness next to the output
+    --scale <n>           paint the strokes again at n times the size, sharper
+                          than scaling the pixels, the height map keeps the size
+    --timelapse <path>    also write a time-lapse of a log, .gif, .apng or pngs
+    --fps <n>             time-lapse frame rate, default 10
+    --speed <s>           seconds of painting per time-lapse second, default 1";
+
+const DEFAULT_OUTPUT: &str = "rusty_paint.png";
+/// canvas side for scripts, which have no recorded size.
+const DEFAULT_SIDE: u32 = 512;
+const DEFAULT_FPS: u16 = 10;
+const DEFAULT_SPEED: f32 = 1.0;
+
+fn main() {
+    env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(input) = args.first().filter(|arg| !arg.starts_with("--")) else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+    if let Err(err) = run(Path::new(input), &args) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+/// Renders the log or script as asked by the options in `args`.
+///
+/// # Errors
+///
+/// Returns a message if an option is invalid or rendering fails.
+fn run(input: &Path, args: &[String]) -> Result<(), String> {
+    let log = if input.extension().is_some_and(|ext| ext == "rhai") {
+        None
+    } else {
+        let log = EventLog::load(input)
+            .map_err(|err| format!("Failed to load event log {}: {err}", input.display()))?;
+        if log.has_unrecorded() {
+            eprintln!(
+                "{} used a paper, file or pasted image which is not in the log, pass the paper with --paper",
+                input.display()
+            );
+        }
+        Some(log)
+    };
+    let size = match arg_value(args, "--size") {
+        Some(size) => parse_size(size).ok_or_else(|| format!("Invalid size {size}"))?,
+        None => log.as_ref().map_or(
+            PhysicalSize {
+                width: DEFAULT_SIDE,
+                height: DEFAULT_SIDE,
+            },
+            EventLog::size,
+        ),
+    };
+    let paper = arg_value(args, "--paper")
+        .map(|path| {
+            Paper::from_png(Path::new(path))
+                .map_err(|err| format!("Failed to load paper {path}: {err}"))
+        })
+        .transpose()?;
+    let mut canvas =
+        futures::executor::block_on(Canvas::headless(size)).map_err(|err| err.to_string())?;
+    if let Some(paper) = &paper {
+        canvas.set_paper(paper);
+    }
+
+    let Some(log) = log else {
+        if arg_value(args, "--timelapse").is_some() {
+            return Err("A time-lapse needs an event log".into());
+        }
+        Script::new()
+            .run_file(&mut canvas, input)
+            .map_err(|err| format!("Failed to run script {}: {err}", input.display()))?;
+        return export(&mut canvas, args);
+    };
+    // the last time-lapse frame is the finished painting.
+    if let Some(path) = arg_value(args, "--timelapse") {
+        let path = Path::new(path);
+        let timelapse = Timelapse {
+            format: TimelapseFormat::from_path(path),
+            fps: parse_arg(args, "--fps")?.unwrap_or(DEFAULT_FPS),
+            speed: parse_arg(args, "--speed")?.unwrap_or(DEFAULT_SPEED),
+            size,
+        };
+        timelapse
+            .export(&mut canvas, &log, path)
+            .map_err(|err| format!("Failed to export {}: {err}", path.display()))?;
+    } else {
+        canvas.replay(&log.resized(size));
+    }
+    export(&mut canvas, args)
+}
+
+/// Writes `--output` and with `--height-map` the paint thickness, with
+/// `--scale` or as svg the output is made from the strokes.
+fn export(canvas: &mut Canvas, args: &[String]) -> Result<(), String> {
+    let output =
+        arg_value(args, "--output").map_or_else(|| PathBuf::from(DEFAULT_OUTPUT), PathBuf::from);
+    let failed = |err| format!("Failed to export {}: {err}", output.display());
+    let with_height_map = args.iter().any(|arg| arg == "--height-map");
+    match output.extension().and_then(|ext| ext.to_str()) {
+        Some("svg") => return canvas.export_svg(&output).map_err(failed),
+        Some("ora" | "psd") => return canvas.save_layered(&output).map_err(failed),
+        _ => {}
+    }
+    let scale = parse_arg::<u32>(args, "--scale")?.unwrap_or(1);
+    if scale <= 1 {
+        return canvas.export_png(&output, with_height_map).map_err(failed);
+    }
+    let size = canvas.size();
+    let size = PhysicalSize {
+        width: size.width * scale,
+        height: size.height * scale,
+    };
+    canvas.export_strokes_png(&output, size).map_err(failed)?;
+    if with_height_map {
+        let height_map = export::create_file(&export::height_map_path(&output)).map_err(failed)?;
+        canvas.encode_height_map_png(height_map).map_err(failed)?;
+    }
+    Ok(())
+}
+
+/// The value following `flag`.
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let mut args = args.iter().skip_while(|arg| *arg != flag);
+    args.nth(1).map(String::as_str)
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+    arg_value(args, flag)
+        .map(|value| value.parse().map_err(|_| format!("Invalid {flag} {value}")))
+        .transpose()
+}
+
+/// `640x480`, or `512` for a square.
+fn parse_size(size: &str) -> Option<PhysicalSize> {
+    let (width, height) = size.split_once('x').unwrap_or((size, size));
+    let size = PhysicalSize {
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+    };
+    (size.width > 0 && size.height > 0).then_some(size)
+}
@@ -1,11 +1,428 @@
-use yew::prelude::*;
+#![deny(clippy::all)]
+#![warn(clippy::pedantic)]
+#![warn(clippy::nursery)]
+#![allow(clippy::cast_precision_loss)]
+#![allow(clippy::cast_possible_truncation)]
+#![allow(clippy::cast_sign_loss)]
+#![allow(clippy::float_cmp)]
+// the browser runs everything on one thread.
+#![allow(clippy::future_not_send)]
+
+use std::{cell::RefCell, path::Path, rc::Rc, time::Duration};
+
+use futures::StreamExt;
+use rusty_paint::{
+    canvas::{Canvas, PhysicalSize},
+    error::CanvasError,
+    export,
+    keymap::{Action, Input, Keymap, Modifiers},
+    tool::Tool,
+};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    Blob, BlobPropertyBag, HtmlAnchorElement, HtmlCanvasElement, KeyboardEvent, PointerEvent, Url,
+};
+use wgpu::{PowerPreference, RequestAdapterOptions};
+use yew::{platform::spawn_local, prelude::*};
 
 fn main() {
     yew::Renderer::<App>::new().render();
 }
+
+const EXPORT_PATH: &str = "rusty_paint.png";
+const SVG_EXPORT_PATH: &str = "rusty_paint.svg";
+/// redraw interval animating the marching ants of a selection.
+const ANTS_INTERVAL: Duration = Duration::from_millis(120);
+/// canvas side in css pixels when the window size is unknown.
+const DEFAULT_SIDE: f64 = 512.0;
+
+/// The canvas once the adapter and device have been created.
+type SharedCanvas = Rc<RefCell<Option<Canvas>>>;
+
+/// `KeyboardEvent.key` values, lower case, with another name in the keymap.
+const KEY_NAMES: [(&str, &str); 21] = [
+    (" ", "space"),
+    ("+", "plus"),
+    ("-", "minus"),
+    ("=", "equals"),
+    (".", "period"),
+    (",", "comma"),
+    ("/", "slash"),
+    ("\\", "backslash"),
+    (";", "semicolon"),
+    ("'", "apostrophe"),
+    ("`", "grave"),
+    ("[", "left_bracket"),
+    ("]", "right_bracket"),
+    ("arrowleft", "left"),
+    ("arrowright", "right"),
+    ("arrowup", "up"),
+    ("arrowdown", "down"),
+    ("pageup", "page_up"),
+    ("pagedown", "page_down"),
+    ("del", "delete"),
+    ("esc", "escape"),
+];
+
+/// The key of a `KeyboardEvent.key` value, as named in the keymap.
+fn key_input(key: &str) -> Option<Input> {
+    let key = key.to_ascii_lowercase();
+    let name = KEY_NAMES
+        .iter()
+        .find(|(value, _)| *value == key)
+        .map_or(key.as_str(), |(_, name)| name);
+    match name.parse() {
+        Ok(input @ Input::Key(_)) => Some(input),
+        _ => None,
+    }
+}
+
+/// The action of a key press in the default keymap of the winit binary.
+fn key_action(keymap: &Keymap, event: &KeyboardEvent) -> Option<Action> {
+    let held = Modifiers {
+        ctrl: event.ctrl_key(),
+        shift: event.shift_key(),
+        alt: event.alt_key(),
+    };
+    keymap.action(key_input(&event.key())?, held)
+}
+
+/// Offers the exports as downloads, returns whether the canvas needs a
+/// redraw, `None` for the file, clipboard and document actions the browser
+/// does not have.
+fn apply_file_action(action: Action, canvas: &mut Canvas) -> Option<bool> {
+    let downloaded = match action {
+        Action::Export { with_height_map } => download_png(canvas, with_height_map),
+        Action::ExportSvg => download_svg(canvas),
+        _ => return None,
+    };
+    if let Err(err) = downloaded {
+        web_sys::console::error_2(&"Failed to export".into(), &err);
+    }
+    Some(false)
+}
+
+/// Css cursor matching the winit binary's cursor icons, hidden behind the
+/// brush outline.
+const fn cursor(canvas: &Canvas) -> &'static str {
+    if canvas.is_color_wheel_enabled() {
+        return "pointer";
+    }
+    match canvas.tool() {
+        Tool::Brush => "none",
+        Tool::Transform => "move",
+        _ => "crosshair",
+    }
+}
+
+/// Converts a pointer offset in css pixels to canvas pixels.
+fn canvas_pos(offset: [i32; 2], device_pixel_ratio: f64) -> [f32; 2] {
+    offset.map(|value| (f64::from(value) * device_pixel_ratio) as f32)
+}
+
+fn device_pixel_ratio() -> f64 {
+    web_sys::window().map_or(1.0, |window| window.device_pixel_ratio())
+}
+
+/// Side of the square canvas filling the shorter side of the browser window, in css pixels.
+fn canvas_side() -> f64 {
+    let dimension = |value: Result<JsValue, JsValue>| value.ok().and_then(|value| value.as_f64());
+    web_sys::window()
+        .and_then(|window| {
+            Some(dimension(window.inner_width())?.min(dimension(window.inner_height())?))
+        })
+        .unwrap_or(DEFAULT_SIDE)
+}
+
+async fn create_canvas(element: HtmlCanvasElement) -> Result<Canvas, JsValue> {
+    let css_side = canvas_side();
+    let style = element.style();
+    style.set_property("width", &format!("{css_side}px"))?;
+    style.set_property("height", &format!("{css_side}px"))?;
+    let pixels = (css_side * device_pixel_ratio()) as u32;
+    let size = PhysicalSize {
+        width: pixels,
+        height: pixels,
+    };
+    element.set_width(size.width);
+    element.set_height(size.height);
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::GL,
+        ..wgpu::InstanceDescriptor::default()
+    });
+    let surface = instance
+        .create_surface_from_canvas(&element)
+        .map_err(|err| err.to_string())?;
+    let adapter = instance
+        .request_adapter(&RequestAdapterOptions {
+            power_preference: PowerPreference::default(),
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or_else(|| CanvasError::NoAdapter.to_string())?;
+    let (device, queue) = Canvas::request_device(&adapter)
+        .await
+        .map_err(|err| err.to_string())?;
+    Canvas::new(size, surface, device, adapter, queue).map_err(|err| err.to_string().into())
+}
+
+/// Offers the painting (and its height map) as file downloads.
+fn download_png(canvas: &mut Canvas, with_height_map: bool) -> Result<(), JsValue> {
+    let mut data = vec![];
+    canvas
+        .encode_png(&mut data)
+        .map_err(|err| err.to_string())?;
+    download(EXPORT_PATH, "image/png", &data)?;
+    if with_height_map {
+        data.clear();
+        canvas
+            .encode_height_map_png(&mut data)
+            .map_err(|err| err.to_string())?;
+        download(
+            &export::height_map_path(Path::new(EXPORT_PATH)).to_string_lossy(),
+            "image/png",
+            &data,
+        )?;
+    }
+    Ok(())
+}
+
+/// Offers the strokes as an svg download.
+fn download_svg(canvas: &Canvas) -> Result<(), JsValue> {
+    let mut data = vec![];
+    canvas
+        .encode_svg(&mut data)
+        .map_err(|err| err.to_string())?;
+    download(SVG_EXPORT_PATH, "image/svg+xml", &data)
+}
+
+fn download(file_name: &str, mime_type: &str, data: &[u8]) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document")?;
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Url::revoke_object_url(&url)
+}
+
+/// Runs `f` on the canvas once it exists and redraws if it returns true.
+fn with_canvas(canvas: &SharedCanvas, element: &NodeRef, f: impl FnOnce(&mut Canvas) -> bool) {
+    let mut canvas = canvas.borrow_mut();
+    let Some(painting) = canvas.as_mut() else {
+        return;
+    };
+    let needs_redraw = f(painting);
+    if let Some(element) = element.cast::<HtmlCanvasElement>() {
+        // the style is always set by the html below.
+        let _ = element.style().set_property("cursor", cursor(painting));
+    }
+    if needs_redraw {
+        redraw(&mut canvas);
+    }
+}
+
+/// Draws the canvas, dropping it after a fatal error.
+fn redraw(canvas: &mut Option<Canvas>) {
+    let Some(Err(err)) = canvas.as_mut().map(Canvas::redraw_canvas) else {
+        return;
+    };
+    if err.is_fatal() {
+        *canvas = None;
+        report_error(&err.to_string().into());
+    } else {
+        web_sys::console::error_1(&err.to_string().into());
+    }
+}
+
+/// Tells the user the canvas stopped working.
+fn report_error(err: &JsValue) {
+    web_sys::console::error_1(err);
+    if let Some(window) = web_sys::window() {
+        let message = err.as_string().unwrap_or_else(|| format!("{err:?}"));
+        let _ = window.alert_with_message(&format!("Rusty Paint stopped: {message}"));
+    }
+}
+
+/// Redraws while a selection is shown so its marching ants move.
+async fn animate_ants(canvas: SharedCanvas) {
+    let mut interval = std::pin::pin!(yew::platform::time::interval(ANTS_INTERVAL));
+    while interval.next().await.is_some() {
+        let mut canvas = canvas.borrow_mut();
+        if canvas.as_ref().is_some_and(Canvas::has_selection) {
+            redraw(&mut canvas);
+        }
+    }
+}
+
 #[function_component(App)]
 pub fn app() -> Html {
+    let element = use_node_ref();
+    let canvas: SharedCanvas = use_mut_ref(|| None);
+    let pointer = use_mut_ref(|| [0.0; 2]);
+    let keymap = use_memo(|()| Keymap::default(), ());
+    {
+        let element = element.clone();
+        let canvas = canvas.clone();
+        use_effect_with_deps(
+            move |()| {
+                if let Some(html_canvas) = element.cast::<HtmlCanvasElement>() {
+                    let _ = html_canvas.focus();
+                    spawn_local(async move {
+                        match create_canvas(html_canvas).await {
+                            Ok(created) => {
+                                *canvas.borrow_mut() = Some(created);
+                                with_canvas(&canvas, &element, |_| true);
+                                animate_ants(canvas).await;
+                            }
+                            Err(err) => report_error(&err),
+                        }
+                    });
+                }
+            },
+            (),
+        );
+    }
+    let on_pointer = |brush_down: Option<bool>| {
+        let (element, canvas, pointer) = (element.clone(), canvas.clone(), pointer.clone());
+        Callback::from(move |event: PointerEvent| {
+            let pos = canvas_pos([event.offset_x(), event.offset_y()], device_pixel_ratio());
+            *pointer.borrow_mut() = pos;
+            if brush_down == Some(true) {
+                // keeps the stroke going when the pointer leaves the canvas.
+                if let Some(element) = element.cast::<HtmlCanvasElement>() {
+                    let _ = element.set_pointer_capture(event.pointer_id());
+                }
+            }
+            // moves keep the button state, presses and releases set it.
+            let brush_down = brush_down.unwrap_or_else(|| event.buttons() & 1 != 0);
+            with_canvas(&canvas, &element, |canvas| {
+                if brush_down {
+                    canvas.set_pressure(event.pressure());
+                }
+                canvas.mouse_at(brush_down, pos)
+            });
+        })
+    };
+    let onpointerdown = on_pointer(Some(true));
+    let onpointermove = on_pointer(None);
+    let onpointerup = on_pointer(Some(false));
+    let onpointerleave = {
+        let (element, canvas) = (element.clone(), canvas.clone());
+        Callback::from(move |_: PointerEvent| {
+            with_canvas(&canvas, &element, |canvas| {
+                canvas.mouse_left();
+                true
+            });
+        })
+    };
+    let onkeydown = {
+        let (element, canvas) = (element.clone(), canvas);
+        Callback::from(move |event: KeyboardEvent| {
+            let Some(action) = key_action(&keymap, &event) else {
+                return;
+            };
+            let pointer = *pointer.borrow();
+            with_canvas(&canvas, &element, |canvas| {
+                let applied = action
+                    .apply(canvas, Some(pointer))
+                    .or_else(|| apply_file_action(action, canvas));
+                let Some(needs_redraw) = applied else {
+                    return false;
+                };
+                event.prevent_default();
+                needs_redraw
+            });
+        })
+    };
     html! {
-        <h1>{env!("CARGO_PKG_NAME")}</h1>
+        <canvas
+            ref={element}
+            tabindex="0"
+            style="touch-action: none; outline: none"
+            {onpointerdown}
+            {onpointermove}
+            {onpointerup}
+            {onpointerleave}
+            {onkeydown}
+        />
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusty_paint::brush::BrushKind;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn action(keymap: &Keymap, key: &str, held: Modifiers) -> Option<Action> {
+        keymap.action(key_input(key)?, held)
+    }
+
+    #[wasm_bindgen_test]
+    fn keys_match_the_winit_binary() {
+        let keymap = Keymap::default();
+        let none = Modifiers::default();
+        let shift = Modifiers {
+            shift: true,
+            ..none
+        };
+        assert_eq!(action(&keymap, "+", shift), Some(Action::IncBrushSize));
+        assert_eq!(
+            action(&keymap, "3", none),
+            Some(Action::BrushKind(BrushKind::Pencil))
+        );
+        assert_eq!(
+            action(&keymap, "T", shift),
+            Some(Action::Tool(Tool::Transform))
+        );
+        assert_eq!(action(&keymap, "Escape", none), Some(Action::Cancel));
+        assert_eq!(action(&keymap, "[", none), Some(Action::ShrinkSelection));
+        assert_eq!(action(&keymap, " ", none), Some(Action::ToggleColorWheel));
+        assert_eq!(action(&keymap, "v", none), Some(Action::ExportSvg));
+        assert_eq!(
+            action(&keymap, "z", Modifiers { ctrl: true, ..none }),
+            Some(Action::Undo)
+        );
+        assert_eq!(action(&keymap, "s", none), None);
+        assert_eq!(key_input("Unidentified"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn shift_exports_the_height_map() {
+        let keymap = Keymap::default();
+        let none = Modifiers::default();
+        assert_eq!(
+            action(&keymap, "e", none),
+            Some(Action::Export {
+                with_height_map: false
+            })
+        );
+        assert_eq!(
+            action(
+                &keymap,
+                "E",
+                Modifiers {
+                    shift: true,
+                    ..none
+                }
+            ),
+            Some(Action::Export {
+                with_height_map: true
+            })
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn pointer_offsets_scale_to_canvas_pixels() {
+        assert_eq!(canvas_pos([10, 20], 1.0), [10.0, 20.0]);
+        assert_eq!(canvas_pos([10, 20], 2.0), [20.0, 40.0]);
     }
 }
@@ -5,84 +5,166 @@
 #![allow(clippy::cast_possible_truncation)]
 #![allow(clippy::float_cmp)]
 
-use rusty_paint::canvas::{Canvas, PhysicalSize};
-use wgpu::{DeviceDescriptor, Features, Limits, PowerPreference, RequestAdapterOptions};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use rusty_paint::{
+    canvas::{Canvas, PhysicalSize},
+    clipboard::Clipboard,
+    error::CanvasError,
+    event_log::{self, EventLog},
+    hud::Hud,
+    keymap::{Action, Input, Keymap, Modifiers},
+    layers::Layer,
+    paper::Paper,
+    script::Script,
+    settings::{Settings, Theme},
+};
+use wgpu::{Dx12Compiler, PowerPreference, RequestAdapterOptions};
 
 use winit::{
-    event::{Event, VirtualKeyCode, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::{CursorIcon, Window, WindowBuilder},
+    event::{Event, StartCause, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopProxy},
+    window::{Window, WindowBuilder},
 };
 use winit_input_helper::WinitInputHelper;
 fn main() {
     env_logger::init();
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-    run(event_loop, window);
+    let event_loop = EventLoopBuilder::with_user_event().build();
+    let settings = load_settings();
+    let window = match window_builder(&settings).build(&event_loop) {
+        Ok(window) => window,
+        Err(err) => {
+            eprintln!("Failed to create window: {err}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = run(event_loop, window, settings) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
 }
 
-pub fn run(event_loop: EventLoop<()>, window: Window) {
-    let mut input = WinitInputHelper::new();
-    let size = window.inner_size();
+const TITLE: &str = "Rusty Paint";
+const EXPORT_PATH: &str = "rusty_paint.png";
+/// the strokes painted again at a multiple of the window size.
+const STROKES_EXPORT_PATH: &str = "rusty_paint_4x.png";
+const STROKES_EXPORT_SCALE: u32 = 4;
+const SVG_EXPORT_PATH: &str = "rusty_paint.svg";
+const ORA_EXPORT_PATH: &str = "rusty_paint.ora";
+const PSD_EXPORT_PATH: &str = "rusty_paint.psd";
+/// copied images go here when there is no system clipboard.
+const CLIPBOARD_FALLBACK_PATH: &str = "rusty_paint_clipboard.png";
+/// key bindings read at launch when no `--keymap` is given.
+const KEYMAP_PATH: &str = "rusty_paint_keys.toml";
+/// redraw interval animating the marching ants of a selection.
+const ANTS_INTERVAL: Duration = Duration::from_millis(120);
 
+/// Paints in the window until it is closed.
+///
+/// # Errors
+///
+/// Returns an error if no surface, adapter or device can be created for the window.
+pub fn run(
+    event_loop: EventLoop<String>,
+    window: Window,
+    mut settings: Settings,
+) -> Result<(), CanvasError> {
+    let mut input = WinitInputHelper::new();
     let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
         backends: wgpu::Backends::all(),
-        dx12_shader_compiler: Default::default(),
+        dx12_shader_compiler: Dx12Compiler::default(),
     });
-    let surface = unsafe { instance.create_surface(&window) }.unwrap();
-    let adapter = futures::executor::block_on(instance.request_adapter(&RequestAdapterOptions {
-        power_preference: PowerPreference::default(),
-        compatible_surface: Some(&surface),
-        force_fallback_adapter: false,
-    }))
-    .expect("Failed to find an appropriate adapter");
-
-    let (device, queue) = futures::executor::block_on(adapter.request_device(
-        &DeviceDescriptor {
-            label: None,
-            features: Features::PUSH_CONSTANTS,
-            limits: Limits {
-                max_push_constant_size: 32,
-                ..Limits::default()
-            },
-        },
-        None,
-    ))
-    .expect("Failed to create device");
-    let mut canvas = Canvas::new(
-        PhysicalSize {
-            width: size.width,
-            height: size.width,
-        },
-        surface,
-        device,
-        adapter,
-        queue,
-    );
+    let mut canvas = create_canvas(&instance, &window)?;
+    open_from_args(&mut canvas, &mut settings);
+    let mut replay =
+        arg_path("--replay").and_then(|path| Replay::load(&path, &window, &mut canvas));
+    let record_path = arg_path("--record");
+    if record_path.is_some() {
+        canvas.start_recording();
+    }
+    // a replay starts from the brush and tool of its recording.
+    if replay.is_none() {
+        settings.apply(&mut canvas);
+    }
+    show_document(&window, &canvas);
+    let mut script = Script::new();
+    run_script_args(&mut script, &mut canvas, &event_loop);
+    let mut clipboard = connect_clipboard();
+    let keymap = load_keymap();
+    let mut hud = Hud::new(&event_loop, &window);
     event_loop.run(move |event, _, control_flow| {
         let _ = &instance;
-
-        *control_flow = ControlFlow::Wait;
+        if let (Event::WindowEvent { event, .. }, None) = (&event, &replay) {
+            if hud.on_event(event) {
+                window.request_redraw();
+            }
+        }
+        replay = replay
+            .take()
+            .and_then(|playing| playing.play(&window, &mut canvas));
+        *control_flow = next_wake_up(&canvas, replay.as_ref());
         match event {
+            Event::NewEvents(StartCause::ResumeTimeReached { .. }) => window.request_redraw(),
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
                 ..
             } => {
                 canvas.resize_window(PhysicalSize {
                     width: size.width,
-                    height: size.width,
+                    height: size.height,
                 });
             }
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. },
+                ..
+            } => {
+                canvas.mouse_left();
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::TouchpadPressure { pressure, .. },
+                ..
+            } if replay.is_none() => canvas.set_pressure(pressure),
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
+                ..
+            } if replay.is_none() => {
+                if open_dropped(&path, &window, &mut canvas) {
+                    settings.add_recent_file(&path);
+                    window.request_redraw();
+                }
+            }
+            Event::UserEvent(line) if replay.is_none() => match script.run(&mut canvas, &line) {
+                Ok(()) => window.request_redraw(),
+                Err(err) => eprintln!("{err}"),
+            },
             Event::RedrawRequested(_) => {
-                canvas.redraw_canvas();
+                if !redraw(&window, &mut canvas, &mut hud) {
+                    *control_flow = ControlFlow::ExitWithCode(1);
+                }
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => *control_flow = ControlFlow::Exit,
+            } => {
+                close(record_path.as_deref(), &mut settings, &window, &mut canvas);
+                *control_flow = ControlFlow::Exit;
+            }
             _ => {
-                if input.update(&event) {
-                    let redraw_window = handle_input(&input, &window, &mut canvas);
+                // the replayed inputs stand in for the user's.
+                if input.update(&event) && replay.is_none() {
+                    let redraw_window = handle_input(
+                        &input,
+                        &window,
+                        &mut canvas,
+                        &keymap,
+                        &mut clipboard,
+                        &mut settings,
+                        &mut hud,
+                    );
                     if redraw_window {
                         window.request_redraw();
                     }
@@ -92,25 +174,519 @@ pub fn run(event_loop: EventLoop<()>, window: Window) {
     });
 }
 
-fn handle_input(input: &WinitInputHelper, window: &Window, canvas: &mut Canvas) -> bool {
-    let mut redraw_window = false;
-    if input.key_pressed(VirtualKeyCode::Plus) {
-        canvas.inc_brush_size();
+/// Draws the canvas with the widgets on top, and the next frame if they
+/// animate. Returns `false` if drawing failed for good.
+fn redraw(window: &Window, canvas: &mut Canvas, hud: &mut Hud) -> bool {
+    if hud.update(window, canvas) {
+        window.request_redraw();
     }
-    if input.key_pressed(VirtualKeyCode::Minus) {
-        canvas.dec_brush_size();
+    match canvas.redraw_with_overlay(hud) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("{err}");
+            !err.is_fatal()
+        }
     }
-    if let Some((x, y)) = input.mouse() {
-        redraw_window |= canvas.mouse_at(input.mouse_held(0), [x, y]);
+}
+
+/// Waits for the next frame of the marching ants or replayed event, or
+/// else for input.
+fn next_wake_up(canvas: &Canvas, replay: Option<&Replay>) -> ControlFlow {
+    let ants = canvas
+        .has_selection()
+        .then(|| Instant::now() + ANTS_INTERVAL);
+    let next_event = replay.and_then(Replay::next_time);
+    ants.into_iter()
+        .chain(next_event)
+        .min()
+        .map_or(ControlFlow::Wait, ControlFlow::WaitUntil)
+}
+
+/// A canvas presenting to the window.
+fn create_canvas(instance: &wgpu::Instance, window: &Window) -> Result<Canvas, CanvasError> {
+    let size = window.inner_size();
+    let surface = unsafe { instance.create_surface(window) }?;
+    let adapter = futures::executor::block_on(instance.request_adapter(&RequestAdapterOptions {
+        power_preference: PowerPreference::default(),
+        compatible_surface: Some(&surface),
+        force_fallback_adapter: false,
+    }))
+    .ok_or(CanvasError::NoAdapter)?;
+
+    let (device, queue) = futures::executor::block_on(Canvas::request_device(&adapter))?;
+    Canvas::new(
+        PhysicalSize {
+            width: size.width,
+            height: size.height,
+        },
+        surface,
+        device,
+        adapter,
+        queue,
+    )
+}
+
+/// Loads the `--paper` and `--open` files, or the most recent `.ora` or
+/// `.psd` file with `--open-recent`.
+fn open_from_args(canvas: &mut Canvas, settings: &mut Settings) {
+    if let Some(path) = arg_path("--paper") {
+        match Paper::from_png(&path) {
+            Ok(paper) => canvas.set_paper(&paper),
+            Err(err) => eprintln!("Failed to load paper {}: {err}", path.display()),
+        }
+    }
+    let path = arg_path("--open").or_else(|| {
+        std::env::args()
+            .any(|arg| arg == "--open-recent")
+            .then(|| {
+                settings
+                    .recent_files
+                    .iter()
+                    .find(|path| is_layered(path) && path.exists())
+                    .cloned()
+            })
+            .flatten()
+    });
+    if let Some(path) = path {
+        if open_layered(canvas, &path) {
+            settings.add_recent_file(&path);
+        }
+    }
+}
+
+/// Runs the `--script` file and reads `--console` lines as scripts.
+fn run_script_args(script: &mut Script, canvas: &mut Canvas, event_loop: &EventLoop<String>) {
+    if let Some(path) = arg_path("--script") {
+        if let Err(err) = script.run_file(canvas, &path) {
+            eprintln!("Failed to run script {}: {err}", path.display());
+        }
+    }
+    if std::env::args().any(|arg| arg == "--console") {
+        spawn_console(event_loop.create_proxy());
     }
-    if input.key_pressed(VirtualKeyCode::Space) {
-        canvas.color_wheel_toggle();
-        if canvas.is_color_wheel_enabled() {
-            window.set_cursor_icon(CursorIcon::Hand);
-        } else {
-            window.set_cursor_icon(CursorIcon::Default);
+}
+
+/// Saves the `--record` event log and the settings as the window closes.
+fn close(
+    record_path: Option<&Path>,
+    settings: &mut Settings,
+    window: &Window,
+    canvas: &mut Canvas,
+) {
+    if let (Some(path), Some(log)) = (record_path, canvas.stop_recording()) {
+        if let Err(err) = log.save(path) {
+            eprintln!("Failed to save event log {}: {err}", path.display());
+        }
+    }
+    save_settings(settings, window, canvas);
+}
+
+/// The settings of the last session, falling back to the defaults.
+fn load_settings() -> Settings {
+    let Some(path) = Settings::path().filter(|path| path.exists()) else {
+        return Settings::default();
+    };
+    Settings::load(&path).unwrap_or_else(|err| {
+        eprintln!(
+            "Failed to load settings {}, using the defaults: {err}",
+            path.display()
+        );
+        Settings::default()
+    })
+}
+
+/// Remembers the window, brush and tool for the next session.
+fn save_settings(settings: &mut Settings, window: &Window, canvas: &Canvas) {
+    let size = window.inner_size();
+    // a minimized window has no size, the last one is kept.
+    if size.width > 0 && size.height > 0 {
+        settings.window_size = Some(PhysicalSize {
+            width: size.width,
+            height: size.height,
+        });
+        // not every platform can tell the position of a window.
+        if let Ok(position) = window.outer_position() {
+            settings.window_position = Some([position.x, position.y]);
         }
+    }
+    settings.remember(canvas);
+    let Some(path) = Settings::path() else {
+        return;
+    };
+    if let Err(err) = settings.save(&path) {
+        eprintln!("Failed to save settings {}: {err}", path.display());
+    }
+}
+
+/// A window sized, placed and themed like the last one.
+fn window_builder(settings: &Settings) -> WindowBuilder {
+    let mut builder = WindowBuilder::new().with_theme(match settings.theme {
+        Theme::System => None,
+        Theme::Light => Some(winit::window::Theme::Light),
+        Theme::Dark => Some(winit::window::Theme::Dark),
+    });
+    if let Some(size) = settings.window_size {
+        builder = builder.with_inner_size(winit::dpi::PhysicalSize::new(size.width, size.height));
+    }
+    if let Some([x, y]) = settings.window_position {
+        builder = builder.with_position(winit::dpi::PhysicalPosition::new(x, y));
+    }
+    builder
+}
+
+/// The path following `flag` on the command line:
+///
+/// - `--paper <png>` loads the paper grain from an image.
+/// - `--record <log>` saves every input to an event log on exit.
+/// - `--replay <log>` plays an event log back in real time.
+/// - `--script <rhai>` draws with a script at launch.
+/// - `--open <ora|psd>` starts from the layers of an `OpenRaster` or
+///   Photoshop file, `--open-recent` from the last one opened or saved.
+/// - `--keymap <toml>` reads the key bindings, see [`Keymap::parse`].
+fn arg_path(flag: &str) -> Option<PathBuf> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.nth(1).map(PathBuf::from)
+}
+
+/// The system clipboard, printing why the fallback file is used instead.
+fn connect_clipboard() -> Clipboard {
+    let (clipboard, warning) = Clipboard::new(CLIPBOARD_FALLBACK_PATH);
+    if let Some(warning) = warning {
+        eprintln!("{warning}");
+    }
+    clipboard
+}
+
+/// Replaces the painting with an `.ora` or `.psd` file, printing what was
+/// dropped. Returns whether it was opened.
+fn open_layered(canvas: &mut Canvas, path: &Path) -> bool {
+    match canvas.open_layered(path) {
+        Ok(warnings) => {
+            for warning in warnings {
+                eprintln!("{}: {warning}", path.display());
+            }
+            true
+        }
+        Err(err) => {
+            eprintln!("Failed to open {}: {err}", path.display());
+            false
+        }
+    }
+}
+
+/// Whether `path` is an `.ora` or `.psd` file, going by its extension.
+fn is_layered(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ora") || ext.eq_ignore_ascii_case("psd"))
+}
+
+/// A png dropped on the window floats as a new layer, an `.ora` or `.psd`
+/// file opens in a new document. Returns whether it was opened.
+fn open_dropped(path: &Path, window: &Window, canvas: &mut Canvas) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => match Layer::from_png(path) {
+            Ok(layer) => {
+                float_centered(layer, canvas);
+                true
+            }
+            Err(err) => {
+                eprintln!("Failed to open {}: {err}", path.display());
+                false
+            }
+        },
+        Some("ora" | "psd") => {
+            canvas.new_document();
+            let opened = open_layered(canvas, path);
+            if !opened {
+                canvas.close_document();
+            }
+            show_document(window, canvas);
+            opened
+        }
+        _ => {
+            eprintln!(
+                "Cannot open {}, drop a png, ora or psd file",
+                path.display()
+            );
+            false
+        }
+    }
+}
+
+/// Pastes `layer` centered on the canvas for the transform tool.
+fn float_centered(mut layer: Layer, canvas: &mut Canvas) {
+    let size = canvas.size();
+    let center = |length: u32, layer_length: u32| (i64::from(length) - i64::from(layer_length)) / 2;
+    layer.offset = [
+        center(size.width, layer.size.width),
+        center(size.height, layer.size.height),
+    ]
+    .map(|offset| i32::try_from(offset).unwrap_or(i32::MIN));
+    canvas.paste_layer(&layer);
+}
+
+/// The `--keymap` bindings, or those of `rusty_paint_keys.toml` if there is
+/// one, falling back to the defaults.
+fn load_keymap() -> Keymap {
+    let path = arg_path("--keymap").unwrap_or_else(|| PathBuf::from(KEYMAP_PATH));
+    if !path.exists() && path == Path::new(KEYMAP_PATH) {
+        return Keymap::default();
+    }
+    Keymap::load(&path).unwrap_or_else(|err| {
+        eprintln!(
+            "Failed to load key bindings {}, using the defaults: {err}",
+            path.display()
+        );
+        Keymap::default()
+    })
+}
+
+/// `--console` runs each line typed into the terminal as a script, sent to
+/// the event loop as a user event.
+fn spawn_console(proxy: EventLoopProxy<String>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            if proxy.send_event(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// A recorded session played back in real time.
+struct Replay {
+    log: EventLog,
+    start: Instant,
+    /// index of the first event not applied yet.
+    next: usize,
+}
+
+impl Replay {
+    /// Loads the log and sizes the window and canvas like the recording.
+    fn load(path: &Path, window: &Window, canvas: &mut Canvas) -> Option<Self> {
+        let log = match EventLog::load(path) {
+            Ok(log) => log,
+            Err(err) => {
+                eprintln!("Failed to load event log {}: {err}", path.display());
+                return None;
+            }
+        };
+        if log.has_unrecorded() {
+            eprintln!(
+                "{} used a paper, file or pasted image which is not in the log, the replay may differ",
+                path.display()
+            );
+        }
+        let size = log.size();
+        window.set_inner_size(winit::dpi::PhysicalSize::new(size.width, size.height));
+        canvas.resize_window(size);
+        Some(Self {
+            log,
+            start: Instant::now(),
+            next: 0,
+        })
+    }
+
+    /// Applies the events which are due, returns whether the window needs
+    /// to be redrawn.
+    fn advance(&mut self, window: &Window, canvas: &mut Canvas) -> bool {
+        let elapsed = self.start.elapsed();
+        let mut redraw_window = false;
+        while let Some(timed) = self.log.events().get(self.next) {
+            if timed.time > elapsed {
+                break;
+            }
+            if let event_log::Event::Resize(size) = timed.event {
+                window.set_inner_size(winit::dpi::PhysicalSize::new(size.width, size.height));
+            }
+            redraw_window |= canvas.apply(&timed.event);
+            self.next += 1;
+        }
+        redraw_window
+    }
+
+    /// Applies the events which are due and redraws, `None` once all are
+    /// applied.
+    fn play(mut self, window: &Window, canvas: &mut Canvas) -> Option<Self> {
+        if self.advance(window, canvas) {
+            window.request_redraw();
+        }
+        self.next_time().is_some().then_some(self)
+    }
+
+    /// When the next event is due, `None` once all are applied.
+    fn next_time(&self) -> Option<Instant> {
+        let timed = self.log.events().get(self.next)?;
+        Some(self.start + timed.time)
+    }
+}
+
+/// Paints with the mouse and applies the actions of the pressed keys and
+/// buttons, unless the widgets take them. Returns whether the window needs
+/// to be redrawn.
+fn handle_input(
+    input: &WinitInputHelper,
+    window: &Window,
+    canvas: &mut Canvas,
+    keymap: &Keymap,
+    clipboard: &mut Clipboard,
+    settings: &mut Settings,
+    hud: &mut Hud,
+) -> bool {
+    let mut redraw_window = false;
+    if hud.wants_pointer() {
+        // hides the brush outline over the widgets.
+        canvas.mouse_left();
         redraw_window = true;
+    } else if let Some(pos) = input.mouse() {
+        redraw_window |= canvas.mouse_at(is_held(input, keymap, Action::Paint), pos.into());
+    }
+    let modifiers = Modifiers {
+        ctrl: input.held_control(),
+        shift: input.held_shift(),
+        alt: input.held_alt(),
+    };
+    let actions: Vec<_> = keymap
+        .inputs()
+        .filter(|&bound| match bound {
+            Input::Key(code) => !hud.wants_keyboard() && input.key_pressed(code),
+            Input::Mouse(index) => !hud.wants_pointer() && input.mouse_pressed(index),
+        })
+        .filter_map(|pressed| keymap.action(pressed, modifiers))
+        .collect();
+    for action in actions {
+        if action == Action::ToggleHud {
+            hud.toggle();
+            redraw_window = true;
+            continue;
+        }
+        redraw_window |= action
+            .apply(canvas, input.mouse().map(Into::into))
+            .unwrap_or_else(|| {
+                apply_document_action(action, window, canvas)
+                    || apply_file_action(action, canvas, clipboard, settings)
+            });
     }
     redraw_window
 }
+
+/// Whether a binding of `action` is held down, with at least its modifiers.
+fn is_held(input: &WinitInputHelper, keymap: &Keymap, action: Action) -> bool {
+    keymap.bindings_of(action).any(|binding| {
+        let held = match binding.input {
+            Input::Key(code) => input.key_held(code),
+            Input::Mouse(index) => input.mouse_held(index),
+        };
+        let Modifiers { ctrl, shift, alt } = binding.modifiers;
+        held && (!ctrl || input.held_control())
+            && (!shift || input.held_shift())
+            && (!alt || input.held_alt())
+    })
+}
+
+/// Opens, closes or switches documents, returns whether the window needs to
+/// be redrawn.
+fn apply_document_action(action: Action, window: &Window, canvas: &mut Canvas) -> bool {
+    let count = canvas.document_count();
+    match action {
+        Action::NewDocument => canvas.new_document(),
+        Action::CloseDocument => canvas.close_document(),
+        Action::NextDocument => {
+            canvas.switch_document((canvas.active_document() + 1) % count);
+        }
+        Action::PreviousDocument => {
+            canvas.switch_document((canvas.active_document() + count - 1) % count);
+        }
+        _ => return false,
+    }
+    show_document(window, canvas);
+    true
+}
+
+/// Titles the window with the position of the shown document.
+fn show_document(window: &Window, canvas: &Canvas) {
+    let count = canvas.document_count();
+    window.set_title(&if count > 1 {
+        format!("{TITLE} ({}/{count})", canvas.active_document() + 1)
+    } else {
+        TITLE.to_string()
+    });
+}
+
+/// Applies an action exporting, saving or using the clipboard, returns
+/// whether the window needs to be redrawn.
+fn apply_file_action(
+    action: Action,
+    canvas: &mut Canvas,
+    clipboard: &mut Clipboard,
+    settings: &mut Settings,
+) -> bool {
+    let (path, result) = match action {
+        Action::Export { with_height_map } => (
+            EXPORT_PATH,
+            canvas.export_png(Path::new(EXPORT_PATH), with_height_map),
+        ),
+        Action::ExportStrokes => {
+            let size = canvas.size();
+            let size = PhysicalSize {
+                width: size.width * STROKES_EXPORT_SCALE,
+                height: size.height * STROKES_EXPORT_SCALE,
+            };
+            (
+                STROKES_EXPORT_PATH,
+                canvas.export_strokes_png(Path::new(STROKES_EXPORT_PATH), size),
+            )
+        }
+        Action::ExportSvg => (
+            SVG_EXPORT_PATH,
+            canvas.export_svg(Path::new(SVG_EXPORT_PATH)),
+        ),
+        Action::SaveOra => (
+            ORA_EXPORT_PATH,
+            canvas.save_layered(Path::new(ORA_EXPORT_PATH)),
+        ),
+        Action::SavePsd => (
+            PSD_EXPORT_PATH,
+            canvas.save_layered(Path::new(PSD_EXPORT_PATH)),
+        ),
+        Action::Copy => {
+            let copied = match canvas.copy_layer() {
+                Ok(Some(layer)) => clipboard.copy(&layer),
+                Ok(None) => Ok(None),
+                Err(err) => Err(err.into()),
+            };
+            match copied {
+                Ok(warning) => warning.iter().for_each(|warning| eprintln!("{warning}")),
+                Err(err) => eprintln!("Failed to copy: {err}"),
+            }
+            return false;
+        }
+        Action::Paste => {
+            return match clipboard.paste() {
+                Ok((layer, warning)) => {
+                    if let Some(warning) = warning {
+                        eprintln!("{warning}");
+                    }
+                    float_centered(layer, canvas);
+                    true
+                }
+                Err(err) => {
+                    eprintln!("Failed to paste: {err}");
+                    false
+                }
+            };
+        }
+        _ => return false,
+    };
+    match result {
+        Ok(()) if is_layered(Path::new(path)) => settings.add_recent_file(Path::new(path)),
+        Ok(()) => {}
+        Err(err) => eprintln!("Failed to write {path}: {err}"),
+    }
+    false
+}
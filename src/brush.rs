@@ -1,11 +1,45 @@
 use wgpu::{vertex_attr_array, VertexAttribute, VertexBufferLayout, VertexStepMode};
 
+use crate::stroke::{Sample, Stroke};
+
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BrushKind {
+    #[default]
+    Round,
+    /// thick paint which leaves a ridge in the height map.
+    Impasto,
+    /// hard graphite, lightly broken up by the paper grain.
+    Pencil,
+    /// soft charcoal, only catches the peaks of the paper grain.
+    Charcoal,
+}
+
+impl BrushKind {
+    const fn height(self) -> f32 {
+        match self {
+            Self::Impasto => 0.3,
+            Self::Round | Self::Pencil | Self::Charcoal => 0.0,
+        }
+    }
+
+    /// how much the paper grain breaks up the stroke, 0.0 for wet media.
+    const fn dryness(self) -> f32 {
+        match self {
+            Self::Round | Self::Impasto => 0.0,
+            Self::Pencil => 0.6,
+            Self::Charcoal => 1.0,
+        }
+    }
+}
 
 pub struct Brush {
     color: [f32; 3],
     pos: [f32; 2],
     down: bool,
     radius: f32,
+    kind: BrushKind,
+    opacity: f32,
+    pressure: f32,
 }
 
 impl Default for Brush {
@@ -15,6 +49,9 @@ impl Default for Brush {
             pos: Default::default(),
             down: Default::default(),
             radius: Self::BRUSH_MIN * 5.0,
+            kind: BrushKind::default(),
+            opacity: 1.0,
+            pressure: Self::DEFAULT_PRESSURE,
         }
     }
 }
@@ -24,26 +61,65 @@ impl Default for Brush {
 pub struct Point {
     color: [f32; 3],
     pos: [f32; 2],
+    /// paint thickness at the center of the stroke.
+    height: f32,
+    /// -1.0 or 1.0 depending on the side of the stroke.
+    across: f32,
+    dryness: f32,
+    pressure: f32,
+    opacity: f32,
 }
 
 impl Point {
-    const ATTRIBUTES: [VertexAttribute; 3] = vertex_attr_array![
-        0 => Float32x3,  1 => Float32x2, 2 => Float32
+    const ATTRIBUTES: [VertexAttribute; 7] = vertex_attr_array![
+        0 => Float32x3,  1 => Float32x2, 2 => Float32, 3 => Float32, 4 => Float32, 5 => Float32,
+        6 => Float32
     ];
 
-    #[must_use] pub const fn desc<'a>() -> VertexBufferLayout<'a> {
+    #[must_use]
+    pub const fn desc<'a>() -> VertexBufferLayout<'a> {
         VertexBufferLayout {
             array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
             step_mode: VertexStepMode::Vertex,
             attributes: &Self::ATTRIBUTES,
         }
     }
+
+    /// Two triangles covering the segment of `stroke` between two samples,
+    /// as wide as the stroke and with the pressure of `to`.
+    #[must_use]
+    pub fn segment(stroke: &Stroke, from: Sample, to: Sample) -> [Self; 6] {
+        let angle = f32::atan2(from.pos[1] - to.pos[1], from.pos[0] - to.pos[0]);
+        let dx = f32::sin(angle) * stroke.radius / 2.0;
+        let dy = f32::cos(angle) * stroke.radius / 2.0;
+        let point = |pos: [f32; 2], across: f32| Self {
+            pos: [across.mul_add(-dx, pos[0]), across.mul_add(dy, pos[1])],
+            color: stroke.color,
+            height: stroke.kind.height(),
+            across,
+            dryness: stroke.kind.dryness(),
+            pressure: to.pressure,
+            opacity: stroke.opacity,
+        };
+        [
+            point(from.pos, 1.0),
+            point(from.pos, -1.0),
+            point(to.pos, -1.0),
+            point(to.pos, -1.0),
+            point(to.pos, 1.0),
+            point(from.pos, 1.0),
+        ]
+    }
 }
 
 impl Brush {
-    const BRUSH_MIN: f32 = 0.0001;
+    /// smallest radius, as a fraction of the canvas width.
+    pub const BRUSH_MIN: f32 = 0.0001;
     const BRUSH_STEP: f32 = 0.0001;
-    const BRUSH_MAX: f32 = 0.1;
+    /// largest radius, as a fraction of the canvas width.
+    pub const BRUSH_MAX: f32 = 0.1;
+    /// pressure of a mouse, which cannot report any.
+    pub const DEFAULT_PRESSURE: f32 = 0.5;
 
     pub fn dec_radius(&mut self) {
         if self.radius > Self::BRUSH_MIN + Self::BRUSH_STEP {
@@ -51,55 +127,94 @@ impl Brush {
         }
     }
 
+    /// The segment moved along since the last position, if the brush was
+    /// down.
     #[must_use = "stroke output must be used"]
-    pub fn draw_stroke(&mut self, down: bool, pos: [f32; 2]) -> Option<[Point; 6]> {
+    pub fn draw_stroke(&mut self, down: bool, pos: [f32; 2]) -> Option<[Sample; 2]> {
         let prev_pos = self.pos;
         let prev_down = self.down;
         self.down = down;
         self.pos = pos;
-        if prev_down && pos != prev_pos {
-            let color = self.color;
-            let angle = f32::atan2(prev_pos[1] - pos[1], prev_pos[0] - pos[0]);
-            let dx = f32::sin(angle) * self.radius / 2.0;
-            let dy = f32::cos(angle) * self.radius / 2.0;
-            Some([
-                Point {
-                    pos: [prev_pos[0] - dx, prev_pos[1] + dy],
-                    color,
-                },
-                Point {
-                    pos: [prev_pos[0] + dx, prev_pos[1] - dy],
-                    color,
-                },
-                Point {
-                    pos: [pos[0] + dx, pos[1] - dy],
-                    color,
-                },
-                Point {
-                    pos: [pos[0] + dx, pos[1] - dy],
-                    color,
-                },
-                Point {
-                    pos: [pos[0] - dx, pos[1] + dy],
-                    color,
-                },
-                Point {
-                    pos: [prev_pos[0] - dx, prev_pos[1] + dy],
-                    color,
-                },
-            ])
-        } else {
-            None
+        (prev_down && pos != prev_pos).then(|| {
+            [prev_pos, pos].map(|pos| Sample {
+                pos,
+                pressure: self.pressure,
+            })
+        })
+    }
+
+    /// Lifts the brush, the next stroke starts where it is put down.
+    pub const fn lift(&mut self) {
+        self.down = false;
+    }
+
+    /// A stroke along `samples` with the current color, size and kind.
+    #[must_use]
+    pub const fn stroke(&self, samples: Vec<Sample>) -> Stroke {
+        Stroke {
+            color: self.color,
+            radius: self.radius,
+            kind: self.kind,
+            opacity: self.opacity,
+            samples,
         }
     }
 
+    /// Whether `stroke` has the current color, size, kind and opacity.
+    #[must_use]
+    pub fn paints(&self, stroke: &Stroke) -> bool {
+        stroke.color == self.color
+            && stroke.radius == self.radius
+            && stroke.kind == self.kind
+            && stroke.opacity == self.opacity
+    }
+
     pub fn inc_radius(&mut self) {
         if self.radius < Self::BRUSH_MAX - Self::BRUSH_STEP {
             self.radius += Self::BRUSH_STEP;
         }
     }
 
-    pub fn set_color(&mut self, color: [f32; 3]) {
+    #[must_use]
+    pub const fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// `radius` is clamped to the sizes `inc_radius` and `dec_radius` reach.
+    pub const fn set_radius(&mut self, radius: f32) {
+        self.radius = radius.clamp(Self::BRUSH_MIN, Self::BRUSH_MAX);
+    }
+
+    #[must_use]
+    pub const fn color(&self) -> [f32; 3] {
+        self.color
+    }
+
+    pub const fn set_color(&mut self, color: [f32; 3]) {
         self.color = color;
     }
+
+    #[must_use]
+    pub const fn kind(&self) -> BrushKind {
+        self.kind
+    }
+
+    pub const fn set_kind(&mut self, kind: BrushKind) {
+        self.kind = kind;
+    }
+
+    #[must_use]
+    pub const fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// `opacity` is clamped to `0.0..=1.0`.
+    pub const fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// `pressure` is clamped to `0.0..=1.0`.
+    pub const fn set_pressure(&mut self, pressure: f32) {
+        self.pressure = pressure.clamp(0.0, 1.0);
+    }
 }
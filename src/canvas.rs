@@ -1,95 +1,945 @@
+use std::{
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    Adapter, Buffer, BufferUsages, Color, CommandEncoderDescriptor, Device, Extent3d,
-    FragmentState, LoadOp, MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState,
-    PrimitiveTopology, PushConstantRange, Queue, RenderPassColorAttachment, RenderPassDescriptor,
-    RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
-    Surface, SurfaceConfiguration, VertexState,
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent,
+    BlendFactor, BlendOperation, BlendState, Buffer, BufferBindingType, BufferUsages, Color,
+    CommandEncoder, CommandEncoderDescriptor, Device, DeviceDescriptor, Extent3d, Features,
+    FragmentState, Limits, LoadOp, MultisampleState, Operations, PipelineLayoutDescriptor,
+    PrimitiveState, PrimitiveTopology, Queue, RenderPass, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, Surface, SurfaceConfiguration,
+    SurfaceTexture, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureView, TextureViewDimension, VertexState,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PhysicalSize {
     pub width: u32,
     pub height: u32,
 }
 
+/// The layers of a painting, as saved by [`Canvas::layered_image`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanvasLayer {
+    Paper,
+    Paint,
+}
+
+/// How a layer of the painting is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerStyle {
+    pub visible: bool,
+    /// `0.0..=1.0`, applied while the layer is visible.
+    pub opacity: f32,
+}
+
+impl Default for LayerStyle {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl LayerStyle {
+    /// The opacity the layer is drawn with.
+    #[must_use]
+    pub const fn shown_opacity(self) -> f32 {
+        if self.visible {
+            self.opacity
+        } else {
+            0.0
+        }
+    }
+}
+
 use crate::{
-    brush::{Brush, Point},
-    colorwheel::ColorWheel,
+    brush::{Brush, BrushKind, Point},
+    colorwheel::{ColorWheel, Grip},
+    error::CanvasError,
+    event_log::{Event, EventLog},
+    export,
+    layers::{self, BlendMode, Layer, LayeredImage},
+    paper::Paper,
+    selection::Selection,
+    stroke::{Sample, Stroke},
+    svg,
+    symmetry::{Symmetry, SymmetryMode},
+    tool::Tool,
+    transform::{FloatingVertex, Resampling, Transform},
 };
 
-pub struct Canvas {
-    strokes: Vec<Point>,
-    colorwheel: ColorWheel,
-    surface: Surface,
-    _adapter: Adapter,
-    queue: Queue,
-    brush: Brush,
+const PAINT_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+const HEIGHT_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+const EXPORT_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeParams {
+    texel: [f32; 2],
+    lit: f32,
+    relief: f32,
+    paper_color: [f32; 4],
+    ants_phase: f32,
+    show_ants: f32,
+    paint_opacity: f32,
+    paper_opacity: f32,
+    /// whether a transparent paper shows a checkerboard instead of white.
+    checker: f32,
+    _padding: [f32; 3],
+}
+
+/// Offscreen textures the strokes are painted into, kept between frames.
+struct PaintTargets {
+    color_texture: Texture,
+    color: TextureView,
+    height_texture: Texture,
+    height: TextureView,
+    /// samples both textures with the composite bind group layout.
+    bind_group: BindGroup,
+}
+
+/// Paint target pixels read back to restore the painting when the device
+/// is lost, the strokes painted since are painted again on top.
+struct PaintBackup {
+    size: PhysicalSize,
+    color: Vec<u8>,
+    height: Vec<u8>,
+}
+
+impl PaintBackup {
+    /// Crops or extends the pixels to `size` at the top left, like the paint
+    /// targets when the window is resized.
+    fn resize(&mut self, size: PhysicalSize) {
+        let old_size = self.size;
+        let resize = |pixels: &[u8], bytes_per_pixel: usize| {
+            let old_row = old_size.width as usize * bytes_per_pixel;
+            let new_row = size.width as usize * bytes_per_pixel;
+            let kept = old_row.min(new_row);
+            let mut resized = vec![0; new_row * size.height as usize];
+            for (new, old) in resized
+                .chunks_exact_mut(new_row)
+                .zip(pixels.chunks_exact(old_row))
+            {
+                new[..kept].copy_from_slice(&old[..kept]);
+            }
+            resized
+        };
+        self.color = resize(&self.color, 4);
+        self.height = resize(&self.height, 1);
+        self.size = size;
+    }
+}
+
+/// Pipelines lifting, placing and committing floating pixels.
+struct TransformPipelines {
+    lift: RenderPipeline,
+    cut: RenderPipeline,
+    commit: RenderPipeline,
+    preview: RenderPipeline,
+}
+
+/// The device with the pipelines shared by the documents, and the surface
+/// they are presented to.
+struct GpuContext {
+    /// `None` for a headless canvas which only renders offscreen.
+    surface: Option<Surface>,
+    surface_config: SurfaceConfiguration,
+    adapter: Adapter,
     device: Device,
-    output_buffer: Buffer,
-    brush_down: bool,
+    queue: Queue,
+    /// first uncaptured error of the device, checked before each frame.
+    device_error: Arc<Mutex<Option<CanvasError>>>,
     paint_pipeline: RenderPipeline,
     colorwheel_pipeline: RenderPipeline,
-    surface_config: SurfaceConfiguration,
+    colorwheel_layout: BindGroupLayout,
+    colorwheel_buffer: Buffer,
+    colorwheel_bind_group: BindGroup,
+    composite_pipeline: RenderPipeline,
+    /// the composite pipeline for textures of [`EXPORT_FORMAT`].
+    export_pipeline: RenderPipeline,
+    guide_pipeline: RenderPipeline,
+    composite_layout: BindGroupLayout,
+    composite_buffer: Buffer,
+    sampler: Sampler,
+    paper_layout: BindGroupLayout,
+    paper_sampler: Sampler,
+    selection_layout: BindGroupLayout,
+    transform_pipelines: TransformPipelines,
+    /// set when the device is recreated, until an [`Overlay`] drew with the
+    /// new one.
+    recreated: bool,
+}
+
+/// Draws on top of the canvas in the same frame, e.g. the widgets of a user
+/// interface.
+pub trait Overlay {
+    fn draw(&mut self, frame: OverlayFrame<'_>);
+}
+
+/// The surface texture an [`Overlay`] draws into, already holding the
+/// canvas.
+pub struct OverlayFrame<'a> {
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+    /// submitted after the canvas passes.
+    pub encoder: &'a mut CommandEncoder,
+    pub view: &'a TextureView,
+    pub format: TextureFormat,
+    pub size: PhysicalSize,
+    /// whether the device was recreated since the last overlay, so anything
+    /// created with the old one has to be created again.
+    pub device_recreated: bool,
+}
+
+/// A painting with its own strokes, brush, selection and undo history.
+struct Document {
+    /// every stroke painted, kept as vector paths.
+    strokes: Vec<Stroke>,
+    brush: Brush,
+    backup: Option<PaintBackup>,
+    paint_targets: PaintTargets,
+    paper_bind_group: BindGroup,
+    paper: Paper,
+    symmetry: Symmetry,
+    tool: Tool,
+    selection: Selection,
+    selection_texture: Texture,
+    selection_bind_group: BindGroup,
+    /// where the current rectangle or ellipse selection started.
+    drag_start: Option<[f32; 2]>,
+    lasso: Vec<[f32; 2]>,
+    transform: Option<Transform>,
+    /// pixels lifted by the transform tool.
+    floating: PaintTargets,
+    /// number of samples of each stroke already painted into the paint
+    /// targets.
+    painted: Vec<usize>,
+    /// `painted` when the backup was saved.
+    backed_up: Vec<usize>,
+    /// first of the strokes (with their symmetry copies) the brush is
+    /// drawing.
+    open_strokes: Option<usize>,
+    /// first stroke of each group painted together, for undo.
+    stroke_groups: Vec<usize>,
+    /// of the paper and the paint, indexed by [`CanvasLayer`].
+    layer_styles: [LayerStyle; 2],
+    /// whether the paper or painting came from outside the event log, see
+    /// [`Event::Unrecorded`].
+    unrecorded: bool,
+}
+
+pub struct Canvas {
+    gpu: GpuContext,
+    /// the document shown and painted on.
+    document: Document,
+    /// the other open documents in order, without the shown one.
+    documents: Vec<Document>,
+    /// index of the shown document among all of them.
+    active_document: usize,
+    colorwheel: ColorWheel,
+    /// what the drag started on the color wheel does.
+    colorwheel_grip: Option<Grip>,
+    brush_down: bool,
+    /// where the mouse is in the window, `None` outside of it.
+    pointer: Option<[f32; 2]>,
+    ants_phase: u32,
     colorwheel_enabled: bool,
+    lighting_enabled: bool,
     buffer_dimensions: PhysicalSize,
+    /// inputs since the recording started.
+    recording: Option<(Instant, EventLog)>,
 }
 
 impl Canvas {
+    const MAGIC_WAND_TOLERANCE: u8 = 24;
+    /// the marching ants move one pixel per frame along 8 pixel dashes.
+    const ANTS_PERIOD: u32 = 8;
+    /// line segments approximating the brush outline.
+    const OUTLINE_SEGMENTS: usize = 48;
+    /// name of the paper in layered files, a bottom layer of that name is
+    /// opened as the paper.
+    const PAPER_LAYER_NAME: &'static str = "Paper";
+
+    /// Moves the mouse to `new_pos` in window pixels, returns whether the
+    /// window needs to be redrawn.
     pub fn mouse_at(&mut self, brush_down: bool, new_pos: [f32; 2]) -> bool {
+        self.record(Event::MouseAt {
+            brush_down,
+            pos: new_pos,
+        });
         let prev_brush_down = self.brush_down;
         self.brush_down = brush_down;
+        let moved = self.pointer.replace(new_pos) != Some(new_pos);
+        let outline_moved = moved && self.shows_brush_outline();
+        outline_moved | self.use_tool_at(brush_down, prev_brush_down, new_pos)
+    }
+
+    /// Hides the brush outline until the mouse moves into the window again.
+    pub const fn mouse_left(&mut self) {
+        self.pointer = None;
+    }
+
+    /// Whether the brush outline follows the mouse in place of the cursor.
+    pub const fn shows_brush_outline(&self) -> bool {
+        matches!(self.document.tool, Tool::Brush) && !self.colorwheel_enabled
+    }
+
+    fn use_tool_at(&mut self, brush_down: bool, prev_brush_down: bool, new_pos: [f32; 2]) -> bool {
         if self.colorwheel_enabled && brush_down && !prev_brush_down {
-            let width = Self::float_to_usize(new_pos[0]);
-            let height = Self::float_to_usize(new_pos[1]);
-            let buffer_slice = self.output_buffer.slice(..);
-            let mut color_set = false;
-            let (tx, rx) = futures::channel::oneshot::channel();
-            buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                tx.send(result).unwrap();
-            });
-            self.device.poll(wgpu::Maintain::Wait);
-            if futures::executor::block_on(async {rx.await}).is_ok() {
-                let padded_buffer = buffer_slice.get_mapped_range();
-                if let Some(padded_row) = padded_buffer
-                    .chunks(Self::padded_bytes_per_row(self.buffer_dimensions) as usize)
-                    .nth(height)
-                {
-                    if let Some(color_rgba) = padded_row.chunks(4).nth(width) {
-                        let blue = f32::from(color_rgba[0]) / 0xFF as f32;
-                        let green = f32::from(color_rgba[1]) / 0xFF as f32;
-                        let red = f32::from(color_rgba[2]) / 0xFF as f32;
-                        let color = [red, green, blue];
-                        self.colorwheel.set_color(color);
-                        self.brush.set_color(color);
-                        color_set = true;
+            self.colorwheel_grip = self.colorwheel.grip_at(new_pos);
+            if self.colorwheel_grip.is_none() {
+                return self.pick_color(new_pos);
+            }
+        }
+        if let Some(grip) = self.colorwheel_grip {
+            if brush_down {
+                self.drag_color_wheel(grip, new_pos);
+            } else {
+                self.colorwheel_grip = None;
+            }
+            true
+        } else {
+            match self.document.tool {
+                Tool::Brush => self.paint_at(brush_down, new_pos),
+                Tool::RectSelect | Tool::EllipseSelect => self.select_shape_at(brush_down, new_pos),
+                Tool::Lasso => self.lasso_at(brush_down, prev_brush_down, new_pos),
+                Tool::Transform => self.transform_at(brush_down, prev_brush_down, new_pos),
+                Tool::MagicWand => {
+                    let clicked = brush_down && !prev_brush_down;
+                    if !clicked {
+                        return false;
+                    }
+                    match self.render_image() {
+                        Ok(pixels) => {
+                            self.update_selection(|selection| {
+                                selection.select_color(
+                                    &pixels,
+                                    new_pos,
+                                    Self::MAGIC_WAND_TOLERANCE,
+                                );
+                            });
+                            true
+                        }
+                        Err(err) => {
+                            self.report_error(err);
+                            false
+                        }
                     }
                 }
             }
-            self.output_buffer.unmap();
-            color_set
-        } else if let Some(points) = self
+        }
+    }
+
+    /// Picks a color, moves or resizes the color wheel with `grip`.
+    fn drag_color_wheel(&mut self, grip: Grip, pos: [f32; 2]) {
+        if let Some(color) = self.colorwheel.drag(grip, pos) {
+            // the square keeps the hue it shows while a shade is picked.
+            if grip == Grip::Hue {
+                self.colorwheel.set_color(color);
+            }
+            self.document.brush.set_color(color);
+        }
+    }
+
+    /// Sets the brush color to the pixel of the painting under `pos`.
+    fn pick_color(&mut self, pos: [f32; 2]) -> bool {
+        let x = Self::float_to_usize(pos[0]);
+        let y = Self::float_to_usize(pos[1]);
+        let (width, height) = (
+            self.buffer_dimensions.width as usize,
+            self.buffer_dimensions.height as usize,
+        );
+        if x >= width || y >= height {
+            return false;
+        }
+        let pixels = match self.render_image() {
+            Ok(pixels) => pixels,
+            Err(err) => {
+                self.report_error(err);
+                return false;
+            }
+        };
+        let index = (y * width + x) * 4;
+        let color = [0, 1, 2]
+            .map(|channel| export::srgb_to_linear(f32::from(pixels[index + channel]) / 255.0));
+        self.colorwheel.set_color(color);
+        self.document.brush.set_color(color);
+        true
+    }
+
+    fn paint_at(&mut self, brush_down: bool, new_pos: [f32; 2]) -> bool {
+        let Some([from, to]) = self
+            .document
             .brush
             .draw_stroke(brush_down, self.colorwheel.get_canvas_pos(new_pos))
+        else {
+            if !brush_down {
+                self.document.open_strokes = None;
+            }
+            return false;
+        };
+        let froms = self.document.symmetry.map(from.pos);
+        let tos = self.document.symmetry.map(to.pos);
+        if let Some(first) = self
+            .document
+            .open_strokes
+            .filter(|&first| self.continues(first, &froms))
         {
-            self.strokes.extend_from_slice(&points);
+            for (stroke, pos) in self.document.strokes[first..].iter_mut().zip(tos) {
+                stroke.samples.push(Sample { pos, ..to });
+            }
+        } else {
+            // a new stroke each time the brush, symmetry or a gap changes it.
+            self.document.open_strokes = Some(self.document.strokes.len());
+            self.document
+                .stroke_groups
+                .push(self.document.strokes.len());
+            for (from_pos, to_pos) in froms.into_iter().zip(tos) {
+                let samples = vec![
+                    Sample {
+                        pos: from_pos,
+                        ..from
+                    },
+                    Sample { pos: to_pos, ..to },
+                ];
+                self.document
+                    .strokes
+                    .push(self.document.brush.stroke(samples));
+                self.document.painted.push(1);
+            }
+        }
+        true
+    }
+
+    /// Whether the strokes from `first` on end at `froms` with the current
+    /// brush, so a segment starting there extends them.
+    fn continues(&self, first: usize, froms: &[[f32; 2]]) -> bool {
+        let strokes = &self.document.strokes[first..];
+        strokes.len() == froms.len()
+            && strokes.iter().zip(froms).all(|(stroke, from)| {
+                self.document.brush.paints(stroke)
+                    && stroke.samples.last().is_some_and(|last| last.pos == *from)
+            })
+    }
+
+    /// Whether strokes were painted since the last backup.
+    fn painted_since_backup(&self) -> bool {
+        self.document
+            .strokes
+            .iter()
+            .map(|stroke| stroke.samples.len())
+            .ne(self.document.backed_up.iter().copied())
+    }
+
+    fn select_shape_at(&mut self, brush_down: bool, new_pos: [f32; 2]) -> bool {
+        let tool = self.document.tool;
+        match (brush_down, self.document.drag_start) {
+            (true, start) => {
+                let start = start.unwrap_or(new_pos);
+                self.document.drag_start = Some(start);
+                self.update_selection(|selection| {
+                    if tool == Tool::RectSelect {
+                        selection.select_rect(start, new_pos);
+                    } else {
+                        selection.select_ellipse(start, new_pos);
+                    }
+                });
+                true
+            }
+            (false, Some(start)) => {
+                self.document.drag_start = None;
+                // a click without dragging drops the selection.
+                if start == new_pos {
+                    self.update_selection(Selection::select_all);
+                }
+                true
+            }
+            (false, None) => false,
+        }
+    }
+
+    fn lasso_at(&mut self, brush_down: bool, prev_brush_down: bool, new_pos: [f32; 2]) -> bool {
+        if brush_down {
+            if self.document.lasso.last() != Some(&new_pos) {
+                self.document.lasso.push(new_pos);
+            }
+            true
+        } else if prev_brush_down {
+            let lasso = std::mem::take(&mut self.document.lasso);
+            self.update_selection(|selection| selection.select_lasso(&lasso));
             true
         } else {
             false
         }
     }
 
+    fn transform_at(&mut self, brush_down: bool, prev_brush_down: bool, new_pos: [f32; 2]) -> bool {
+        let Some(transform) = &mut self.document.transform else {
+            return false;
+        };
+        match (brush_down, prev_brush_down) {
+            (true, false) => transform.press(new_pos),
+            (true, true) => transform.drag_to(new_pos),
+            (false, true) => {
+                transform.release();
+                true
+            }
+            (false, false) => false,
+        }
+    }
+
+    /// Lifts the selected pixels (or everything) into the floating textures.
+    fn begin_transform(&mut self) {
+        if self.document.transform.is_some() {
+            return;
+        }
+        let Some((min, max)) = self.document.selection.bounds() else {
+            return;
+        };
+        self.flush_strokes();
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("lift encoder"),
+            });
+        for (targets, pipeline, bind_group) in [
+            (
+                &self.document.floating,
+                &self.gpu.transform_pipelines.lift,
+                &self.document.paint_targets.bind_group,
+            ),
+            (
+                &self.document.paint_targets,
+                &self.gpu.transform_pipelines.cut,
+                &self.document.floating.bind_group,
+            ),
+        ] {
+            let mut rpass = Self::begin_paint_targets_pass(&mut encoder, targets, "lift pass");
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.set_bind_group(1, &self.document.selection_bind_group, &[]);
+            rpass.draw(0..4, 0..1);
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+        self.document.transform = Some(Transform::new(min, max));
+    }
+
+    /// Removes the last strokes painted together, back to when the
+    /// selection, paper or window size last changed or pixels were placed
+    /// or imported. Returns whether there were strokes to remove.
+    pub fn undo(&mut self) -> bool {
+        self.record(Event::Undo);
+        let Some(&first) = self.document.stroke_groups.last() else {
+            return false;
+        };
+        if self.document.transform.is_some() || first < self.document.backed_up.len() {
+            return false;
+        }
+        self.document.stroke_groups.pop();
+        self.document.strokes.truncate(first);
+        self.document.painted.truncate(first);
+        self.document.open_strokes = None;
+        self.restore_backup();
+        true
+    }
+
+    /// Pastes the floating pixels back where they were moved to.
+    pub fn commit_transform(&mut self) {
+        self.record(Event::CommitTransform);
+        self.place_floating();
+    }
+
+    fn place_floating(&mut self) {
+        let Some(transform) = self.document.transform.take() else {
+            return;
+        };
+        let vertex_buffer = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("floating vertex buffer"),
+            contents: bytemuck::cast_slice(&transform.vertices(self.buffer_dimensions)),
+            usage: BufferUsages::VERTEX,
+        });
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("commit encoder"),
+            });
+        {
+            let mut rpass = Self::begin_paint_targets_pass(
+                &mut encoder,
+                &self.document.paint_targets,
+                "commit pass",
+            );
+            rpass.set_pipeline(&self.gpu.transform_pipelines.commit);
+            rpass.set_bind_group(0, &self.document.floating.bind_group, &[]);
+            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            rpass.draw(0..6, 0..1);
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+        self.save_backup();
+        // the selection no longer matches the moved pixels.
+        self.update_selection(Selection::select_all);
+    }
+
+    /// Puts the floating pixels back where they were lifted from, discards
+    /// pasted ones.
+    pub fn cancel_transform(&mut self) {
+        self.record(Event::CancelTransform);
+        if let Some(transform) = &mut self.document.transform {
+            if transform.is_pasted() {
+                self.document.transform = None;
+                return;
+            }
+            transform.reset();
+        }
+        self.place_floating();
+    }
+
+    pub fn resampling(&self) -> Option<Resampling> {
+        self.document.transform.as_ref().map(Transform::resampling)
+    }
+
+    pub fn set_resampling(&mut self, resampling: Resampling) {
+        self.record(Event::Resampling(resampling));
+        if let Some(transform) = &mut self.document.transform {
+            transform.set_resampling(resampling);
+        }
+    }
+
+    /// Paints the pending strokes with the old selection before changing it.
+    fn update_selection(&mut self, update: impl FnOnce(&mut Selection)) {
+        if self.painted_since_backup() {
+            self.save_backup();
+        } else {
+            self.flush_strokes();
+        }
+        update(&mut self.document.selection);
+        self.upload_selection();
+    }
+
+    pub const fn size(&self) -> PhysicalSize {
+        self.buffer_dimensions
+    }
+
+    pub const fn tool(&self) -> Tool {
+        self.document.tool
+    }
+
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.record(Event::Tool(tool));
+        if tool != Tool::Transform {
+            self.place_floating();
+        }
+        self.document.tool = tool;
+        self.document.drag_start = None;
+        self.document.lasso.clear();
+        if tool == Tool::Transform {
+            self.begin_transform();
+        }
+    }
+
+    pub const fn has_selection(&self) -> bool {
+        self.document.selection.is_active()
+    }
+
+    pub fn select_all(&mut self) {
+        self.record(Event::SelectAll);
+        self.update_selection(Selection::select_all);
+    }
+
+    pub fn invert_selection(&mut self) {
+        self.record(Event::InvertSelection);
+        self.update_selection(Selection::invert);
+    }
+
+    pub fn feather_selection(&mut self, radius: usize) {
+        self.record(Event::FeatherSelection(radius));
+        self.update_selection(|selection| selection.feather(radius));
+    }
+
+    pub fn grow_selection(&mut self, radius: usize) {
+        self.record(Event::GrowSelection(radius));
+        self.update_selection(|selection| selection.grow(radius));
+    }
+
+    pub fn shrink_selection(&mut self, radius: usize) {
+        self.record(Event::ShrinkSelection(radius));
+        self.update_selection(|selection| selection.shrink(radius));
+    }
+
     pub const fn is_color_wheel_enabled(&self) -> bool {
         self.colorwheel_enabled
     }
 
+    /// What dragging on the color wheel does under the mouse, or the drag
+    /// going on. `None` off the wheel or while it is hidden.
+    pub fn color_wheel_grip(&self) -> Option<Grip> {
+        if !self.colorwheel_enabled {
+            return None;
+        }
+        self.colorwheel_grip
+            .or_else(|| self.colorwheel.grip_at(self.pointer?))
+    }
+
     pub fn color_wheel_toggle(&mut self) {
+        self.record(Event::ColorWheelToggle);
         self.colorwheel_enabled = !self.colorwheel_enabled;
+        self.colorwheel_grip = None;
+    }
+
+    pub const fn is_lighting_enabled(&self) -> bool {
+        self.lighting_enabled
+    }
+
+    /// switch between flat color and lit paint.
+    pub fn lighting_toggle(&mut self) {
+        self.record(Event::LightingToggle);
+        self.lighting_enabled = !self.lighting_enabled;
+    }
+
+    pub const fn brush_kind(&self) -> BrushKind {
+        self.document.brush.kind()
+    }
+
+    pub fn set_brush_kind(&mut self, kind: BrushKind) {
+        self.record(Event::BrushKind(kind));
+        self.document.brush.set_kind(kind);
+    }
+
+    /// Stroke width as a fraction of the canvas width.
+    pub const fn brush_radius(&self) -> f32 {
+        self.document.brush.radius()
+    }
+
+    pub fn set_brush_radius(&mut self, radius: f32) {
+        self.record(Event::BrushRadius(radius));
+        self.document.brush.set_radius(radius);
+    }
+
+    pub const fn brush_opacity(&self) -> f32 {
+        self.document.brush.opacity()
+    }
+
+    pub fn set_brush_opacity(&mut self, opacity: f32) {
+        self.record(Event::BrushOpacity(opacity));
+        self.document.brush.set_opacity(opacity);
+    }
+
+    pub const fn brush_color(&self) -> [f32; 3] {
+        self.document.brush.color()
+    }
+
+    /// Sets the paint color, as picked with the color wheel.
+    pub fn set_brush_color(&mut self, color: [f32; 3]) {
+        self.record(Event::BrushColor(color));
+        self.colorwheel.set_color(color);
+        self.document.brush.set_color(color);
+    }
+
+    pub const fn layer_style(&self, layer: CanvasLayer) -> LayerStyle {
+        self.document.layer_styles[layer as usize]
+    }
+
+    pub fn set_layer_visible(&mut self, layer: CanvasLayer, visible: bool) {
+        self.record(Event::LayerVisible(layer, visible));
+        self.document.layer_styles[layer as usize].visible = visible;
+    }
+
+    pub fn set_layer_opacity(&mut self, layer: CanvasLayer, opacity: f32) {
+        self.record(Event::LayerOpacity(layer, opacity));
+        self.document.layer_styles[layer as usize].opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    pub fn set_pressure(&mut self, pressure: f32) {
+        self.record(Event::Pressure(pressure));
+        self.document.brush.set_pressure(pressure);
+    }
+
+    pub const fn symmetry_mode(&self) -> SymmetryMode {
+        self.document.symmetry.mode()
+    }
+
+    pub fn set_symmetry_mode(&mut self, mode: SymmetryMode) {
+        self.record(Event::SymmetryMode(mode));
+        self.document.symmetry.set_mode(mode);
+    }
+
+    /// Moves the symmetry center to a window position.
+    pub fn set_symmetry_center(&mut self, pos: [f32; 2]) {
+        self.record(Event::SymmetryCenter(pos));
+        self.document
+            .symmetry
+            .set_center(self.colorwheel.get_canvas_pos(pos));
+    }
+
+    /// Replaces the paper grain and background color, which event logs only
+    /// note.
+    pub fn set_paper(&mut self, paper: &Paper) {
+        self.record_unrecorded();
+        // strokes painted so far keep the old grain.
+        if self.painted_since_backup() {
+            self.save_backup();
+        }
+        self.document.paper_bind_group = Self::create_paper_bind_group(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.gpu.paper_layout,
+            &self.gpu.paper_sampler,
+            paper,
+        );
+        self.document.paper = paper.clone();
+    }
+
+    fn create_paper_sampler(device: &Device) -> Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("paper sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..wgpu::SamplerDescriptor::default()
+        })
+    }
+
+    fn create_canvas_sampler(device: &Device) -> Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("canvas sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..wgpu::SamplerDescriptor::default()
+        })
+    }
+
+    fn create_paper_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("paper bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    fn create_paper_bind_group(
+        device: &Device,
+        queue: &Queue,
+        paper_layout: &BindGroupLayout,
+        paper_sampler: &Sampler,
+        paper: &Paper,
+    ) -> BindGroup {
+        let size = paper.size();
+        let texture = device.create_texture_with_data(
+            queue,
+            &TextureDescriptor {
+                label: Some("paper texture"),
+                size: Extent3d {
+                    width: size.width,
+                    height: size.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::R8Unorm,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            paper.grain(),
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("paper bind group"),
+            layout: paper_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(paper_sampler),
+                },
+            ],
+        })
+    }
+
+    fn create_selection_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("selection bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    fn create_selection_texture(device: &Device, size: PhysicalSize) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some("selection texture"),
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+
+    fn create_selection_bind_group(
+        device: &Device,
+        selection_layout: &BindGroupLayout,
+        selection_texture: &Texture,
+    ) -> BindGroup {
+        let view = selection_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some("selection bind group"),
+            layout: selection_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&view),
+            }],
+        })
     }
 
     fn create_paint_pipeline(
         device: &Device,
-        surface_config: &SurfaceConfiguration,
+        paper_layout: &BindGroupLayout,
+        selection_layout: &BindGroupLayout,
     ) -> RenderPipeline {
         let paint_shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("paint shader"),
@@ -97,7 +947,7 @@ impl Canvas {
         });
         let paint_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("paint layout"),
-            bind_group_layouts: &[],
+            bind_group_layouts: &[paper_layout, selection_layout],
             push_constant_ranges: &[],
         });
         device.create_render_pipeline(&RenderPipelineDescriptor {
@@ -111,11 +961,26 @@ impl Canvas {
             fragment: Some(FragmentState {
                 module: &paint_shader,
                 entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: PAINT_FORMAT,
+                        blend: Some(BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    // paint thickness piles up where strokes overlap.
+                    Some(wgpu::ColorTargetState {
+                        format: HEIGHT_FORMAT,
+                        blend: Some(BlendState {
+                            color: BlendComponent {
+                                src_factor: BlendFactor::One,
+                                dst_factor: BlendFactor::One,
+                                operation: BlendOperation::Add,
+                            },
+                            alpha: BlendComponent::REPLACE,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
             }),
             primitive: PrimitiveState {
                 topology: PrimitiveTopology::TriangleList,
@@ -127,26 +992,62 @@ impl Canvas {
         })
     }
 
-    fn create_colorwheel_pipeline(
+    /// The color wheel parameters are a uniform buffer rather than push
+    /// constants, which WebGL and many downlevel adapters lack.
+    fn create_colorwheel_layout(device: &Device) -> BindGroupLayout {
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("color wheel bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    fn create_colorwheel_bind_group(
         device: &Device,
-        surface_config: &SurfaceConfiguration,
-    ) -> RenderPipeline {
-        let colorwheel_shader = device.create_shader_module(ShaderModuleDescriptor {
+        colorwheel_layout: &BindGroupLayout,
+    ) -> (Buffer, BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("color wheel buffer"),
+            size: std::mem::size_of::<ColorWheel>() as wgpu::BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("color wheel bind group"),
+            layout: colorwheel_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    fn create_colorwheel_pipeline(
+        device: &Device,
+        colorwheel_layout: &BindGroupLayout,
+        format: TextureFormat,
+    ) -> RenderPipeline {
+        let colorwheel_shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("color wheel shader"),
             source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("colorwheel.wgsl"))),
         });
-        let colorwheel = PushConstantRange {
-            stages: ShaderStages::FRAGMENT,
-            range: 0..std::mem::size_of::<ColorWheel>() as u32,
-        };
-        let colorwheel_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("color wheel layout"),
-            bind_group_layouts: &[],
-            push_constant_ranges: &[colorwheel],
+            bind_group_layouts: &[colorwheel_layout],
+            push_constant_ranges: &[],
         });
         device.create_render_pipeline(&RenderPipelineDescriptor {
             label: Some("color wheel pipeline"),
-            layout: Some(&colorwheel_layout),
+            layout: Some(&layout),
             vertex: VertexState {
                 module: &colorwheel_shader,
                 entry_point: "vs_main",
@@ -156,7 +1057,7 @@ impl Canvas {
                 module: &colorwheel_shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
+                    format,
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -171,164 +1072,1587 @@ impl Canvas {
         })
     }
 
-    pub fn new(
+    fn create_guide_pipeline(
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+    ) -> RenderPipeline {
+        let guide_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("guide shader"),
+            source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("guide.wgsl"))),
+        });
+        let guide_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("guide layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("guide pipeline"),
+            layout: Some(&guide_layout),
+            vertex: VertexState {
+                module: &guide_shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &guide_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_config.format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineList,
+                ..PrimitiveState::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_transform_pipelines(
+        device: &Device,
+        composite_layout: &BindGroupLayout,
+        selection_layout: &BindGroupLayout,
+        surface_format: TextureFormat,
+    ) -> TransformPipelines {
+        let transform_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("transform shader"),
+            source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("transform.wgsl"))),
+        });
+        let masked_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("masked transform layout"),
+            bind_group_layouts: &[composite_layout, selection_layout],
+            push_constant_ranges: &[],
+        });
+        let floating_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("floating transform layout"),
+            bind_group_layouts: &[composite_layout],
+            push_constant_ranges: &[],
+        });
+        // scales the destination down by the cut coverage.
+        let erase = BlendComponent {
+            src_factor: BlendFactor::Zero,
+            dst_factor: BlendFactor::OneMinusSrcAlpha,
+            operation: BlendOperation::Add,
+        };
+        let erase = BlendState {
+            color: erase,
+            alpha: erase,
+        };
+        let paint_targets = |blend| {
+            [PAINT_FORMAT, HEIGHT_FORMAT].map(|format| {
+                Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })
+            })
+        };
+        let floating_buffers = [FloatingVertex::desc()];
+        let pipeline = |label, layout, floating: bool, entry_point, targets: &[_]| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: VertexState {
+                    module: &transform_shader,
+                    entry_point: if floating {
+                        "vs_floating"
+                    } else {
+                        "vs_fullscreen"
+                    },
+                    buffers: if floating { &floating_buffers } else { &[] },
+                },
+                fragment: Some(FragmentState {
+                    module: &transform_shader,
+                    entry_point,
+                    targets,
+                }),
+                primitive: PrimitiveState {
+                    topology: if floating {
+                        PrimitiveTopology::TriangleList
+                    } else {
+                        PrimitiveTopology::TriangleStrip
+                    },
+                    ..PrimitiveState::default()
+                },
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        TransformPipelines {
+            lift: pipeline(
+                "lift pipeline",
+                &masked_layout,
+                false,
+                "fs_lift",
+                &paint_targets(BlendState::REPLACE),
+            ),
+            cut: pipeline(
+                "cut pipeline",
+                &masked_layout,
+                false,
+                "fs_cut",
+                &paint_targets(erase),
+            ),
+            commit: pipeline(
+                "commit pipeline",
+                &floating_layout,
+                true,
+                "fs_commit",
+                &paint_targets(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            ),
+            preview: pipeline(
+                "floating preview pipeline",
+                &floating_layout,
+                true,
+                "fs_preview",
+                &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            ),
+        }
+    }
+
+    fn create_composite_layout(device: &Device) -> BindGroupLayout {
+        let texture = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("composite bind group layout"),
+            entries: &[
+                texture(0),
+                texture(1),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    fn create_composite_pipeline(
+        device: &Device,
+        composite_layout: &BindGroupLayout,
+        paper_layout: &BindGroupLayout,
+        selection_layout: &BindGroupLayout,
+        format: TextureFormat,
+    ) -> RenderPipeline {
+        let composite_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("composite shader"),
+            source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("composite.wgsl"))),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("composite layout"),
+            bind_group_layouts: &[composite_layout, paper_layout, selection_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("composite pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &composite_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &composite_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                ..PrimitiveState::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    fn create_texture(
+        device: &Device,
+        size: PhysicalSize,
+        format: TextureFormat,
+        label: &str,
+    ) -> Texture {
+        device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+
+    fn create_paint_targets(
+        device: &Device,
+        size: PhysicalSize,
+        composite_layout: &BindGroupLayout,
+        sampler: &Sampler,
+        composite_buffer: &Buffer,
+    ) -> PaintTargets {
+        let color_texture = Self::create_texture(device, size, PAINT_FORMAT, "paint texture");
+        let height_texture = Self::create_texture(device, size, HEIGHT_FORMAT, "height texture");
+        let color = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let height = height_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("composite bind group"),
+            layout: composite_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&color),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&height),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: composite_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        PaintTargets {
+            color_texture,
+            color,
+            height_texture,
+            height,
+            bind_group,
+        }
+    }
+
+    fn create_surface_config(
+        surface: &Surface,
+        adapter: &Adapter,
         window_size: PhysicalSize,
-        surface: Surface,
-        device: Device,
-        adapter: Adapter,
-        queue: Queue,
-    ) -> Self {
-        let surface_caps = surface.get_capabilities(&adapter);
+    ) -> Result<SurfaceConfiguration, CanvasError> {
+        let surface_caps = surface.get_capabilities(adapter);
         let texture_format = surface_caps
             .formats
             .iter()
             .copied()
             .find(|f| f.describe().srgb)
-            .unwrap_or(surface_caps.formats[0]);
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            .or_else(|| surface_caps.formats.first().copied())
+            .ok_or(CanvasError::IncompatibleSurface)?;
+        Ok(SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: texture_format,
             width: window_size.width,
             height: window_size.height,
             present_mode: surface_caps.present_modes[0],
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
+        })
+    }
+
+    fn create_composite_buffer(device: &Device) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("composite buffer"),
+            size: std::mem::size_of::<CompositeParams>() as wgpu::BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Requests a device with the features and limits the canvas needs,
+    /// which WebGL2 and downlevel adapters provide.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the adapter cannot create the device.
+    pub async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), CanvasError> {
+        let descriptor = DeviceDescriptor {
+            label: None,
+            features: Features::empty(),
+            limits: Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
         };
-        let buffer_dimensions = window_size;
+        Ok(adapter.request_device(&descriptor, None).await?)
+    }
+
+    /// Collects the first uncaptured device error instead of panicking.
+    fn capture_device_errors(device: &Device) -> Arc<Mutex<Option<CanvasError>>> {
+        let device_error = Arc::new(Mutex::new(None));
+        let sink = Arc::clone(&device_error);
+        device.on_uncaptured_error(Box::new(move |error| {
+            if let Ok(mut sink) = sink.lock() {
+                sink.get_or_insert_with(|| CanvasError::from_device_error(&error));
+            }
+        }));
+        device_error
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the surface cannot be presented with the adapter.
+    pub fn new(
+        window_size: PhysicalSize,
+        surface: Surface,
+        device: Device,
+        adapter: Adapter,
+        queue: Queue,
+    ) -> Result<Self, CanvasError> {
+        let surface_config = Self::create_surface_config(&surface, &adapter, window_size)?;
         surface.configure(&device, &surface_config);
-        let paint_pipeline = Self::create_paint_pipeline(&device, &surface_config);
-        let colorwheel_pipeline = Self::create_colorwheel_pipeline(&device, &surface_config);
-        let output_buffer = Self::create_output_buffer(&device, buffer_dimensions);
-        Self {
-            surface,
+        Ok(Self::create(
+            window_size,
+            Some(surface),
             surface_config,
             device,
-            paint_pipeline,
-            strokes: vec![],
+            adapter,
             queue,
-            colorwheel_enabled: false,
+        ))
+    }
+
+    /// A canvas without a window, for replaying and exporting paintings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no adapter or device is available.
+    pub async fn headless(size: PhysicalSize) -> Result<Self, CanvasError> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .ok_or(CanvasError::NoAdapter)?;
+        let (device, queue) = Self::request_device(&adapter).await?;
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: EXPORT_FORMAT,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        Ok(Self::create(
+            size,
+            None,
+            surface_config,
+            device,
+            adapter,
+            queue,
+        ))
+    }
+
+    fn create(
+        window_size: PhysicalSize,
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+        device: Device,
+        adapter: Adapter,
+        queue: Queue,
+    ) -> Self {
+        let gpu = GpuContext::new(surface, surface_config, adapter, device, queue);
+        let document = Document::new(&gpu, window_size, Paper::default(), Brush::default());
+        let canvas = Self {
+            gpu,
+            document,
+            documents: vec![],
+            active_document: 0,
+            colorwheel: ColorWheel::new(window_size),
+            colorwheel_grip: None,
             brush_down: false,
-            colorwheel_pipeline,
-            _adapter: adapter,
-            buffer_dimensions,
-            output_buffer,
-            colorwheel: ColorWheel::default(),
-            brush: Brush::default(),
+            pointer: None,
+            ants_phase: 0,
+            colorwheel_enabled: false,
+            lighting_enabled: false,
+            buffer_dimensions: window_size,
+            recording: None,
+        };
+        canvas.upload_selection();
+        canvas
+    }
+
+    /// Number of open documents, at least one.
+    pub const fn document_count(&self) -> usize {
+        self.documents.len() + 1
+    }
+
+    /// Index of the shown document.
+    pub const fn active_document(&self) -> usize {
+        self.active_document
+    }
+
+    /// Opens a blank document on the same paper after the others and shows
+    /// it, the brush keeps its color, size, kind and opacity.
+    pub fn new_document(&mut self) {
+        self.record(Event::NewDocument);
+        self.leave_document();
+        let mut brush = Brush::default();
+        brush.set_color(self.document.brush.color());
+        brush.set_radius(self.document.brush.radius());
+        brush.set_kind(self.document.brush.kind());
+        brush.set_opacity(self.document.brush.opacity());
+        let document = Document::new(
+            &self.gpu,
+            self.buffer_dimensions,
+            self.document.paper.clone(),
+            brush,
+        );
+        let previous = std::mem::replace(&mut self.document, document);
+        self.documents.push(previous);
+        self.active_document = self.documents.len();
+        self.upload_selection();
+    }
+
+    /// Shows the document at `index`, returns whether there is one.
+    pub fn switch_document(&mut self, index: usize) -> bool {
+        if index == self.active_document || index > self.documents.len() {
+            return false;
         }
+        self.record(Event::Document(index));
+        self.leave_document();
+        let active = self.active_document;
+        let document = self
+            .documents
+            .remove(if index < active { index } else { index - 1 });
+        let previous = std::mem::replace(&mut self.document, document);
+        self.documents
+            .insert(if index < active { active - 1 } else { active }, previous);
+        self.active_document = index;
+        self.colorwheel.set_color(self.document.brush.color());
+        true
     }
 
-    pub fn redraw_canvas(&mut self) {
-        let vertex_buffer = self.device.create_buffer_init(&BufferInitDescriptor {
+    /// Closes the shown document and shows the next one, or the previous
+    /// one for the last. Closing the only document leaves a blank one.
+    pub fn close_document(&mut self) {
+        self.record(Event::CloseDocument);
+        if self.documents.is_empty() {
+            let brush = std::mem::take(&mut self.document.brush);
+            let paper = self.document.paper.clone();
+            self.document = Document::new(&self.gpu, self.buffer_dimensions, paper, brush);
+            self.document.brush.lift();
+            self.upload_selection();
+            return;
+        }
+        if self.active_document == self.documents.len() {
+            self.active_document -= 1;
+        }
+        self.document = self.documents.remove(self.active_document);
+        self.colorwheel.set_color(self.document.brush.color());
+    }
+
+    /// Ends the strokes and drags of the shown document before another one
+    /// is shown, a transform stays floating.
+    fn leave_document(&mut self) {
+        self.flush_strokes();
+        self.document.brush.lift();
+        self.document.open_strokes = None;
+        self.document.drag_start = None;
+        self.document.lasso.clear();
+    }
+
+    /// Starts logging every input, from the current canvas size on.
+    pub fn start_recording(&mut self) {
+        let mut log = EventLog::new(self.buffer_dimensions);
+        let mut documents = std::iter::once(&self.document).chain(&self.documents);
+        if documents.any(|document| document.unrecorded) {
+            log.push(Duration::ZERO, Event::Unrecorded);
+        }
+        self.recording = Some((Instant::now(), log));
+    }
+
+    /// The inputs since [`start_recording`](Self::start_recording).
+    pub fn stop_recording(&mut self) -> Option<EventLog> {
+        self.recording.take().map(|(_, log)| log)
+    }
+
+    fn record(&mut self, event: Event) {
+        if let Some((start, log)) = &mut self.recording {
+            log.push(start.elapsed(), event);
+        }
+    }
+
+    /// Notes a change event logs cannot hold, e.g. an opened file.
+    fn record_unrecorded(&mut self) {
+        self.document.unrecorded = true;
+        self.record(Event::Unrecorded);
+    }
+
+    /// Feeds a recorded input through the same path as the live one,
+    /// returns whether the canvas needs to be redrawn.
+    pub fn apply(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::MouseAt { brush_down, pos } => return self.mouse_at(brush_down, pos),
+            Event::Pressure(pressure) => self.set_pressure(pressure),
+            Event::IncBrushSize => self.inc_brush_size(),
+            Event::DecBrushSize => self.dec_brush_size(),
+            Event::BrushRadius(radius) => self.set_brush_radius(radius),
+            Event::BrushColor(color) => self.set_brush_color(color),
+            Event::BrushKind(kind) => self.set_brush_kind(kind),
+            Event::BrushOpacity(opacity) => self.set_brush_opacity(opacity),
+            Event::ColorWheelToggle => self.color_wheel_toggle(),
+            Event::LightingToggle => self.lighting_toggle(),
+            Event::Tool(tool) => self.set_tool(tool),
+            Event::CommitTransform => self.commit_transform(),
+            Event::CancelTransform => self.cancel_transform(),
+            Event::Undo => return self.undo(),
+            Event::Resampling(resampling) => self.set_resampling(resampling),
+            Event::SelectAll => self.select_all(),
+            Event::InvertSelection => self.invert_selection(),
+            Event::FeatherSelection(radius) => self.feather_selection(radius),
+            Event::GrowSelection(radius) => self.grow_selection(radius),
+            Event::ShrinkSelection(radius) => self.shrink_selection(radius),
+            Event::SymmetryMode(mode) => self.set_symmetry_mode(mode),
+            Event::SymmetryCenter(pos) => self.set_symmetry_center(pos),
+            Event::Resize(size) => self.resize_window(size),
+            Event::NewDocument => self.new_document(),
+            Event::CloseDocument => self.close_document(),
+            Event::Document(index) => return self.switch_document(index),
+            Event::LayerVisible(layer, visible) => self.set_layer_visible(layer, visible),
+            Event::LayerOpacity(layer, opacity) => self.set_layer_opacity(layer, opacity),
+            Event::Unrecorded => return false,
+        }
+        true
+    }
+
+    /// Replays a whole log at once, on a fresh canvas this paints the
+    /// recorded picture.
+    pub fn replay(&mut self, log: &EventLog) {
+        if log.size() != self.size() {
+            self.resize_window(log.size());
+        }
+        for timed in log.events() {
+            self.apply(&timed.event);
+        }
+    }
+
+    fn write_colorwheel_params(&self) {
+        self.gpu.queue.write_buffer(
+            &self.gpu.colorwheel_buffer,
+            0,
+            bytemuck::bytes_of(&self.colorwheel),
+        );
+    }
+
+    /// `size` of the paint targets being composited, `on_screen` shows the
+    /// selection and a checkerboard below a transparent paper.
+    fn write_composite_params(&self, size: PhysicalSize, on_screen: bool) {
+        let paint_opacity = self.layer_style(CanvasLayer::Paint).shown_opacity();
+        let params = CompositeParams {
+            texel: [1.0 / size.width as f32, 1.0 / size.height as f32],
+            lit: if self.lighting_enabled { 1.0 } else { 0.0 },
+            // the thickness fades with the paint.
+            relief: 8.0 * paint_opacity,
+            paper_color: [
+                self.document.paper.color()[0],
+                self.document.paper.color()[1],
+                self.document.paper.color()[2],
+                1.0,
+            ],
+            ants_phase: self.ants_phase as f32,
+            show_ants: if on_screen && self.document.selection.is_active() {
+                1.0
+            } else {
+                0.0
+            },
+            paint_opacity,
+            paper_opacity: self.layer_style(CanvasLayer::Paper).shown_opacity(),
+            checker: if on_screen { 1.0 } else { 0.0 },
+            _padding: [0.0; 3],
+        };
+        self.gpu
+            .queue
+            .write_buffer(&self.gpu.composite_buffer, 0, bytemuck::bytes_of(&params));
+    }
+
+    fn paper_clear_color(&self) -> Color {
+        let [r, g, b] = self.document.paper.color().map(f64::from);
+        Color { r, g, b, a: 1.0 }
+    }
+
+    fn upload_selection(&self) {
+        Self::write_selection(
+            &self.gpu.queue,
+            &self.document.selection_texture,
+            &self.document.selection,
+        );
+    }
+
+    fn write_selection(queue: &Queue, texture: &Texture, selection: &Selection) {
+        let size = selection.size();
+        queue.write_texture(
+            texture.as_image_copy(),
+            selection.mask(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(size.width),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Paints the strokes added since the last paint pass, clipped to the
+    /// current selection.
+    fn encode_paint_pass(&mut self, encoder: &mut CommandEncoder) {
+        let mut points = vec![];
+        for (stroke, painted) in self.document.strokes.iter().zip(&mut self.document.painted) {
+            if stroke.samples.len() > *painted {
+                points.extend(stroke.tessellate(*painted));
+                *painted = stroke.samples.len();
+            }
+        }
+        self.encode_strokes(
+            encoder,
+            &points,
+            &self.document.paint_targets,
+            &self.document.selection_bind_group,
+        );
+    }
+
+    /// Paints tessellated strokes into `targets`, clipped to `selection`.
+    fn encode_strokes(
+        &self,
+        encoder: &mut CommandEncoder,
+        points: &[Point],
+        targets: &PaintTargets,
+        selection: &BindGroup,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        let vertex_buffer = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
             label: Some("vertex buffer"),
-            contents: bytemuck::cast_slice(&self.strokes),
+            contents: bytemuck::cast_slice(points),
             usage: BufferUsages::VERTEX,
         });
-        let output_texture = loop {
-            match self.surface.get_current_texture() {
-                // output texture
-                Ok(texture) => break texture,
-                // retry
-                Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Timeout) => {}
-                err => {
-                    panic!("Failed to get texture for rendering: {err:?}")
-                }
+        let mut rpass = Self::begin_paint_targets_pass(encoder, targets, "paint pass");
+        rpass.set_pipeline(&self.gpu.paint_pipeline);
+        rpass.set_bind_group(0, &self.document.paper_bind_group, &[]);
+        rpass.set_bind_group(1, selection, &[]);
+        rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        rpass.draw(0..points.len() as u32, 0..1);
+    }
+
+    /// Render pass drawing on top of the paint (or floating) textures.
+    fn begin_paint_targets_pass<'a>(
+        encoder: &'a mut CommandEncoder,
+        targets: &'a PaintTargets,
+        label: &str,
+    ) -> RenderPass<'a> {
+        let attachment = |view| {
+            Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })
+        };
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[attachment(&targets.color), attachment(&targets.height)],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    fn flush_strokes(&mut self) {
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("flush encoder"),
+            });
+        self.encode_paint_pass(&mut encoder);
+        self.gpu.queue.submit(Some(encoder.finish()));
+    }
+
+    fn draw_composite<'a>(
+        &'a self,
+        rpass: &mut RenderPass<'a>,
+        pipeline: &'a RenderPipeline,
+        targets: &'a PaintTargets,
+        selection: &'a BindGroup,
+    ) {
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, &targets.bind_group, &[]);
+        rpass.set_bind_group(1, &self.document.paper_bind_group, &[]);
+        rpass.set_bind_group(2, selection, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+
+    /// Guide lines of the symmetry axes and the lasso being drawn.
+    fn guide_lines(&self) -> Vec<[f32; 2]> {
+        let mut guide_lines = self.document.symmetry.guide_lines();
+        let lasso: Vec<_> = self
+            .document
+            .lasso
+            .iter()
+            .map(|&pos| self.colorwheel.get_canvas_pos(pos))
+            .collect();
+        guide_lines.extend(lasso.windows(2).flatten());
+        if let Some(transform) = &self.document.transform {
+            guide_lines.extend(
+                transform
+                    .outline()
+                    .into_iter()
+                    .map(|pos| self.colorwheel.get_canvas_pos(pos)),
+            );
+        }
+        if let Some(pointer) = self.pointer.filter(|_| self.shows_brush_outline()) {
+            let center = self.colorwheel.get_canvas_pos(pointer);
+            for center in self.document.symmetry.map(center) {
+                guide_lines.extend(self.brush_outline(center));
             }
+        }
+        guide_lines
+    }
+
+    /// A circle as wide as the strokes of the brush, as line segments.
+    fn brush_outline(&self, center: [f32; 2]) -> Vec<[f32; 2]> {
+        let radius = self.document.brush.radius() / 2.0;
+        let point = |index: usize| {
+            let angle = std::f32::consts::TAU * index as f32 / Self::OUTLINE_SEGMENTS as f32;
+            [
+                radius.mul_add(angle.cos(), center[0]),
+                radius.mul_add(angle.sin(), center[1]),
+            ]
+        };
+        (0..Self::OUTLINE_SEGMENTS)
+            .flat_map(|index| [point(index), point(index + 1)])
+            .collect()
+    }
+
+    /// Draws the painting with its guides and the color wheel to the surface.
+    ///
+    /// A lost or outdated surface is reconfigured and a lost device recreated
+    /// with the painting restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame cannot be drawn, see [`CanvasError::is_fatal`].
+    pub fn redraw_canvas(&mut self) -> Result<(), CanvasError> {
+        self.redraw(None)
+    }
+
+    /// Draws like [`Self::redraw_canvas`], with `overlay` on top.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame cannot be drawn, see [`CanvasError::is_fatal`].
+    pub fn redraw_with_overlay(&mut self, overlay: &mut dyn Overlay) -> Result<(), CanvasError> {
+        self.redraw(Some(overlay))
+    }
+
+    fn redraw(&mut self, overlay: Option<&mut dyn Overlay>) -> Result<(), CanvasError> {
+        self.recover_device()?;
+        let Some(output_texture) = self.current_texture()? else {
+            return Ok(());
         };
+        let guide_lines = self.guide_lines();
+        let guide_buffer = self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("guide buffer"),
+            contents: bytemuck::cast_slice(&guide_lines),
+            usage: BufferUsages::VERTEX,
+        });
+        let floating_buffer = self.document.transform.as_ref().map(|transform| {
+            self.gpu.device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("floating vertex buffer"),
+                contents: bytemuck::cast_slice(&transform.vertices(self.buffer_dimensions)),
+                usage: BufferUsages::VERTEX,
+            })
+        });
+        self.ants_phase = (self.ants_phase + 1) % Self::ANTS_PERIOD;
+        self.write_composite_params(self.buffer_dimensions, true);
+        self.write_colorwheel_params();
         let mut encoder = self
+            .gpu
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("paint encoder"),
             });
+        self.encode_paint_pass(&mut encoder);
+        let view = output_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
         {
-            let view = output_texture
-                .texture
-                .create_view(&wgpu::TextureViewDescriptor::default());
             let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("render pass"),
                 color_attachments: &[Some(RenderPassColorAttachment {
                     view: &view,
                     resolve_target: None,
                     ops: Operations {
-                        load: LoadOp::Clear(Color::WHITE),
+                        load: LoadOp::Clear(self.paper_clear_color()),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
-            rpass.set_pipeline(&self.paint_pipeline);
-            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            rpass.draw(0..self.strokes.len() as u32, 0..1);
+            self.draw_composite(
+                &mut rpass,
+                &self.gpu.composite_pipeline,
+                &self.document.paint_targets,
+                &self.document.selection_bind_group,
+            );
+            if let Some(floating_buffer) = &floating_buffer {
+                rpass.set_pipeline(&self.gpu.transform_pipelines.preview);
+                rpass.set_bind_group(0, &self.document.floating.bind_group, &[]);
+                rpass.set_vertex_buffer(0, floating_buffer.slice(..));
+                rpass.draw(0..6, 0..1);
+            }
+            if !guide_lines.is_empty() {
+                rpass.set_pipeline(&self.gpu.guide_pipeline);
+                rpass.set_vertex_buffer(0, guide_buffer.slice(..));
+                rpass.draw(0..guide_lines.len() as u32, 0..1);
+            }
             if self.colorwheel_enabled {
-                rpass.set_pipeline(&self.colorwheel_pipeline);
-                rpass.set_push_constants(
-                    ShaderStages::FRAGMENT,
-                    0,
-                    bytemuck::bytes_of(&self.colorwheel),
-                );
+                rpass.set_pipeline(&self.gpu.colorwheel_pipeline);
+                rpass.set_bind_group(0, &self.gpu.colorwheel_bind_group, &[]);
                 rpass.draw(0..4, 0..1);
             }
         }
+        if let Some(overlay) = overlay {
+            overlay.draw(OverlayFrame {
+                device: &self.gpu.device,
+                queue: &self.gpu.queue,
+                encoder: &mut encoder,
+                view: &view,
+                format: self.gpu.surface_config.format,
+                size: PhysicalSize {
+                    width: self.gpu.surface_config.width,
+                    height: self.gpu.surface_config.height,
+                },
+                device_recreated: self.gpu.recreated,
+            });
+            self.gpu.recreated = false;
+        }
 
-        encoder.copy_texture_to_buffer(
-            wgpu::ImageCopyTexture {
-                aspect: wgpu::TextureAspect::All,
-                texture: &output_texture.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            wgpu::ImageCopyBuffer {
-                buffer: &self.output_buffer,
-                layout: wgpu::ImageDataLayout {
+        self.gpu.queue.submit(Some(encoder.finish()));
+        output_texture.present();
+        Ok(())
+    }
+
+    /// The next surface texture, `None` skips the frame.
+    fn current_texture(&self) -> Result<Option<SurfaceTexture>, CanvasError> {
+        let Some(surface) = &self.gpu.surface else {
+            return Ok(None);
+        };
+        match surface.get_current_texture() {
+            Ok(texture) => Ok(Some(texture)),
+            Err(wgpu::SurfaceError::Timeout) => Ok(None),
+            Err(wgpu::SurfaceError::OutOfMemory) => Err(CanvasError::OutOfMemory),
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                surface.configure(&self.gpu.device, &self.gpu.surface_config);
+                match surface.get_current_texture() {
+                    Ok(texture) => Ok(Some(texture)),
+                    // e.g. a minimized window, try again next frame.
+                    Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Timeout) => Ok(None),
+                    Err(err) => Err(CanvasError::Surface(err)),
+                }
+            }
+        }
+    }
+
+    /// Recreates a lost device, other device errors are returned once.
+    fn recover_device(&mut self) -> Result<(), CanvasError> {
+        let device_error = self
+            .gpu
+            .device_error
+            .lock()
+            .ok()
+            .and_then(|mut device_error| device_error.take());
+        match device_error {
+            None => Ok(()),
+            Some(CanvasError::DeviceLost) => self.recreate_device(),
+            Some(err) => Err(err),
+        }
+    }
+
+    /// Replaces the device and everything created with it, the paintings are
+    /// restored from their last backup and the strokes since.
+    fn recreate_device(&mut self) -> Result<(), CanvasError> {
+        self.gpu.recreate_device()?;
+        self.for_each_document(|canvas| {
+            let size = canvas.buffer_dimensions;
+            let gpu = &canvas.gpu;
+            let document = &mut canvas.document;
+            document.paper_bind_group = Self::create_paper_bind_group(
+                &gpu.device,
+                &gpu.queue,
+                &gpu.paper_layout,
+                &gpu.paper_sampler,
+                &document.paper,
+            );
+            document.selection_texture = Self::create_selection_texture(&gpu.device, size);
+            document.selection_bind_group = Self::create_selection_bind_group(
+                &gpu.device,
+                &gpu.selection_layout,
+                &document.selection_texture,
+            );
+            document.paint_targets = gpu.create_paint_targets(size);
+            document.floating = gpu.create_paint_targets(size);
+            // the lifted pixels are in the backup at their old place.
+            document.transform = None;
+            canvas.upload_selection();
+            canvas.restore_backup();
+        });
+        Ok(())
+    }
+
+    /// Runs `update` with each document in turn as the shown one, the
+    /// shown one last.
+    fn for_each_document(&mut self, mut update: impl FnMut(&mut Self)) {
+        for index in 0..self.documents.len() {
+            std::mem::swap(&mut self.document, &mut self.documents[index]);
+            update(self);
+            std::mem::swap(&mut self.document, &mut self.documents[index]);
+        }
+        update(self);
+    }
+
+    /// Reads back the paint targets so the strokes painted so far are not
+    /// painted again when restoring. When they cannot be read the last
+    /// backup is kept, and the error returned by the next redraw.
+    fn save_backup(&mut self) {
+        self.flush_strokes();
+        let size = self.buffer_dimensions;
+        let targets = &self.document.paint_targets;
+        let read = |texture, bytes_per_pixel| {
+            export::read_texture(
+                &self.gpu.device,
+                &self.gpu.queue,
+                texture,
+                size,
+                bytes_per_pixel,
+            )
+        };
+        match (
+            read(&targets.color_texture, 4),
+            read(&targets.height_texture, 1),
+        ) {
+            (Ok(color), Ok(height)) => {
+                self.document.backup = Some(PaintBackup {
+                    size,
+                    color,
+                    height,
+                });
+                self.document.backed_up.clone_from(&self.document.painted);
+            }
+            (Err(err), _) | (_, Err(err)) => self.report_error(err),
+        }
+    }
+
+    /// Keeps `err` of a change that cannot return it, for the next redraw to
+    /// return (or recover from a lost device).
+    fn report_error(&self, err: CanvasError) {
+        if let Ok(mut device_error) = self.gpu.device_error.lock() {
+            device_error.get_or_insert(err);
+        }
+    }
+
+    /// Uploads the last backup (or a blank painting without one), the
+    /// strokes painted since are painted again with the next frame.
+    fn restore_backup(&mut self) {
+        self.document.painted = (0..self.document.strokes.len())
+            .map(|index| self.document.backed_up.get(index).copied().unwrap_or(1))
+            .collect();
+        let Some(backup) = &self.document.backup else {
+            let size = self.buffer_dimensions;
+            let pixels = size.width as usize * size.height as usize;
+            self.write_paint_targets(
+                &self.document.paint_targets,
+                &vec![0; pixels * 4],
+                &vec![0; pixels],
+            );
+            return;
+        };
+        if backup.size.width != self.buffer_dimensions.width
+            || backup.size.height != self.buffer_dimensions.height
+        {
+            return;
+        }
+        self.write_paint_targets(&self.document.paint_targets, &backup.color, &backup.height);
+    }
+
+    /// Replaces the textures of `targets` with window sized color and height
+    /// pixels.
+    fn write_paint_targets(&self, targets: &PaintTargets, color: &[u8], height: &[u8]) {
+        let size = self.buffer_dimensions;
+        for (texture, data, bytes_per_pixel) in [
+            (&targets.color_texture, color, 4),
+            (&targets.height_texture, height, 1),
+        ] {
+            self.gpu.queue.write_texture(
+                texture.as_image_copy(),
+                data,
+                wgpu::ImageDataLayout {
                     offset: 0,
-                    bytes_per_row: Some(
-                        std::num::NonZeroU32::new(
-                            Self::padded_bytes_per_row(self.buffer_dimensions) as u32,
-                        )
-                        .unwrap(),
-                    ),
+                    bytes_per_row: std::num::NonZeroU32::new(size.width * bytes_per_pixel),
                     rows_per_image: None,
                 },
+                Extent3d {
+                    width: size.width,
+                    height: size.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    /// Renders the painting as shown on screen, without guides and the color
+    /// wheel, as tightly packed rgba pixels.
+    fn render_image(&mut self) -> Result<Vec<u8>, CanvasError> {
+        let size = self.buffer_dimensions;
+        self.write_composite_params(size, false);
+        let export_texture = Self::create_texture(&self.gpu.device, size, EXPORT_FORMAT, "export");
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("export encoder"),
+            });
+        self.encode_paint_pass(&mut encoder);
+        {
+            let view = export_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("export pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.paper_clear_color()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.draw_composite(
+                &mut rpass,
+                &self.gpu.export_pipeline,
+                &self.document.paint_targets,
+                &self.document.selection_bind_group,
+            );
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+        export::read_texture(&self.gpu.device, &self.gpu.queue, &export_texture, size, 4)
+    }
+
+    /// Writes the painting as shown on screen (without color wheel) to a png file.
+    ///
+    /// With `with_height_map` the paint thickness is written as a grayscale
+    /// png next to it, see [`export::height_map_path`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the files cannot be written.
+    pub fn export_png(&mut self, path: &Path, with_height_map: bool) -> std::io::Result<()> {
+        self.encode_png(export::create_file(path)?)?;
+        if with_height_map {
+            self.encode_height_map_png(export::create_file(&export::height_map_path(path))?)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the painting as shown on screen (without color wheel) as png.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the png cannot be encoded or written.
+    pub fn encode_png(&mut self, writer: impl Write) -> std::io::Result<()> {
+        let pixels = self.pixels()?;
+        export::encode_png(
+            writer,
+            self.buffer_dimensions,
+            png::ColorType::Rgba,
+            &pixels,
+        )
+    }
+
+    /// The painting as shown on screen (without color wheel) as tightly
+    /// packed rgba.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the painting cannot be read back from the device.
+    pub fn pixels(&mut self) -> Result<Vec<u8>, CanvasError> {
+        self.render_image()
+    }
+
+    /// Paints every stroke again at `size`, sharper than the window when
+    /// larger, and writes it to a png file.
+    ///
+    /// Only brush strokes are vector paths, pixels moved with the transform
+    /// tool and the clipping by selections are left out. `size` is scaled
+    /// down to the largest texture of the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn export_strokes_png(&self, path: &Path, size: PhysicalSize) -> std::io::Result<()> {
+        self.encode_strokes_png(export::create_file(path)?, size)
+    }
+
+    /// Encodes every stroke painted again at `size` as png, see
+    /// [`Self::export_strokes_png`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the png cannot be encoded or written.
+    pub fn encode_strokes_png(
+        &self,
+        writer: impl Write,
+        size: PhysicalSize,
+    ) -> std::io::Result<()> {
+        let size = self.fit_texture_size(size);
+        let pixels = self.render_strokes(size)?;
+        export::encode_png(writer, size, png::ColorType::Rgba, &pixels)
+    }
+
+    /// Every stroke painted, oldest first.
+    #[must_use]
+    pub fn strokes(&self) -> &[Stroke] {
+        &self.document.strokes
+    }
+
+    /// Writes the strokes as SVG paths over the paper color.
+    ///
+    /// Like [`Self::export_strokes_png`] pixels moved with the transform tool
+    /// and the clipping by selections are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn export_svg(&self, path: &Path) -> std::io::Result<()> {
+        let mut writer = export::create_file(path)?;
+        self.encode_svg(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Encodes the strokes as SVG, see [`Self::export_svg`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer fails.
+    pub fn encode_svg(&self, writer: impl Write) -> std::io::Result<()> {
+        svg::write_svg(
+            writer,
+            &self.document.strokes,
+            self.buffer_dimensions,
+            self.document.paper.color(),
+        )
+    }
+
+    /// The painting as a paper layer with its grain and a paint layer above,
+    /// without the lighting of the paint thickness.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the paint cannot be read back from the device.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn layered_image(&mut self) -> Result<LayeredImage, CanvasError> {
+        self.flush_strokes();
+        let size = self.buffer_dimensions;
+        let grain_size = self.document.paper.size();
+        let grain = self.document.paper.grain();
+        let paper_color = self.document.paper.color();
+        let paper = (0..size.height)
+            .flat_map(|y| (0..size.width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let shade = Self::paper_shade(
+                    grain[((y % grain_size.height) * grain_size.width + x % grain_size.width)
+                        as usize],
+                );
+                let [r, g, b] = paper_color
+                    .map(|channel| (export::linear_to_srgb(channel * shade) * 255.0).round() as u8);
+                [r, g, b, u8::MAX]
+            })
+            .collect();
+        let mut paint = export::read_texture(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.document.paint_targets.color_texture,
+            size,
+            4,
+        )?;
+        paint.chunks_exact_mut(4).for_each(Self::unpremultiply);
+        let styled = |layer: CanvasLayer, image: Layer| {
+            let style = self.layer_style(layer);
+            Layer {
+                visible: style.visible,
+                opacity: style.opacity,
+                ..image
+            }
+        };
+        Ok(LayeredImage {
+            size,
+            layers: vec![
+                styled(
+                    CanvasLayer::Paper,
+                    Layer::new(Self::PAPER_LAYER_NAME, size, paper),
+                ),
+                styled(CanvasLayer::Paint, Layer::new("Paint", size, paint)),
+            ],
+        })
+    }
+
+    /// How much of the paper color shows at a grain `tooth` of `0..=255`.
+    fn paper_shade(tooth: u8) -> f32 {
+        0.15f32.mul_add(f32::from(tooth) / 255.0, 0.85)
+    }
+
+    /// Replaces the painting with `image`, returns what of it could not be
+    /// kept.
+    ///
+    /// A bottom layer named like the paper of [`Self::layered_image`]
+    /// becomes the paper color and style. The other layers become paint
+    /// without thickness at the top left of the canvas, cropped to it: a
+    /// single layer keeps its opacity and visibility, more are flattened
+    /// into one. The strokes painted before are forgotten and event logs
+    /// only note that the painting was replaced.
+    #[allow(clippy::cast_sign_loss)]
+    #[must_use]
+    pub fn import_layers(&mut self, image: &LayeredImage) -> Vec<String> {
+        self.record_unrecorded();
+        self.place_floating();
+        self.flush_strokes();
+        let mut warnings = vec![];
+        let mut styles = [LayerStyle::default(); 2];
+        let mut layers = image.layers.as_slice();
+        if let [paper, above @ ..] = layers {
+            if paper.name == Self::PAPER_LAYER_NAME {
+                let color = self.paper_color_of(paper);
+                self.document.paper.set_color(color);
+                styles[CanvasLayer::Paper as usize] = LayerStyle {
+                    visible: paper.visible,
+                    opacity: paper.opacity.clamp(0.0, 1.0),
+                };
+                layers = above;
+            }
+        }
+        let paint = if let [layer] = layers {
+            styles[CanvasLayer::Paint as usize] = LayerStyle {
+                visible: layer.visible,
+                opacity: layer.opacity.clamp(0.0, 1.0),
+            };
+            if layer.blend_mode != BlendMode::Normal {
+                warnings.push(format!("{} blends normally over the paper", layer.name));
+            }
+            vec![Layer {
+                visible: true,
+                opacity: 1.0,
+                ..layer.clone()
+            }]
+        } else {
+            if layers.len() > 1 {
+                warnings.push(format!(
+                    "{} layers merged into the paint layer",
+                    layers.len()
+                ));
+            }
+            layers.to_vec()
+        };
+        let size = self.buffer_dimensions;
+        let mut color = LayeredImage {
+            size: image.size,
+            layers: paint,
+        }
+        .flatten_at(size);
+        color.chunks_exact_mut(4).for_each(Self::premultiply);
+        let height = vec![0; size.width as usize * size.height as usize];
+        self.write_paint_targets(&self.document.paint_targets, &color, &height);
+        self.document.layer_styles = styles;
+        self.document.strokes.clear();
+        self.document.painted.clear();
+        self.document.backed_up.clear();
+        self.document.open_strokes = None;
+        self.document.stroke_groups.clear();
+        self.save_backup();
+        warnings
+    }
+
+    /// The paper color which, shaded by the grain, averages to the colors
+    /// of `layer`.
+    fn paper_color_of(&self, layer: &Layer) -> [f32; 3] {
+        let pixel_count = layer.pixels.len() / 4;
+        let grain = self.document.paper.grain();
+        if pixel_count == 0 || grain.is_empty() {
+            return self.document.paper.color();
+        }
+        let shade = grain.iter().copied().map(Self::paper_shade).sum::<f32>() / grain.len() as f32;
+        let mut sum = [0.0f32; 3];
+        for pixel in layer.pixels.chunks_exact(4) {
+            for (sum, channel) in sum.iter_mut().zip(pixel) {
+                *sum += export::srgb_to_linear(f32::from(*channel) / 255.0);
+            }
+        }
+        sum.map(|sum| (sum / pixel_count as f32 / shade).clamp(0.0, 1.0))
+    }
+
+    /// Straight sRGB rgba to the paint's, which is premultiplied by its
+    /// coverage in linear space.
+    #[allow(clippy::cast_sign_loss)]
+    fn premultiply(pixel: &mut [u8]) {
+        let alpha = f32::from(pixel[3]) / 255.0;
+        for channel in &mut pixel[..3] {
+            let linear = export::srgb_to_linear(f32::from(*channel) / 255.0) * alpha;
+            *channel = (export::linear_to_srgb(linear) * 255.0).round() as u8;
+        }
+    }
+
+    /// Inverse of [`Self::premultiply`].
+    #[allow(clippy::cast_sign_loss)]
+    fn unpremultiply(pixel: &mut [u8]) {
+        let alpha = f32::from(pixel[3]) / 255.0;
+        if alpha > 0.0 {
+            for channel in &mut pixel[..3] {
+                let linear = export::srgb_to_linear(f32::from(*channel) / 255.0) / alpha;
+                *channel = (export::linear_to_srgb(linear) * 255.0).round() as u8;
+            }
+        }
+    }
+
+    /// The selected paint (all of it without a selection, the floating
+    /// pixels while transforming) cropped to the selection, placed at its
+    /// position on the canvas. `None` for an empty selection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the paint cannot be read back from the device.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn copy_layer(&mut self) -> Result<Option<Layer>, CanvasError> {
+        let Some((min, max)) = self.document.selection.bounds() else {
+            return Ok(None);
+        };
+        self.flush_strokes();
+        let size = self.buffer_dimensions;
+        let lifted = self.document.transform.is_some();
+        let targets = if lifted {
+            &self.document.floating
+        } else {
+            &self.document.paint_targets
+        };
+        let paint = export::read_texture(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &targets.color_texture,
+            size,
+            4,
+        )?;
+        let mask = self.document.selection.mask();
+        let [min_x, min_y] = min.map(|value| value as u32);
+        let [max_x, max_y] = max.map(|value| value as u32);
+        let pixels = (min_y..max_y)
+            .flat_map(|y| (min_x..max_x).map(move |x| (y * size.width + x) as usize))
+            .flat_map(|index| {
+                let mut pixel = [0; 4];
+                pixel.copy_from_slice(&paint[index * 4..index * 4 + 4]);
+                Self::unpremultiply(&mut pixel);
+                // the floating pixels are cut out by the selection already.
+                if !lifted {
+                    pixel[3] = (u16::from(pixel[3]) * u16::from(mask[index]) / 255) as u8;
+                }
+                pixel
+            })
+            .collect();
+        let mut layer = Layer::new(
+            "Copy",
+            PhysicalSize {
+                width: max_x - min_x,
+                height: max_y - min_y,
             },
-            self.create_texture_extent(),
+            pixels,
         );
-        self.queue.submit(Some(encoder.finish()));
-        output_texture.present();
+        layer.offset = [min_x, min_y].map(u32::cast_signed);
+        Ok(Some(layer))
     }
 
-    pub fn inc_brush_size(&mut self) {
-        self.brush.inc_radius();
+    /// Floats `layer` at its offset above the paint, to be moved with the
+    /// transform tool and placed with [`Self::commit_transform`] or
+    /// discarded with [`Self::cancel_transform`].
+    ///
+    /// The pasted pixels have no thickness and, like imports, event logs
+    /// only note that they were pasted.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn paste_layer(&mut self, layer: &Layer) {
+        self.record_unrecorded();
+        self.place_floating();
+        self.flush_strokes();
+        let size = self.buffer_dimensions;
+        let clamp = |value: i64, length: u32| value.clamp(0, i64::from(length)) as f32;
+        let [x, y] = layer.offset.map(i64::from);
+        let min = [clamp(x, size.width), clamp(y, size.height)];
+        let max = [
+            clamp(x + i64::from(layer.size.width), size.width),
+            clamp(y + i64::from(layer.size.height), size.height),
+        ];
+        if min[0] >= max[0] || min[1] >= max[1] {
+            return;
+        }
+        let image = LayeredImage {
+            size,
+            layers: vec![layer.clone()],
+        };
+        let mut color = image.flatten();
+        color.chunks_exact_mut(4).for_each(Self::premultiply);
+        let height = vec![0; size.width as usize * size.height as usize];
+        self.write_paint_targets(&self.document.floating, &color, &height);
+        self.document.tool = Tool::Transform;
+        self.document.drag_start = None;
+        self.document.lasso.clear();
+        self.document.transform = Some(Transform::pasted(min, max));
     }
 
-    pub fn dec_brush_size(&mut self) {
-        self.brush.dec_radius();
+    /// Writes the layers of [`Self::layered_image`] to an `OpenRaster` or
+    /// Photoshop file, chosen by the `.ora` or `.psd` extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save_layered(&mut self, path: &Path) -> std::io::Result<()> {
+        layers::save(path, &self.layered_image()?)
     }
 
-    const fn padded_bytes_per_row(buffer_dimensions: PhysicalSize) -> u64 {
-        let bytes_per_pixel = std::mem::size_of::<u32>();
-        let unpadded_bytes_per_row = buffer_dimensions.width as usize * bytes_per_pixel;
-        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
-        let padded_bytes_per_row_padding = (align - unpadded_bytes_per_row % align) % align;
-        let padded_bytes_per_row = unpadded_bytes_per_row + padded_bytes_per_row_padding;
-        padded_bytes_per_row as u64
+    /// Opens an `.ora` or `.psd` file with [`Self::import_layers`], returns
+    /// what of it could not be kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not supported.
+    pub fn open_layered(&mut self, path: &Path) -> std::io::Result<Vec<String>> {
+        let imported = layers::load(path)?;
+        let mut warnings = imported.warnings;
+        warnings.extend(self.import_layers(&imported.image));
+        Ok(warnings)
     }
 
-    const fn create_texture_extent(&self) -> Extent3d {
-        Extent3d {
-            width: self.buffer_dimensions.width,
-            height: self.buffer_dimensions.height,
-            depth_or_array_layers: 1,
+    /// `size` scaled down to the largest texture, keeping its aspect ratio.
+    #[allow(clippy::cast_sign_loss)]
+    fn fit_texture_size(&self, size: PhysicalSize) -> PhysicalSize {
+        let max = self.gpu.device.limits().max_texture_dimension_2d;
+        let scale = (max as f32 / size.width.max(size.height) as f32).min(1.0);
+        let fit = |length: u32| ((length as f32 * scale) as u32).clamp(1, max);
+        PhysicalSize {
+            width: fit(size.width),
+            height: fit(size.height),
         }
     }
 
-    #[must_use]
-    pub fn create_output_buffer(device: &Device, buffer_dimensions: PhysicalSize) -> Buffer {
-        device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("output buffer"),
-            size: Self::padded_bytes_per_row(buffer_dimensions)
-                * u64::from(buffer_dimensions.height),
-            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        })
+    /// Tessellates all strokes into new paint targets of `size` and renders
+    /// them as tightly packed rgba.
+    fn render_strokes(&self, size: PhysicalSize) -> Result<Vec<u8>, CanvasError> {
+        let device = &self.gpu.device;
+        let targets = self.gpu.create_paint_targets(size);
+        // the selection mask has the window size, so nothing is clipped.
+        let selection_texture = Self::create_selection_texture(device, size);
+        Self::write_selection(&self.gpu.queue, &selection_texture, &Selection::new(size));
+        let selection = Self::create_selection_bind_group(
+            device,
+            &self.gpu.selection_layout,
+            &selection_texture,
+        );
+        let points: Vec<Point> = self
+            .document
+            .strokes
+            .iter()
+            .flat_map(|stroke| stroke.tessellate(0))
+            .collect();
+        self.write_composite_params(size, false);
+        let export_texture = Self::create_texture(device, size, EXPORT_FORMAT, "export");
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("stroke export encoder"),
+        });
+        self.encode_strokes(&mut encoder, &points, &targets, &selection);
+        {
+            let view = export_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("stroke export pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.paper_clear_color()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.draw_composite(&mut rpass, &self.gpu.export_pipeline, &targets, &selection);
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+        export::read_texture(&self.gpu.device, &self.gpu.queue, &export_texture, size, 4)
+    }
+
+    /// Encodes the paint thickness as a grayscale png.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the png cannot be encoded or written.
+    pub fn encode_height_map_png(&mut self, writer: impl Write) -> std::io::Result<()> {
+        self.flush_strokes();
+        let size = self.buffer_dimensions;
+        let heights = export::read_texture(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.document.paint_targets.height_texture,
+            size,
+            1,
+        )?;
+        export::encode_png(writer, size, png::ColorType::Grayscale, &heights)
+    }
+
+    pub fn inc_brush_size(&mut self) {
+        self.record(Event::IncBrushSize);
+        self.document.brush.inc_radius();
+    }
+
+    pub fn dec_brush_size(&mut self) {
+        self.record(Event::DecBrushSize);
+        self.document.brush.dec_radius();
     }
 
     #[allow(clippy::cast_sign_loss)]
@@ -337,11 +2661,247 @@ impl Canvas {
     }
 
     pub fn resize_window(&mut self, new_size: PhysicalSize) {
-        self.surface_config.width = new_size.width;
-        self.surface_config.height = new_size.height;
-        self.surface.configure(&self.device, &self.surface_config);
+        if new_size.width == 0 || new_size.height == 0 {
+            // minimized window, keep the old textures.
+            return;
+        }
+        self.record(Event::Resize(new_size));
+        self.for_each_document(|canvas| {
+            canvas.place_floating();
+            // the backup is resized with the painting, which has to be read
+            // back only once while dragging the window edge.
+            if canvas.painted_since_backup() {
+                canvas.save_backup();
+            } else {
+                canvas.flush_strokes();
+            }
+        });
+        self.gpu.surface_config.width = new_size.width;
+        self.gpu.surface_config.height = new_size.height;
+        if let Some(surface) = &self.gpu.surface {
+            surface.configure(&self.gpu.device, &self.gpu.surface_config);
+        }
+        let old_size = self.buffer_dimensions;
         self.buffer_dimensions = new_size;
-        self.output_buffer = Self::create_output_buffer(&self.device, new_size);
+        self.for_each_document(|canvas| {
+            let old_targets = std::mem::replace(
+                &mut canvas.document.paint_targets,
+                canvas.gpu.create_paint_targets(new_size),
+            );
+            canvas.copy_paint_targets(&old_targets, old_size, new_size);
+            canvas.document.floating = canvas.gpu.create_paint_targets(new_size);
+            canvas.document.selection = Selection::new(new_size);
+            canvas.document.selection_texture =
+                Self::create_selection_texture(&canvas.gpu.device, new_size);
+            canvas.document.selection_bind_group = Self::create_selection_bind_group(
+                &canvas.gpu.device,
+                &canvas.gpu.selection_layout,
+                &canvas.document.selection_texture,
+            );
+            canvas.upload_selection();
+            if let Some(backup) = &mut canvas.document.backup {
+                backup.resize(new_size);
+            }
+        });
         self.colorwheel.set_size(new_size);
     }
+
+    /// Keeps the painting where it was when the window is resized.
+    fn copy_paint_targets(
+        &self,
+        old: &PaintTargets,
+        old_size: PhysicalSize,
+        new_size: PhysicalSize,
+    ) {
+        let extent = Extent3d {
+            width: old_size.width.min(new_size.width),
+            height: old_size.height.min(new_size.height),
+            depth_or_array_layers: 1,
+        };
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("resize encoder"),
+            });
+        for (from, to) in [
+            (
+                &old.color_texture,
+                &self.document.paint_targets.color_texture,
+            ),
+            (
+                &old.height_texture,
+                &self.document.paint_targets.height_texture,
+            ),
+        ] {
+            encoder.copy_texture_to_texture(from.as_image_copy(), to.as_image_copy(), extent);
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+    }
+}
+
+impl GpuContext {
+    fn new(
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+        adapter: Adapter,
+        device: Device,
+        queue: Queue,
+    ) -> Self {
+        let paper_layout = Canvas::create_paper_layout(&device);
+        let selection_layout = Canvas::create_selection_layout(&device);
+        let colorwheel_layout = Canvas::create_colorwheel_layout(&device);
+        let (colorwheel_buffer, colorwheel_bind_group) =
+            Canvas::create_colorwheel_bind_group(&device, &colorwheel_layout);
+        let composite_layout = Canvas::create_composite_layout(&device);
+        Self {
+            paint_pipeline: Canvas::create_paint_pipeline(
+                &device,
+                &paper_layout,
+                &selection_layout,
+            ),
+            colorwheel_pipeline: Canvas::create_colorwheel_pipeline(
+                &device,
+                &colorwheel_layout,
+                surface_config.format,
+            ),
+            colorwheel_layout,
+            colorwheel_buffer,
+            colorwheel_bind_group,
+            composite_pipeline: Canvas::create_composite_pipeline(
+                &device,
+                &composite_layout,
+                &paper_layout,
+                &selection_layout,
+                surface_config.format,
+            ),
+            export_pipeline: Canvas::create_composite_pipeline(
+                &device,
+                &composite_layout,
+                &paper_layout,
+                &selection_layout,
+                EXPORT_FORMAT,
+            ),
+            guide_pipeline: Canvas::create_guide_pipeline(&device, &surface_config),
+            transform_pipelines: Canvas::create_transform_pipelines(
+                &device,
+                &composite_layout,
+                &selection_layout,
+                surface_config.format,
+            ),
+            composite_layout,
+            composite_buffer: Canvas::create_composite_buffer(&device),
+            sampler: Canvas::create_canvas_sampler(&device),
+            paper_layout,
+            paper_sampler: Canvas::create_paper_sampler(&device),
+            selection_layout,
+            device_error: Canvas::capture_device_errors(&device),
+            surface,
+            surface_config,
+            adapter,
+            device,
+            queue,
+            recreated: false,
+        }
+    }
+
+    /// Requests a new device and creates the pipelines again with it.
+    fn recreate_device(&mut self) -> Result<(), CanvasError> {
+        let (device, queue) = futures::executor::block_on(Canvas::request_device(&self.adapter))?;
+        let format = self.surface_config.format;
+        self.device_error = Canvas::capture_device_errors(&device);
+        self.device = device;
+        self.queue = queue;
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
+        let device = &self.device;
+        self.paper_layout = Canvas::create_paper_layout(device);
+        self.paper_sampler = Canvas::create_paper_sampler(device);
+        self.selection_layout = Canvas::create_selection_layout(device);
+        self.paint_pipeline =
+            Canvas::create_paint_pipeline(device, &self.paper_layout, &self.selection_layout);
+        self.colorwheel_layout = Canvas::create_colorwheel_layout(device);
+        self.colorwheel_pipeline =
+            Canvas::create_colorwheel_pipeline(device, &self.colorwheel_layout, format);
+        (self.colorwheel_buffer, self.colorwheel_bind_group) =
+            Canvas::create_colorwheel_bind_group(device, &self.colorwheel_layout);
+        self.guide_pipeline = Canvas::create_guide_pipeline(device, &self.surface_config);
+        self.composite_layout = Canvas::create_composite_layout(device);
+        self.composite_pipeline = Canvas::create_composite_pipeline(
+            device,
+            &self.composite_layout,
+            &self.paper_layout,
+            &self.selection_layout,
+            format,
+        );
+        self.export_pipeline = Canvas::create_composite_pipeline(
+            device,
+            &self.composite_layout,
+            &self.paper_layout,
+            &self.selection_layout,
+            EXPORT_FORMAT,
+        );
+        self.composite_buffer = Canvas::create_composite_buffer(device);
+        self.sampler = Canvas::create_canvas_sampler(device);
+        self.transform_pipelines = Canvas::create_transform_pipelines(
+            device,
+            &self.composite_layout,
+            &self.selection_layout,
+            format,
+        );
+        self.recreated = true;
+        Ok(())
+    }
+
+    /// Offscreen paint textures of `size` for a document.
+    fn create_paint_targets(&self, size: PhysicalSize) -> PaintTargets {
+        Canvas::create_paint_targets(
+            &self.device,
+            size,
+            &self.composite_layout,
+            &self.sampler,
+            &self.composite_buffer,
+        )
+    }
+}
+
+impl Document {
+    /// A blank document of `size` on `paper`, painting with `brush`.
+    fn new(gpu: &GpuContext, size: PhysicalSize, paper: Paper, brush: Brush) -> Self {
+        let selection_texture = Canvas::create_selection_texture(&gpu.device, size);
+        Self {
+            strokes: vec![],
+            brush,
+            backup: None,
+            paint_targets: gpu.create_paint_targets(size),
+            paper_bind_group: Canvas::create_paper_bind_group(
+                &gpu.device,
+                &gpu.queue,
+                &gpu.paper_layout,
+                &gpu.paper_sampler,
+                &paper,
+            ),
+            paper,
+            symmetry: Symmetry::default(),
+            tool: Tool::default(),
+            selection: Selection::new(size),
+            selection_bind_group: Canvas::create_selection_bind_group(
+                &gpu.device,
+                &gpu.selection_layout,
+                &selection_texture,
+            ),
+            selection_texture,
+            drag_start: None,
+            lasso: vec![],
+            transform: None,
+            floating: gpu.create_paint_targets(size),
+            painted: vec![],
+            backed_up: vec![],
+            open_strokes: None,
+            stroke_groups: vec![],
+            layer_styles: [LayerStyle::default(); 2],
+            unrecorded: false,
+        }
+    }
 }
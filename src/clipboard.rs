@@ -0,0 +1,109 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufReader, Write},
+    path::PathBuf,
+};
+
+use arboard::ImageData;
+
+use crate::{canvas::PhysicalSize, export, layers::Layer};
+
+/// The system clipboard for images, with a png file standing in for it
+/// where there is none (e.g. without a display server) or it fails.
+pub struct Clipboard {
+    system: Option<arboard::Clipboard>,
+    fallback: PathBuf,
+}
+
+impl Clipboard {
+    /// Connects to the system clipboard, `fallback` is the png used when
+    /// that fails. Returns why the system clipboard is not used, if so.
+    #[must_use]
+    pub fn new(fallback: impl Into<PathBuf>) -> (Self, Option<String>) {
+        let fallback = fallback.into();
+        let (system, warning) = match arboard::Clipboard::new() {
+            Ok(clipboard) => (Some(clipboard), None),
+            Err(err) => (
+                None,
+                Some(format!(
+                    "No system clipboard, copying to {}: {err}",
+                    fallback.display()
+                )),
+            ),
+        };
+        (Self { system, fallback }, warning)
+    }
+
+    /// Puts the pixels of `layer` on the clipboard, returns why they were
+    /// written to the fallback file instead, if so.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither the clipboard nor the fallback file can
+    /// be written.
+    pub fn copy(&mut self, layer: &Layer) -> io::Result<Option<String>> {
+        let mut warning = None;
+        if let Some(system) = &mut self.system {
+            let image = ImageData {
+                width: layer.size.width as usize,
+                height: layer.size.height as usize,
+                bytes: Cow::Borrowed(&layer.pixels),
+            };
+            match system.set_image(image) {
+                Ok(()) => return Ok(None),
+                Err(err) => {
+                    warning = Some(format!(
+                        "Failed to copy to the clipboard, copying to {}: {err}",
+                        self.fallback.display()
+                    ));
+                }
+            }
+        }
+        let mut file = export::create_file(&self.fallback)?;
+        export::encode_png(&mut file, layer.size, png::ColorType::Rgba, &layer.pixels)?;
+        file.flush()?;
+        Ok(warning)
+    }
+
+    /// The image on the clipboard as a layer at the top left, and why it was
+    /// read from the fallback file instead, if so.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the clipboard holds no image, or without a
+    /// clipboard if the fallback file cannot be read.
+    pub fn paste(&mut self) -> io::Result<(Layer, Option<String>)> {
+        let mut warning = None;
+        if let Some(system) = &mut self.system {
+            match system.get_image() {
+                Ok(image) => {
+                    let size = PhysicalSize {
+                        width: u32::try_from(image.width).map_err(invalid)?,
+                        height: u32::try_from(image.height).map_err(invalid)?,
+                    };
+                    let layer = Layer::new("Pasted", size, image.bytes.into_owned());
+                    return Ok((layer, None));
+                }
+                Err(arboard::Error::ContentNotAvailable) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no image on the clipboard",
+                    ));
+                }
+                Err(err) => {
+                    warning = Some(format!(
+                        "Failed to paste from the clipboard, pasting {}: {err}",
+                        self.fallback.display()
+                    ));
+                }
+            }
+        }
+        let (size, pixels) = export::decode_png(BufReader::new(File::open(&self.fallback)?))?;
+        Ok((Layer::new("Pasted", size, pixels), warning))
+    }
+}
+
+fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,232 @@
+use crate::export::{linear_to_srgb, srgb_to_linear};
+
+/// How a color is split into the three channels of a picker.
+///
+/// The colors of the canvas are linear rgb, the channels of every model but
+/// [`Self::Oklch`] are of the sRGB encoded color, as in other applications.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorModel {
+    /// red, green and blue `0.0..=255.0`.
+    #[default]
+    Rgb,
+    /// hue in degrees, saturation and value `0.0..=1.0`.
+    Hsv,
+    /// hue in degrees, saturation and lightness `0.0..=1.0`.
+    Hsl,
+    /// perceptual lightness `0.0..=1.0`, chroma and hue in degrees.
+    Oklch,
+}
+
+const COLOR_MODELS: [(ColorModel, &str); 4] = [
+    (ColorModel::Rgb, "RGB"),
+    (ColorModel::Hsv, "HSV"),
+    (ColorModel::Hsl, "HSL"),
+    (ColorModel::Oklch, "OKLCH"),
+];
+
+/// linear rgb to the cone responses of oklab.
+const LMS_FROM_LINEAR: [[f32; 3]; 3] = [
+    [0.412_221_46, 0.536_332_55, 0.051_445_995],
+    [0.211_903_5, 0.680_699_5, 0.107_396_96],
+    [0.088_302_46, 0.281_718_85, 0.629_978_7],
+];
+const OKLAB_FROM_LMS: [[f32; 3]; 3] = [
+    [0.210_454_26, 0.793_617_8, -0.004_072_047],
+    [1.977_998_5, -2.428_592_2, 0.450_593_7],
+    [0.025_904_037, 0.782_771_77, -0.808_675_77],
+];
+const LMS_FROM_OKLAB: [[f32; 3]; 3] = [
+    [1.0, 0.396_337_78, 0.215_803_76],
+    [1.0, -0.105_561_346, -0.063_854_17],
+    [1.0, -0.089_484_18, -1.291_485_5],
+];
+const LINEAR_FROM_LMS: [[f32; 3]; 3] = [
+    [4.076_741_7, -3.307_711_6, 0.230_969_94],
+    [-1.268_438, 2.609_757_4, -0.341_319_38],
+    [-0.004_196_086_3, -0.703_418_6, 1.707_614_7],
+];
+
+impl ColorModel {
+    pub const ALL: [Self; 4] = [Self::Rgb, Self::Hsv, Self::Hsl, Self::Oklch];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        COLOR_MODELS
+            .iter()
+            .find(|(model, _)| *model == self)
+            .map_or("", |(_, name)| name)
+    }
+
+    /// The name and largest value of each channel, the smallest is `0.0`.
+    #[must_use]
+    pub const fn channels(self) -> [(&'static str, f32); 3] {
+        match self {
+            Self::Rgb => [("R", 255.0), ("G", 255.0), ("B", 255.0)],
+            Self::Hsv => [("H", 360.0), ("S", 1.0), ("V", 1.0)],
+            Self::Hsl => [("H", 360.0), ("S", 1.0), ("L", 1.0)],
+            Self::Oklch => [("L", 1.0), ("C", 0.37), ("H", 360.0)],
+        }
+    }
+
+    /// Splits a `linear` rgb color into the channels of the model.
+    #[must_use]
+    pub fn to_channels(self, linear: [f32; 3]) -> [f32; 3] {
+        let srgb = linear.map(linear_to_srgb);
+        match self {
+            Self::Rgb => srgb.map(|channel| channel * 255.0),
+            Self::Hsv => hsv_from_srgb(srgb),
+            Self::Hsl => hsl_from_srgb(srgb),
+            Self::Oklch => oklch_from_linear(linear),
+        }
+    }
+
+    /// The linear rgb color of the `channels`, clamped to the colors the
+    /// canvas can show.
+    #[must_use]
+    pub fn from_channels(self, channels: [f32; 3]) -> [f32; 3] {
+        let linear = match self {
+            Self::Rgb => channels.map(|channel| srgb_to_linear(channel / 255.0)),
+            Self::Hsv => srgb_from_hsv(channels).map(srgb_to_linear),
+            Self::Hsl => srgb_from_hsl(channels).map(srgb_to_linear),
+            Self::Oklch => linear_from_oklch(channels),
+        };
+        linear.map(|channel| channel.clamp(0.0, 1.0))
+    }
+}
+
+/// `#rrggbb` of the sRGB encoded `linear` color.
+#[must_use]
+#[allow(clippy::cast_sign_loss)]
+pub fn to_hex(linear: [f32; 3]) -> String {
+    let [r, g, b] = linear.map(|channel| (linear_to_srgb(channel) * 255.0).round() as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// The linear color of `#rrggbb` or `#rgb`, the `#` is optional.
+#[must_use]
+pub fn from_hex(hex: &str) -> Option<[f32; 3]> {
+    let digits = hex.trim().trim_start_matches('#');
+    if !digits.is_ascii() {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    let srgb = match digits.len() {
+        3 => [0, 1, 2].map(|i| channel(&digits[i..=i]).map(|value| value * 17)),
+        6 => [0, 2, 4].map(|i| channel(&digits[i..i + 2])),
+        _ => return None,
+    };
+    let [r, g, b] = srgb;
+    Some([r?, g?, b?].map(|value| srgb_to_linear(f32::from(value) / 255.0)))
+}
+
+/// Hue in degrees of the `srgb` color with channels between `min` and `max`.
+fn hue([r, g, b]: [f32; 3], max: f32, min: f32) -> f32 {
+    let delta = max - min;
+    if delta <= 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    }
+}
+
+const fn max_min(srgb: [f32; 3]) -> (f32, f32) {
+    (
+        srgb[0].max(srgb[1]).max(srgb[2]),
+        srgb[0].min(srgb[1]).min(srgb[2]),
+    )
+}
+
+fn hsv_from_srgb(srgb: [f32; 3]) -> [f32; 3] {
+    let (max, min) = max_min(srgb);
+    let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+    [hue(srgb, max, min), saturation, max]
+}
+
+fn srgb_from_hsv([hue, saturation, value]: [f32; 3]) -> [f32; 3] {
+    [5.0, 3.0, 1.0].map(|n: f32| {
+        let k = (n + hue / 60.0).rem_euclid(6.0);
+        (value * saturation).mul_add(-k.min(4.0 - k).clamp(0.0, 1.0), value)
+    })
+}
+
+fn hsl_from_srgb(srgb: [f32; 3]) -> [f32; 3] {
+    let (max, min) = max_min(srgb);
+    let lightness = f32::midpoint(max, min);
+    let saturation = if max > min {
+        (max - min) / (1.0 - 2.0f32.mul_add(lightness, -1.0).abs())
+    } else {
+        0.0
+    };
+    [hue(srgb, max, min), saturation, lightness]
+}
+
+fn srgb_from_hsl([hue, saturation, lightness]: [f32; 3]) -> [f32; 3] {
+    let a = saturation * lightness.min(1.0 - lightness);
+    [0.0, 8.0, 4.0].map(|n: f32| {
+        let k = (n + hue / 30.0).rem_euclid(12.0);
+        a.mul_add(-(k - 3.0).min(9.0 - k).clamp(-1.0, 1.0), lightness)
+    })
+}
+
+fn oklch_from_linear(linear: [f32; 3]) -> [f32; 3] {
+    let lms = transform(LMS_FROM_LINEAR, linear).map(f32::cbrt);
+    let [lightness, a, b] = transform(OKLAB_FROM_LMS, lms);
+    let chroma = a.hypot(b);
+    // gray has no hue, it reads back the same with any.
+    let hue = if chroma < 1e-4 {
+        0.0
+    } else {
+        b.atan2(a).to_degrees().rem_euclid(360.0)
+    };
+    [lightness, chroma, hue]
+}
+
+fn linear_from_oklch([lightness, chroma, hue]: [f32; 3]) -> [f32; 3] {
+    let (sin, cos) = hue.to_radians().sin_cos();
+    let lms = transform(LMS_FROM_OKLAB, [lightness, chroma * cos, chroma * sin]);
+    transform(LINEAR_FROM_LMS, lms.map(|channel| channel.powi(3)))
+}
+
+fn transform(matrix: [[f32; 3]; 3], vector: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0].mul_add(vector[0], row[1].mul_add(vector[1], row[2] * vector[2])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        assert!(
+            a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn channels_round_trip() {
+        let orange = from_hex("#ff8000").unwrap();
+        assert_close(ColorModel::Rgb.to_channels(orange), [255.0, 128.0, 0.0]);
+        assert_close(ColorModel::Hsv.to_channels(orange), [30.118, 1.0, 1.0]);
+        assert_close(ColorModel::Hsl.to_channels(orange), [30.118, 1.0, 0.5]);
+        let white = ColorModel::Oklch.to_channels([1.0; 3]);
+        assert_close(white, [1.0, 0.0, 0.0]);
+        for color in [orange, [0.2, 0.5, 0.9], [0.0; 3], [1.0; 3], [0.3; 3]] {
+            for model in ColorModel::ALL {
+                assert_close(model.from_channels(model.to_channels(color)), color);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_hex() {
+        assert_eq!(to_hex(from_hex("#1a2B3c").unwrap()), "#1a2b3c");
+        assert_eq!(from_hex("f80"), from_hex("#ff8800"));
+        assert_eq!(from_hex("#ff880"), None);
+        assert_eq!(from_hex("#gg8800"), None);
+        assert_eq!(from_hex("#ffé00"), None);
+    }
+}
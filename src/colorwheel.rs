@@ -1,24 +1,271 @@
+use std::f32::consts::{FRAC_1_SQRT_2, TAU};
+
 use crate::canvas::PhysicalSize;
 
+/// The hue ring with a square of lighter and grayer shades inside, drawn
+/// over the canvas in a box the user can move and resize.
 #[repr(C)]
-#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ColorWheel {
     // NOTE: alpha is never used.
+    /// the color the square fades to white and black from.
     color: [f32; 4],
-    size: [f32; 3],
+    /// window size in pixels.
+    resolution: [f32; 2],
+    /// top left corner of the box in window pixels.
+    origin: [f32; 2],
+    /// width and height of the box in pixels.
+    side: f32,
+    /// space around the square, as a fraction of the side.
+    margin: f32,
+    _padding: [f32; 2],
+}
+
+/// What dragging on the color wheel does, decided where the drag starts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Grip {
+    /// picks the hue around the ring.
+    Hue,
+    /// picks a shade in the square.
+    Shade,
+    /// moves the box, `offset` from its top left corner.
+    Move { offset: [f32; 2] },
+    /// resizes the box from its bottom right corner.
+    Resize,
 }
 
 impl ColorWheel {
-    pub fn set_color(&mut self, color: [f32; 3]) {
+    /// smallest side in pixels.
+    const MIN_SIDE: f32 = 64.0;
+    /// the bottom right corner resizing the box, as a fraction of the side.
+    const RESIZE_GRIP: f32 = 0.15;
+    const MIN_MARGIN: f32 = 0.05;
+    const MAX_MARGIN: f32 = 0.35;
+
+    /// A wheel at the top right of a window of `size`, a quarter of its
+    /// width.
+    #[must_use]
+    pub fn new(size: PhysicalSize) -> Self {
+        let [width, height] = [size.width as f32, size.height as f32];
+        let mut wheel = Self {
+            color: [0.0, 0.0, 0.0, 1.0],
+            resolution: [width, height],
+            origin: [0.73 * width, 0.02 * height],
+            side: 0.25 * width,
+            margin: 0.2,
+            _padding: [0.0; 2],
+        };
+        wheel.keep_inside();
+        wheel
+    }
+
+    pub const fn set_color(&mut self, color: [f32; 3]) {
         self.color = [color[0], color[1], color[2], 1.0];
     }
 
     #[must_use]
     pub fn get_canvas_pos(&self, pos: [f32; 2]) -> [f32; 2] {
-        [pos[0] / self.size[0] - 0.5, -pos[1] / self.size[1] + 0.5]
+        [
+            pos[0] / self.resolution[0] - 0.5,
+            -pos[1] / self.resolution[1] + 0.5,
+        ]
     }
+
+    /// Keeps the box inside a window of `size`.
     pub fn set_size(&mut self, size: PhysicalSize) {
-        self.size[0] = size.width as f32;
-        self.size[1] = size.height as f32;
+        self.resolution = [size.width as f32, size.height as f32];
+        self.keep_inside();
+    }
+
+    #[must_use]
+    pub const fn origin(&self) -> [f32; 2] {
+        self.origin
+    }
+
+    /// Moves the top left corner of the box to `origin` in window pixels,
+    /// as far as the box stays inside the window.
+    pub fn move_to(&mut self, origin: [f32; 2]) {
+        self.origin = origin;
+        self.keep_inside();
+    }
+
+    #[must_use]
+    pub const fn side(&self) -> f32 {
+        self.side
+    }
+
+    /// Resizes the box to `side` pixels, keeping its top left corner where
+    /// it fits.
+    pub fn resize(&mut self, side: f32) {
+        self.side = side;
+        self.keep_inside();
+    }
+
+    #[must_use]
+    pub const fn margin(&self) -> f32 {
+        self.margin
+    }
+
+    /// Sets the space between the square and the box, which also sets how
+    /// wide the ring is.
+    pub const fn set_margin(&mut self, margin: f32) {
+        self.margin = margin.clamp(Self::MIN_MARGIN, Self::MAX_MARGIN);
+    }
+
+    /// What dragging from `pos` in window pixels does, `None` outside of the
+    /// box.
+    #[must_use]
+    pub fn grip_at(&self, pos: [f32; 2]) -> Option<Grip> {
+        let uv = self.uv(pos);
+        if !uv.iter().all(|coord| (0.0..=1.0).contains(coord)) {
+            return None;
+        }
+        let grip = if self.in_ring(uv) {
+            Grip::Hue
+        } else if self.square_uv(uv).is_some() {
+            Grip::Shade
+        } else if uv[0] + uv[1] >= 2.0 - Self::RESIZE_GRIP {
+            Grip::Resize
+        } else {
+            Grip::Move {
+                offset: [pos[0] - self.origin[0], pos[1] - self.origin[1]],
+            }
+        };
+        Some(grip)
+    }
+
+    /// Applies dragging `grip` to `pos` in window pixels, returns the color
+    /// picked by it.
+    pub fn drag(&mut self, grip: Grip, pos: [f32; 2]) -> Option<[f32; 3]> {
+        match grip {
+            Grip::Hue => return Some(self.hue_at(pos)),
+            Grip::Shade => return Some(self.shade_at(pos)),
+            Grip::Move { offset } => self.move_to([pos[0] - offset[0], pos[1] - offset[1]]),
+            Grip::Resize => self.resize((pos[0] - self.origin[0]).max(pos[1] - self.origin[1])),
+        }
+        None
+    }
+
+    /// The color of the ring in the direction of `pos` from the center, as
+    /// drawn by `color_wheel` in `colorwheel.wgsl`.
+    #[must_use]
+    pub fn hue_at(&self, pos: [f32; 2]) -> [f32; 3] {
+        let [u, v] = self.uv(pos);
+        let angle = (v - 0.5).atan2(u - 0.5);
+        [angle, angle - TAU / 3.0, angle + TAU / 3.0].map(primary_color)
+    }
+
+    /// The color of the square at `pos`, or of its nearest edge, as drawn by
+    /// `color_square` in `colorwheel.wgsl`.
+    #[must_use]
+    pub fn shade_at(&self, pos: [f32; 2]) -> [f32; 3] {
+        let [u, v] = self
+            .unclipped_square_uv(self.uv(pos))
+            .map(|coord| coord.clamp(0.0, 1.0));
+        [0, 1, 2].map(|channel| (1.0 - u).mul_add(v, u * self.color[channel]))
+    }
+
+    /// `pos` in window pixels relative to the box, `0.0..=1.0` inside it.
+    fn uv(&self, pos: [f32; 2]) -> [f32; 2] {
+        [
+            (pos[0] - self.origin[0]) / self.side,
+            (pos[1] - self.origin[1]) / self.side,
+        ]
+    }
+
+    fn in_ring(&self, [u, v]: [f32; 2]) -> bool {
+        let inner_radius = (0.5 - self.margin) / FRAC_1_SQRT_2;
+        let radius = (u - 0.5).hypot(v - 0.5);
+        (inner_radius..=0.5).contains(&radius)
+    }
+
+    /// `uv` of the box relative to the square, `None` outside of it.
+    fn square_uv(&self, uv: [f32; 2]) -> Option<[f32; 2]> {
+        let square_uv = self.unclipped_square_uv(uv);
+        square_uv
+            .iter()
+            .all(|coord| (0.0..=1.0).contains(coord))
+            .then_some(square_uv)
+    }
+
+    fn unclipped_square_uv(&self, uv: [f32; 2]) -> [f32; 2] {
+        uv.map(|coord| (coord - self.margin) / 2.0f32.mul_add(-self.margin, 1.0))
+    }
+
+    fn keep_inside(&mut self) {
+        let [width, height] = self.resolution;
+        self.side = self.side.min(width.min(height)).max(Self::MIN_SIDE);
+        self.origin = [
+            self.origin[0].min(width - self.side).max(0.0),
+            self.origin[1].min(height - self.side).max(0.0),
+        ];
+    }
+}
+
+/// How much of a primary color there is at `angle` from its own direction,
+/// as `compute_primary_color` in `colorwheel.wgsl`.
+fn primary_color(angle: f32) -> f32 {
+    let normalized_angle = angle.cos().atan2(angle.sin());
+    (1.0 - normalized_angle.abs() / (TAU / 3.0)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: PhysicalSize = PhysicalSize {
+        width: 400,
+        height: 400,
+    };
+
+    #[test]
+    fn grips_follow_the_box() {
+        let mut wheel = ColorWheel::new(SIZE);
+        wheel.move_to([100.0, 50.0]);
+        wheel.resize(200.0);
+        assert_eq!(wheel.grip_at([99.0, 150.0]), None);
+        assert_eq!(wheel.grip_at([200.0, 150.0]), Some(Grip::Shade));
+        assert_eq!(wheel.grip_at([295.0, 150.0]), Some(Grip::Hue));
+        assert_eq!(wheel.grip_at([298.0, 248.0]), Some(Grip::Resize));
+        assert_eq!(
+            wheel.grip_at([102.0, 52.0]),
+            Some(Grip::Move { offset: [2.0, 2.0] })
+        );
+        wheel.drag(Grip::Move { offset: [2.0, 2.0] }, [1000.0, 12.0]);
+        assert_eq!(wheel.origin(), [200.0, 10.0]);
+        wheel.drag(Grip::Resize, [250.0, 20.0]);
+        assert_eq!(wheel.side(), ColorWheel::MIN_SIDE);
+    }
+
+    #[test]
+    fn picks_the_drawn_colors() {
+        let assert_close = |a: [f32; 3], b: [f32; 3]| {
+            assert!(
+                a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5),
+                "{a:?} != {b:?}"
+            );
+        };
+        let mut wheel = ColorWheel::new(SIZE);
+        wheel.move_to([0.0, 0.0]);
+        wheel.resize(100.0);
+        wheel.set_color([0.0, 0.0, 1.0]);
+        let at = |angle: f32| {
+            [
+                50.0f32.mul_add(angle.cos(), 50.0),
+                50.0f32.mul_add(angle.sin(), 50.0),
+            ]
+        };
+        // red points down, green up left and blue up right.
+        assert_close(
+            wheel.drag(Grip::Hue, at(TAU / 4.0)).unwrap(),
+            [1.0, 0.0, 0.0],
+        );
+        assert_close(wheel.hue_at(at(TAU * 7.0 / 12.0)), [0.0, 1.0, 0.0]);
+        assert_close(wheel.hue_at(at(-TAU / 12.0)), [0.0, 0.0, 1.0]);
+        // the square fades from black at the top left to white at the bottom
+        // left, with the color along the right.
+        assert_eq!(wheel.shade_at([20.0, 20.0]), [0.0, 0.0, 0.0]);
+        assert_eq!(wheel.shade_at([80.0, 20.0]), [0.0, 0.0, 1.0]);
+        assert_eq!(wheel.shade_at([0.0, 100.0]), [1.0, 1.0, 1.0]);
     }
 }
@@ -4,14 +4,21 @@ const corners_constants = array<vec4<f32>, 4>(
     vec4<f32>(-1.0,-1.0,0.0,1.0),
     vec4<f32>(-1.0,1.0,0.0,1.0),
 );
-const margin: f32 = 0.2;
+// the bottom right corner resizing the box, as in `colorwheel.rs`.
+const resize_grip: f32 = 0.15;
 
 struct ColorWheel {
     color: vec4<f32>,
     resolution: vec2<f32>,
+    // top left corner of the box in pixels.
+    origin: vec2<f32>,
+    side: f32,
+    // space around the square, as a fraction of the side.
+    margin: f32,
 };
 
-var<push_constant> pc: ColorWheel;
+@group(0) @binding(0)
+var<uniform> pc: ColorWheel;
 
 @vertex
 fn vs_main(
@@ -29,6 +36,7 @@ fn color_square(uv: vec2<f32>) -> vec3<f32>{
 }
 
 fn color_square_scaled(uv: vec2<f32>) -> vec4<f32>{
+  let margin = pc.margin;
   var margin_other = margin - 1.0;
   var clip: f32 = step(margin, uv.x) * step(margin_other, -uv.x) * step(margin, uv.y) * step(margin_other, -uv.y);
   var uv_in_margin: vec2<f32> = (uv - margin)/ (1.0-(2.0*margin));
@@ -44,7 +52,7 @@ fn compute_primary_color(angle: f32) -> f32 {
 
 fn color_wheel(uv: vec2<f32>)-> vec4<f32> {
     let two_pi_by_three: f32 = acos(-0.5);
-    let min_radius: f32 = sqrt(2.0) * (0.5 - margin);
+    let min_radius: f32 = sqrt(2.0) * (0.5 - pc.margin);
 
     var radius: vec2<f32> = uv - 0.5;
     let angle: f32 = atan2(radius.y, radius.x);
@@ -58,9 +66,16 @@ fn color_wheel(uv: vec2<f32>)-> vec4<f32> {
     );
 }
 
+// a faint box to drag the wheel by, darker in the corner resizing it.
+fn drag_box(uv: vec2<f32>) -> vec4<f32> {
+    let inside = step(0.0, uv.x) * step(uv.x, 1.0) * step(0.0, uv.y) * step(uv.y, 1.0);
+    let grip = step(2.0 - resize_grip, uv.x + uv.y);
+    return vec4<f32>(vec3<f32>(0.5), inside * mix(0.25, 0.6, grip));
+}
+
 @fragment
 fn fs_main(@builtin(position) in: vec4<f32>) -> @location(0) vec4<f32> {
-    var normal_in: vec2<f32> = in.xy / pc.resolution.xy;
-    var down_right_in = (normal_in - vec2<f32>(0.73, 0.02)) * 4.0;
-    return color_square_scaled(down_right_in) + color_wheel(down_right_in);
+    let uv = (in.xy - pc.origin) / pc.side;
+    let wheel = color_square_scaled(uv) + color_wheel(uv);
+    return select(drag_box(uv), wheel, wheel.a > 0.0);
 }
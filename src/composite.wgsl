@@ -0,0 +1,104 @@
+const corners_constants = array<vec4<f32>, 4>(
+    vec4<f32>(1.0,-1.0,0.0,1.0),
+    vec4<f32>(1.0,1.0,0.0,1.0),
+    vec4<f32>(-1.0,-1.0,0.0,1.0),
+    vec4<f32>(-1.0,1.0,0.0,1.0),
+);
+const light_direction = vec3<f32>(-0.5, 0.5, 1.0);
+
+struct Composite {
+    texel: vec2<f32>,
+    lit: f32,
+    relief: f32,
+    paper_color: vec4<f32>,
+    ants_phase: f32,
+    show_ants: f32,
+    paint_opacity: f32,
+    paper_opacity: f32,
+    checker: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@group(0) @binding(0) var color_texture: texture_2d<f32>;
+@group(0) @binding(1) var height_texture: texture_2d<f32>;
+@group(0) @binding(2) var canvas_sampler: sampler;
+@group(0) @binding(3) var<uniform> params: Composite;
+@group(1) @binding(0) var paper_texture: texture_2d<f32>;
+@group(1) @binding(1) var paper_sampler: sampler;
+@group(2) @binding(0) var selection_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32
+) -> VertexOutput {
+    // https://github.com/gfx-rs/naga/issues/1910
+    var corners = corners_constants;
+    var out: VertexOutput;
+    out.position = corners[in_vertex_index];
+    out.uv = vec2<f32>(out.position.x + 1.0, 1.0 - out.position.y) * 0.5;
+    return out;
+}
+
+fn height_at(uv: vec2<f32>) -> f32 {
+    return textureSample(height_texture, canvas_sampler, uv).r;
+}
+
+fn is_selected(pixel: vec2<i32>) -> bool {
+    let last = vec2<i32>(textureDimensions(selection_texture)) - 1;
+    return textureLoad(selection_texture, clamp(pixel, vec2<i32>(0), last), 0).r >= 0.5;
+}
+
+// black and white dashes along the selection edge.
+fn marching_ants(pixel: vec2<i32>) -> vec4<f32> {
+    let inside = is_selected(pixel);
+    let edge = inside != is_selected(pixel + vec2<i32>(1, 0))
+        || inside != is_selected(pixel - vec2<i32>(1, 0))
+        || inside != is_selected(pixel + vec2<i32>(0, 1))
+        || inside != is_selected(pixel - vec2<i32>(0, 1));
+    let dash = f32(((pixel.x + pixel.y + i32(params.ants_phase)) / 4) % 2);
+    return vec4<f32>(vec3<f32>(dash), f32(edge) * params.show_ants);
+}
+
+// light and dark squares on screen, white in exports.
+fn backdrop(pixel: vec2<f32>) -> vec3<f32> {
+    let square = vec2<i32>(pixel) / 8;
+    let dark = f32((square.x + square.y) % 2) * params.checker;
+    return vec3<f32>(1.0 - 0.4 * dark);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let paint = textureSample(color_texture, canvas_sampler, in.uv);
+    let paper_uv = in.position.xy / vec2<f32>(textureDimensions(paper_texture));
+    let tooth = textureSample(paper_texture, paper_sampler, paper_uv).r;
+    let paper = mix(
+        backdrop(in.position.xy),
+        params.paper_color.rgb * (0.85 + 0.15 * tooth),
+        params.paper_opacity,
+    );
+    // paint is stored premultiplied by its coverage.
+    let color = vec4<f32>(
+        paint.rgb * params.paint_opacity + paper * (1.0 - paint.a * params.paint_opacity),
+        1.0,
+    );
+    let dx = vec2<f32>(params.texel.x, 0.0);
+    let dy = vec2<f32>(0.0, params.texel.y);
+    let slope = vec2<f32>(
+        height_at(in.uv - dx) - height_at(in.uv + dx),
+        height_at(in.uv + dy) - height_at(in.uv - dy),
+    ) * params.relief;
+    let normal = normalize(vec3<f32>(slope, 1.0));
+    let light = normalize(light_direction);
+    // flat canvas keeps its color, slopes facing the light get brighter.
+    let diffuse = max(dot(normal, light), 0.0) / light.z;
+    let specular = pow(max(dot(reflect(-light, normal), vec3<f32>(0.0, 0.0, 1.0)), 0.0), 24.0)
+        * step(0.001, height_at(in.uv)) * 0.25;
+    let lit = vec4<f32>(color.rgb * diffuse + specular, color.a);
+    let shaded = select(color, lit, params.lit > 0.5);
+    let ants = marching_ants(vec2<i32>(in.position.xy));
+    return vec4<f32>(mix(shaded.rgb, ants.rgb, ants.a), shaded.a);
+}
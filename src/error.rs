@@ -0,0 +1,146 @@
+use std::{error::Error, fmt, io};
+
+use wgpu_core::{
+    binding_model::{CreateBindGroupError, CreateBindGroupLayoutError, CreatePipelineLayoutError},
+    device::{
+        queue::{QueueSubmitError, QueueWriteError},
+        DeviceError,
+    },
+    pipeline::{CreateRenderPipelineError, CreateShaderModuleError},
+    resource::{BufferAccessError, CreateBufferError, CreateSamplerError, CreateTextureError},
+};
+
+/// Errors creating or drawing a [`Canvas`](crate::canvas::Canvas).
+#[derive(Debug)]
+pub enum CanvasError {
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// no adapter can present to the surface.
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /// the surface supports no texture format on the adapter.
+    IncompatibleSurface,
+    Surface(wgpu::SurfaceError),
+    OutOfMemory,
+    /// the device was lost and could not be recreated.
+    DeviceLost,
+    /// an error reported by the device, e.g. a failed validation.
+    Device(String),
+    /// a texture could not be read back from the device.
+    MapBuffer(wgpu::BufferAsyncError),
+}
+
+impl CanvasError {
+    /// Whether the canvas cannot draw anymore.
+    #[must_use]
+    pub const fn is_fatal(&self) -> bool {
+        !matches!(
+            self,
+            Self::Surface(_) | Self::Device(_) | Self::MapBuffer(_)
+        )
+    }
+
+    /// Sorts an uncaptured device error into a lost device, out of memory or
+    /// anything else.
+    #[must_use]
+    pub fn from_device_error(error: &wgpu::Error) -> Self {
+        match error {
+            wgpu::Error::OutOfMemory { .. } => Self::OutOfMemory,
+            wgpu::Error::Validation {
+                source,
+                description,
+            } => {
+                let mut cause: Option<&(dyn Error + 'static)> = Some(source.as_ref());
+                while let Some(error) = cause {
+                    match device_error(error) {
+                        Some(DeviceError::Lost) => return Self::DeviceLost,
+                        Some(DeviceError::OutOfMemory) => return Self::OutOfMemory,
+                        _ => cause = error.source(),
+                    }
+                }
+                Self::Device(description.clone())
+            }
+        }
+    }
+}
+
+/// The [`DeviceError`] in `error`, if it is one of the errors of the calls
+/// the canvas makes. wgpu reports a lost device as a validation error, with
+/// the [`DeviceError`] as a variant of the error of the failed call.
+fn device_error<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a DeviceError> {
+    macro_rules! find_device_error {
+        ($($error:ident::$variant:ident),* $(,)?) => {
+            $(
+                if let Some($error::$variant(device_error)) = error.downcast_ref::<$error>() {
+                    return Some(device_error);
+                }
+            )*
+        };
+    }
+    find_device_error!(
+        CreateBufferError::Device,
+        CreateTextureError::Device,
+        CreateSamplerError::Device,
+        CreateBindGroupLayoutError::Device,
+        CreateBindGroupError::Device,
+        CreatePipelineLayoutError::Device,
+        CreateShaderModuleError::Device,
+        CreateRenderPipelineError::Device,
+        BufferAccessError::Device,
+        QueueWriteError::Queue,
+        QueueSubmitError::Queue,
+    );
+    error.downcast_ref()
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateSurface(err) => write!(f, "Failed to create surface: {err}"),
+            Self::NoAdapter => write!(f, "Failed to find an appropriate adapter"),
+            Self::RequestDevice(err) => write!(f, "Failed to create device: {err}"),
+            Self::IncompatibleSurface => write!(f, "The surface is incompatible with the adapter"),
+            Self::Surface(err) => write!(f, "Failed to get texture for rendering: {err}"),
+            Self::OutOfMemory => write!(f, "The device ran out of memory"),
+            Self::DeviceLost => write!(f, "The device was lost"),
+            Self::Device(description) => write!(f, "Device error: {description}"),
+            Self::MapBuffer(err) => write!(f, "Failed to read back a texture: {err}"),
+        }
+    }
+}
+
+impl Error for CanvasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::CreateSurface(err) => Some(err),
+            Self::RequestDevice(err) => Some(err),
+            Self::Surface(err) => Some(err),
+            Self::MapBuffer(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for CanvasError {
+    fn from(err: wgpu::CreateSurfaceError) -> Self {
+        Self::CreateSurface(err)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for CanvasError {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        Self::RequestDevice(err)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for CanvasError {
+    fn from(err: wgpu::BufferAsyncError) -> Self {
+        Self::MapBuffer(err)
+    }
+}
+
+/// For exports, which fail with io errors.
+impl From<CanvasError> for io::Error {
+    fn from(err: CanvasError) -> Self {
+        Self::other(err)
+    }
+}
@@ -0,0 +1,532 @@
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    brush::BrushKind,
+    canvas::{CanvasLayer, PhysicalSize},
+    export,
+    symmetry::SymmetryMode,
+    tool::Tool,
+    transform::Resampling,
+};
+
+/// An input fed to the [`Canvas`](crate::canvas::Canvas).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    MouseAt {
+        brush_down: bool,
+        pos: [f32; 2],
+    },
+    Pressure(f32),
+    IncBrushSize,
+    DecBrushSize,
+    BrushRadius(f32),
+    BrushColor([f32; 3]),
+    BrushKind(BrushKind),
+    BrushOpacity(f32),
+    ColorWheelToggle,
+    LightingToggle,
+    Tool(Tool),
+    CommitTransform,
+    CancelTransform,
+    Undo,
+    Resampling(Resampling),
+    SelectAll,
+    InvertSelection,
+    FeatherSelection(usize),
+    GrowSelection(usize),
+    ShrinkSelection(usize),
+    SymmetryMode(SymmetryMode),
+    SymmetryCenter([f32; 2]),
+    Resize(PhysicalSize),
+    NewDocument,
+    CloseDocument,
+    /// shows the document at the index.
+    Document(usize),
+    LayerVisible(CanvasLayer, bool),
+    LayerOpacity(CanvasLayer, f32),
+    /// the paper or painting was replaced from a file or the clipboard,
+    /// which the log does not hold.
+    Unrecorded,
+}
+
+const BRUSH_KINDS: [(BrushKind, &str); 4] = [
+    (BrushKind::Round, "round"),
+    (BrushKind::Impasto, "impasto"),
+    (BrushKind::Pencil, "pencil"),
+    (BrushKind::Charcoal, "charcoal"),
+];
+
+const TOOLS: [(Tool, &str); 6] = [
+    (Tool::Brush, "brush"),
+    (Tool::RectSelect, "rect_select"),
+    (Tool::EllipseSelect, "ellipse_select"),
+    (Tool::Lasso, "lasso"),
+    (Tool::MagicWand, "magic_wand"),
+    (Tool::Transform, "transform"),
+];
+
+const CANVAS_LAYERS: [(CanvasLayer, &str); 2] =
+    [(CanvasLayer::Paper, "paper"), (CanvasLayer::Paint, "paint")];
+
+const RESAMPLINGS: [(Resampling, &str); 2] = [
+    (Resampling::Bilinear, "bilinear"),
+    (Resampling::Bicubic, "bicubic"),
+];
+
+fn name_of<T: PartialEq + Copy>(names: &[(T, &'static str)], value: T) -> &'static str {
+    names
+        .iter()
+        .find(|(v, _)| *v == value)
+        .map_or("", |(_, name)| name)
+}
+
+fn from_name<T: Copy>(names: &[(T, &str)], name: &str) -> Option<T> {
+    names.iter().find(|(_, n)| *n == name).map(|(v, _)| *v)
+}
+
+/// `round`, `impasto`, `pencil` or `charcoal`.
+#[must_use]
+pub fn parse_brush_kind(name: &str) -> Option<BrushKind> {
+    from_name(&BRUSH_KINDS, name)
+}
+
+/// The name [`parse_brush_kind`] reads back.
+#[must_use]
+pub fn brush_kind_name(kind: BrushKind) -> &'static str {
+    name_of(&BRUSH_KINDS, kind)
+}
+
+/// `brush`, `rect_select`, `ellipse_select`, `lasso`, `magic_wand` or
+/// `transform`.
+#[must_use]
+pub fn parse_tool(name: &str) -> Option<Tool> {
+    from_name(&TOOLS, name)
+}
+
+/// The name [`parse_tool`] reads back.
+#[must_use]
+pub fn tool_name(tool: Tool) -> &'static str {
+    name_of(&TOOLS, tool)
+}
+
+impl fmt::Display for Event {
+    /// Writes the event as a name followed by its arguments, floats are
+    /// written in full so they read back exactly.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MouseAt { brush_down, pos } => {
+                write!(
+                    f,
+                    "mouse_at {} {} {}",
+                    u8::from(*brush_down),
+                    pos[0],
+                    pos[1]
+                )
+            }
+            Self::Pressure(pressure) => write!(f, "pressure {pressure}"),
+            Self::IncBrushSize => write!(f, "inc_brush_size"),
+            Self::DecBrushSize => write!(f, "dec_brush_size"),
+            Self::BrushRadius(radius) => write!(f, "brush_radius {radius}"),
+            Self::BrushColor([r, g, b]) => write!(f, "brush_color {r} {g} {b}"),
+            Self::BrushKind(kind) => write!(f, "brush_kind {}", name_of(&BRUSH_KINDS, *kind)),
+            Self::BrushOpacity(opacity) => write!(f, "brush_opacity {opacity}"),
+            Self::ColorWheelToggle => write!(f, "color_wheel_toggle"),
+            Self::LightingToggle => write!(f, "lighting_toggle"),
+            Self::Tool(tool) => write!(f, "tool {}", name_of(&TOOLS, *tool)),
+            Self::CommitTransform => write!(f, "commit_transform"),
+            Self::CancelTransform => write!(f, "cancel_transform"),
+            Self::Undo => write!(f, "undo"),
+            Self::Resampling(resampling) => {
+                write!(f, "resampling {}", name_of(&RESAMPLINGS, *resampling))
+            }
+            Self::SelectAll => write!(f, "select_all"),
+            Self::InvertSelection => write!(f, "invert_selection"),
+            Self::FeatherSelection(radius) => write!(f, "feather_selection {radius}"),
+            Self::GrowSelection(radius) => write!(f, "grow_selection {radius}"),
+            Self::ShrinkSelection(radius) => write!(f, "shrink_selection {radius}"),
+            Self::SymmetryMode(mode) => match mode {
+                SymmetryMode::Off => write!(f, "symmetry off"),
+                SymmetryMode::Vertical => write!(f, "symmetry vertical"),
+                SymmetryMode::Horizontal => write!(f, "symmetry horizontal"),
+                SymmetryMode::Both => write!(f, "symmetry both"),
+                SymmetryMode::Radial(count) => write!(f, "symmetry radial {count}"),
+            },
+            Self::SymmetryCenter(pos) => write!(f, "symmetry_center {} {}", pos[0], pos[1]),
+            Self::Resize(size) => write!(f, "resize {} {}", size.width, size.height),
+            Self::NewDocument => write!(f, "new_document"),
+            Self::CloseDocument => write!(f, "close_document"),
+            Self::Document(index) => write!(f, "document {index}"),
+            Self::LayerVisible(layer, visible) => write!(
+                f,
+                "layer_visible {} {}",
+                name_of(&CANVAS_LAYERS, *layer),
+                u8::from(*visible)
+            ),
+            Self::LayerOpacity(layer, opacity) => write!(
+                f,
+                "layer_opacity {} {opacity}",
+                name_of(&CANVAS_LAYERS, *layer)
+            ),
+            Self::Unrecorded => write!(f, "unrecorded"),
+        }
+    }
+}
+
+impl Event {
+    /// Parses the words written by the [`Display`](fmt::Display) impl.
+    fn parse(words: &[&str]) -> Option<Self> {
+        let event = match *words {
+            ["mouse_at", down, x, y] => Self::MouseAt {
+                brush_down: down == "1",
+                pos: [x.parse().ok()?, y.parse().ok()?],
+            },
+            ["pressure", pressure] => Self::Pressure(pressure.parse().ok()?),
+            ["inc_brush_size"] => Self::IncBrushSize,
+            ["dec_brush_size"] => Self::DecBrushSize,
+            ["brush_radius", radius] => Self::BrushRadius(radius.parse().ok()?),
+            ["brush_color", r, g, b] => {
+                Self::BrushColor([r.parse().ok()?, g.parse().ok()?, b.parse().ok()?])
+            }
+            ["brush_kind", kind] => Self::BrushKind(parse_brush_kind(kind)?),
+            ["brush_opacity", opacity] => Self::BrushOpacity(opacity.parse().ok()?),
+            ["color_wheel_toggle"] => Self::ColorWheelToggle,
+            ["lighting_toggle"] => Self::LightingToggle,
+            ["tool", tool] => Self::Tool(from_name(&TOOLS, tool)?),
+            ["commit_transform"] => Self::CommitTransform,
+            ["cancel_transform"] => Self::CancelTransform,
+            ["undo"] => Self::Undo,
+            ["resampling", resampling] => Self::Resampling(from_name(&RESAMPLINGS, resampling)?),
+            ["select_all"] => Self::SelectAll,
+            ["invert_selection"] => Self::InvertSelection,
+            ["feather_selection", radius] => Self::FeatherSelection(radius.parse().ok()?),
+            ["grow_selection", radius] => Self::GrowSelection(radius.parse().ok()?),
+            ["shrink_selection", radius] => Self::ShrinkSelection(radius.parse().ok()?),
+            ["symmetry", "off"] => Self::SymmetryMode(SymmetryMode::Off),
+            ["symmetry", "vertical"] => Self::SymmetryMode(SymmetryMode::Vertical),
+            ["symmetry", "horizontal"] => Self::SymmetryMode(SymmetryMode::Horizontal),
+            ["symmetry", "both"] => Self::SymmetryMode(SymmetryMode::Both),
+            ["symmetry", "radial", count] => {
+                Self::SymmetryMode(SymmetryMode::Radial(count.parse().ok()?))
+            }
+            ["symmetry_center", x, y] => Self::SymmetryCenter([x.parse().ok()?, y.parse().ok()?]),
+            ["resize", width, height] => Self::Resize(parse_size(width, height)?),
+            ["new_document"] => Self::NewDocument,
+            ["close_document"] => Self::CloseDocument,
+            ["document", index] => Self::Document(index.parse().ok()?),
+            ["layer_visible", layer, visible] => {
+                Self::LayerVisible(from_name(&CANVAS_LAYERS, layer)?, visible == "1")
+            }
+            ["layer_opacity", layer, opacity] => {
+                Self::LayerOpacity(from_name(&CANVAS_LAYERS, layer)?, opacity.parse().ok()?)
+            }
+            ["unrecorded"] => Self::Unrecorded,
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+impl Event {
+    /// The same input in a window `scale` times the recorded size, the
+    /// canvas sees the same relative positions.
+    #[must_use]
+    pub fn scaled(self, scale: [f32; 2]) -> Self {
+        let scale_pos = |pos: [f32; 2]| [pos[0] * scale[0], pos[1] * scale[1]];
+        let scale_radius = |radius: usize| scale_length(radius as f32 * scale[0]) as usize;
+        match self {
+            Self::MouseAt { brush_down, pos } => Self::MouseAt {
+                brush_down,
+                pos: scale_pos(pos),
+            },
+            Self::SymmetryCenter(pos) => Self::SymmetryCenter(scale_pos(pos)),
+            Self::FeatherSelection(radius) => Self::FeatherSelection(scale_radius(radius)),
+            Self::GrowSelection(radius) => Self::GrowSelection(scale_radius(radius)),
+            Self::ShrinkSelection(radius) => Self::ShrinkSelection(scale_radius(radius)),
+            Self::Resize(size) => Self::Resize(PhysicalSize {
+                width: scale_length(size.width as f32 * scale[0]),
+                height: scale_length(size.height as f32 * scale[1]),
+            }),
+            event => event,
+        }
+    }
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn scale_length(length: f32) -> u32 {
+    length.round() as u32
+}
+
+fn parse_size(width: &str, height: &str) -> Option<PhysicalSize> {
+    Some(PhysicalSize {
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+    })
+}
+
+/// An event with the time since the recording started.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimedEvent {
+    pub time: Duration,
+    pub event: Event,
+}
+
+/// The inputs of a painting session, replayed on a canvas of the same size
+/// they paint the same picture.
+///
+/// Stored as text, a `size <width> <height>` line followed by one
+/// `<milliseconds> <event>` line per event.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventLog {
+    size: PhysicalSize,
+    events: Vec<TimedEvent>,
+}
+
+impl EventLog {
+    #[must_use]
+    pub const fn new(size: PhysicalSize) -> Self {
+        Self {
+            size,
+            events: vec![],
+        }
+    }
+
+    /// The canvas size when the recording started.
+    #[must_use]
+    pub const fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    #[must_use]
+    pub fn events(&self) -> &[TimedEvent] {
+        &self.events
+    }
+
+    pub fn push(&mut self, time: Duration, event: Event) {
+        self.events.push(TimedEvent { time, event });
+    }
+
+    /// Whether the session started from or loaded a paper, file or pasted
+    /// image the log does not hold, so a replay can paint something else.
+    #[must_use]
+    pub fn has_unrecorded(&self) -> bool {
+        self.events
+            .iter()
+            .any(|timed| timed.event == Event::Unrecorded)
+    }
+
+    /// The session in a window of `size`, window resizes are left out so
+    /// the canvas keeps that size.
+    #[must_use]
+    pub fn resized(&self, size: PhysicalSize) -> Self {
+        let mut recorded = self.size;
+        let mut log = Self::new(size);
+        for &TimedEvent { time, event } in &self.events {
+            if let Event::Resize(new_size) = event {
+                recorded = new_size;
+                continue;
+            }
+            let scale = [
+                size.width as f32 / recorded.width as f32,
+                size.height as f32 / recorded.height as f32,
+            ];
+            log.push(time, event.scaled(scale));
+        }
+        log
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "size {} {}", self.size.width, self.size.height)?;
+        for TimedEvent { time, event } in &self.events {
+            writeln!(writer, "{} {event}", time.as_millis())?;
+        }
+        writer.flush()
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if reading fails or a line is not a valid event.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let invalid = |line: usize| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid event log line {}", line + 1),
+            )
+        };
+        let mut lines = reader.lines().enumerate();
+        let size = match lines.next() {
+            Some((_, line)) => match *line?.split_whitespace().collect::<Vec<_>>() {
+                ["size", width, height] => parse_size(width, height).ok_or_else(|| invalid(0))?,
+                _ => return Err(invalid(0)),
+            },
+            None => return Err(invalid(0)),
+        };
+        let mut log = Self::new(size);
+        for (number, line) in lines {
+            let line = line?;
+            let words: Vec<_> = line.split_whitespace().collect();
+            let Some((millis, event)) = words.split_first() else {
+                continue;
+            };
+            let millis = millis.parse().map_err(|_| invalid(number))?;
+            let event = Event::parse(event).ok_or_else(|| invalid(number))?;
+            log.push(Duration::from_millis(millis), event);
+        }
+        Ok(log)
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.write(export::create_file(path)?)
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not an event log.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::read(BufReader::new(std::fs::File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: PhysicalSize = PhysicalSize {
+        width: 200,
+        height: 100,
+    };
+
+    const EVENTS: [Event; 32] = [
+        Event::MouseAt {
+            brush_down: true,
+            pos: [12.5, 0.1],
+        },
+        Event::Pressure(0.333_333_34),
+        Event::IncBrushSize,
+        Event::DecBrushSize,
+        Event::BrushRadius(0.012),
+        Event::BrushColor([0.1, 0.5, 1.0]),
+        Event::BrushKind(BrushKind::Charcoal),
+        Event::BrushOpacity(0.75),
+        Event::ColorWheelToggle,
+        Event::LightingToggle,
+        Event::Tool(Tool::MagicWand),
+        Event::CommitTransform,
+        Event::CancelTransform,
+        Event::Undo,
+        Event::Resampling(Resampling::Bicubic),
+        Event::SelectAll,
+        Event::InvertSelection,
+        Event::FeatherSelection(4),
+        Event::GrowSelection(2),
+        Event::ShrinkSelection(3),
+        Event::SymmetryMode(SymmetryMode::Both),
+        Event::SymmetryMode(SymmetryMode::Radial(6)),
+        Event::SymmetryCenter([-3.0, 40.25]),
+        Event::Resize(SIZE),
+        Event::NewDocument,
+        Event::CloseDocument,
+        Event::Document(2),
+        Event::LayerVisible(CanvasLayer::Paper, false),
+        Event::LayerOpacity(CanvasLayer::Paint, 0.5),
+        Event::MouseAt {
+            brush_down: false,
+            pos: [0.0, 99.0],
+        },
+        Event::SymmetryMode(SymmetryMode::Off),
+        Event::Unrecorded,
+    ];
+
+    #[test]
+    fn events_read_back() {
+        for event in EVENTS {
+            let text = event.to_string();
+            let words: Vec<_> = text.split_whitespace().collect();
+            assert_eq!(Event::parse(&words), Some(event), "{text}");
+        }
+        let mut log = EventLog::new(SIZE);
+        for (millis, event) in (0..).step_by(16).zip(EVENTS) {
+            log.push(Duration::from_millis(millis), event);
+        }
+        let mut text = vec![];
+        log.write(&mut text).unwrap();
+        assert_eq!(EventLog::read(text.as_slice()).unwrap(), log);
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        let read = |text: &str| EventLog::read(text.as_bytes());
+        assert_eq!(read("size 200 100\n\n0 undo\n").unwrap().events().len(), 1);
+        for text in [
+            "",
+            "200 100\n",
+            "size 200\n",
+            "size 200 100\n0 redo\n",
+            "size 200 100\nsoon undo\n",
+            "size 200 100\n0 tool pen\n",
+            "size 200 100\n0 mouse_at 1 x 3\n",
+            "size 200 100\n0 select_all now\n",
+        ] {
+            let err = read(text).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{text:?}");
+        }
+        assert_eq!(
+            read("size 200 100\n0 undo\n5 brush_kind felt\n")
+                .unwrap_err()
+                .to_string(),
+            "invalid event log line 3"
+        );
+    }
+
+    #[test]
+    fn resizing_scales_positions() {
+        let mut log = EventLog::new(SIZE);
+        let at = |pos| Event::MouseAt {
+            brush_down: true,
+            pos,
+        };
+        log.push(Duration::ZERO, at([100.0, 50.0]));
+        log.push(Duration::from_millis(10), Event::GrowSelection(4));
+        log.push(
+            Duration::from_millis(20),
+            Event::Resize(PhysicalSize {
+                width: 100,
+                height: 100,
+            }),
+        );
+        log.push(
+            Duration::from_millis(30),
+            Event::SymmetryCenter([50.0, 25.0]),
+        );
+        log.push(Duration::from_millis(40), Event::Undo);
+        let resized = log.resized(PhysicalSize {
+            width: 400,
+            height: 50,
+        });
+        let events: Vec<_> = resized
+            .events()
+            .iter()
+            .map(|timed| (timed.time.as_millis(), timed.event))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (0, at([200.0, 25.0])),
+                (10, Event::GrowSelection(8)),
+                (30, Event::SymmetryCenter([200.0, 12.5])),
+                (40, Event::Undo),
+            ]
+        );
+        assert_eq!(resized.size().width, 400);
+    }
+}
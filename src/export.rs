@@ -0,0 +1,179 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use wgpu::{Device, Extent3d, Queue, Texture};
+
+use crate::{canvas::PhysicalSize, error::CanvasError};
+
+#[must_use]
+pub const fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    let unpadded_bytes_per_row = width * bytes_per_pixel;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded_bytes_per_row + (align - unpadded_bytes_per_row % align) % align
+}
+
+/// Copies a whole texture into memory, dropping the row padding.
+///
+/// # Errors
+///
+/// Returns an error if the texture cannot be read back, e.g. when the device
+/// is lost meanwhile.
+pub fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    texture: &Texture,
+    size: PhysicalSize,
+    bytes_per_pixel: u32,
+) -> Result<Vec<u8>, CanvasError> {
+    let padded_bytes_per_row = padded_bytes_per_row(size.width, bytes_per_pixel);
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("export buffer"),
+        size: u64::from(padded_bytes_per_row) * u64::from(size.height),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("export encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+    let buffer_slice = buffer.slice(..);
+    let (tx, rx) = futures::channel::oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        // the receiver is waited on below, so it is still there.
+        let _ = tx.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    // a dropped callback never mapped the buffer.
+    futures::executor::block_on(rx).map_err(|_| wgpu::BufferAsyncError)??;
+    let unpadded_bytes_per_row = (size.width * bytes_per_pixel) as usize;
+    let data = buffer_slice
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..unpadded_bytes_per_row])
+        .copied()
+        .collect();
+    buffer.unmap();
+    Ok(data)
+}
+
+/// Encodes tightly packed 8 bit pixels as png.
+///
+/// # Errors
+///
+/// Returns an error if the image cannot be encoded or written.
+pub fn encode_png(
+    writer: impl Write,
+    size: PhysicalSize,
+    color_type: png::ColorType,
+    data: &[u8],
+) -> std::io::Result<()> {
+    let mut encoder = png::Encoder::new(writer, size.width, size.height);
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// sRGB encoded value of a linear color channel, both `0.0..=1.0`.
+#[must_use]
+pub fn linear_to_srgb(channel: f32) -> f32 {
+    let channel = channel.clamp(0.0, 1.0);
+    if channel <= 0.003_130_8 {
+        channel * 12.92
+    } else {
+        1.055f32.mul_add(channel.powf(1.0 / 2.4), -0.055)
+    }
+}
+
+/// Linear value of an sRGB encoded color channel, both `0.0..=1.0`.
+#[must_use]
+pub fn srgb_to_linear(channel: f32) -> f32 {
+    let channel = channel.clamp(0.0, 1.0);
+    if channel <= 0.040_45 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Decodes a png of any color type as straight rgba.
+///
+/// # Errors
+///
+/// Returns an error if the png cannot be read or decoded.
+pub fn decode_png(reader: impl Read) -> std::io::Result<(PhysicalSize, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let pixels = buffer[..info.buffer_size()]
+        .chunks(info.color_type.samples())
+        .flat_map(|pixel| match *pixel {
+            [red, green, blue, alpha] => [red, green, blue, alpha],
+            [red, green, blue] => [red, green, blue, u8::MAX],
+            [gray, alpha] => [gray, gray, gray, alpha],
+            [gray, ..] => [gray, gray, gray, u8::MAX],
+            [] => [0; 4],
+        })
+        .collect();
+    let size = PhysicalSize {
+        width: info.width,
+        height: info.height,
+    };
+    Ok((size, pixels))
+}
+
+/// Opens a buffered file for writing.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be created.
+pub fn create_file(path: &Path) -> std::io::Result<BufWriter<File>> {
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+/// `painting.png` becomes `painting_height.png`.
+#[must_use]
+pub fn height_map_path(path: &Path) -> PathBuf {
+    let extension = path
+        .extension()
+        .map_or_else(|| "png".into(), |ext| ext.to_string_lossy());
+    suffixed_path(path, "_height", &extension)
+}
+
+/// `frames/painting.png` becomes `frames/painting_0007.png` for frame 7,
+/// with a `.png` extension whatever the extension of `path`.
+#[must_use]
+pub fn frame_path(path: &Path, index: usize) -> PathBuf {
+    suffixed_path(path, &format!("_{index:04}"), "png")
+}
+
+/// `path` with `suffix` after the file stem and the `extension`.
+fn suffixed_path(path: &Path, suffix: &str, extension: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(Default::default, |stem| stem.to_string_lossy());
+    path.with_file_name(format!("{stem}{suffix}.{extension}"))
+}
@@ -0,0 +1,12 @@
+const guide_color = vec4<f32>(0.2, 0.6, 1.0, 0.6);
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    // same scale as the paint shader.
+    return vec4<f32>(position, 0.5, 0.5);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return guide_color;
+}
@@ -0,0 +1,614 @@
+use std::collections::HashMap;
+
+use egui::{
+    epaint::{ImageDelta, Primitive, Vertex},
+    Align2, ClippedPrimitive, Color32, CursorIcon, ImageData, Rect, Response, Rgba, Sense, Slider,
+    TextEdit, TextureFilter, TextureId, TexturesDelta, Ui,
+};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState,
+    Buffer, BufferBindingType, BufferUsages, Device, Extent3d, FragmentState, IndexFormat, LoadOp,
+    MultisampleState, Operations, Origin3d, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, ShaderModuleDescriptor, ShaderSource, ShaderStages, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureViewDimension, VertexState,
+};
+use winit::{
+    event::WindowEvent,
+    event_loop::EventLoopWindowTarget,
+    window::{Theme, Window},
+};
+
+use crate::{
+    brush::{Brush, BrushKind},
+    canvas::{Canvas, CanvasLayer, Overlay, OverlayFrame, PhysicalSize},
+    color::{self, ColorModel},
+    colorwheel::Grip,
+    tool::Tool,
+};
+
+const TOOLS: [(Tool, &str); 6] = [
+    (Tool::Brush, "Brush"),
+    (Tool::RectSelect, "Rectangle"),
+    (Tool::EllipseSelect, "Ellipse"),
+    (Tool::Lasso, "Lasso"),
+    (Tool::MagicWand, "Magic wand"),
+    (Tool::Transform, "Transform"),
+];
+
+const BRUSH_KINDS: [(BrushKind, &str); 4] = [
+    (BrushKind::Round, "Round"),
+    (BrushKind::Impasto, "Impasto"),
+    (BrushKind::Pencil, "Pencil"),
+    (BrushKind::Charcoal, "Charcoal"),
+];
+
+/// the topmost layer first.
+const LAYERS: [(CanvasLayer, &str); 2] =
+    [(CanvasLayer::Paint, "Paint"), (CanvasLayer::Paper, "Paper")];
+
+/// Widgets over the canvas showing the tool, brush and layers, drawn with
+/// egui in the same frame as the painting.
+pub struct Hud {
+    context: egui::Context,
+    state: egui_winit::State,
+    /// created with the device of the first frame drawn.
+    renderer: Option<Renderer>,
+    /// laid out by the last update, drawn with the next frame.
+    primitives: Vec<ClippedPrimitive>,
+    /// texture changes not drawn yet.
+    textures: TexturesDelta,
+    picker: ColorPicker,
+    visible: bool,
+}
+
+/// Sliders and a hex field for the brush color, besides the color wheel.
+#[derive(Default)]
+struct ColorPicker {
+    model: ColorModel,
+    /// kept between frames, so the hue stays where it was dragged when the
+    /// color turns gray.
+    channels: [f32; 3],
+    /// the brush color the channels were read from or set.
+    color: Option<[f32; 3]>,
+    /// the text being typed, `#rrggbb` of the color otherwise.
+    hex: String,
+}
+
+impl Hud {
+    #[must_use]
+    pub fn new<T>(event_loop: &EventLoopWindowTarget<T>, window: &Window) -> Self {
+        let context = egui::Context::default();
+        if window.theme() == Some(Theme::Light) {
+            context.set_visuals(egui::Visuals::light());
+        }
+        let mut state = egui_winit::State::new(event_loop);
+        state.set_pixels_per_point(window.scale_factor() as f32);
+        Self {
+            context,
+            state,
+            renderer: None,
+            primitives: vec![],
+            textures: TexturesDelta::default(),
+            picker: ColorPicker::default(),
+            visible: true,
+        }
+    }
+
+    /// Passes a window event to the widgets, returns whether they need to be
+    /// redrawn.
+    pub fn on_event(&mut self, event: &WindowEvent<'_>) -> bool {
+        self.state.on_event(&self.context, event).repaint
+    }
+
+    /// Whether the mouse is over the widgets or dragging one, so it should
+    /// not paint.
+    #[must_use]
+    pub fn wants_pointer(&self) -> bool {
+        self.visible && self.context.wants_pointer_input()
+    }
+
+    /// Whether a widget takes the key presses, so they are not actions.
+    #[must_use]
+    pub fn wants_keyboard(&self) -> bool {
+        self.visible && self.context.wants_keyboard_input()
+    }
+
+    pub const fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Lays the widgets out for the next frame, applying what was changed
+    /// with them to `canvas`, and sets the cursor. Returns whether they
+    /// animate and need another frame.
+    pub fn update(&mut self, window: &Window, canvas: &mut Canvas) -> bool {
+        let input = self.state.take_egui_input(window);
+        let visible = self.visible;
+        let picker = &mut self.picker;
+        let output = self.context.run(input, |context| {
+            if visible {
+                brush_window(context, canvas, picker);
+                layers_window(context, canvas);
+            }
+        });
+        let mut platform_output = output.platform_output;
+        if !self.wants_pointer() {
+            platform_output.cursor_icon = canvas_cursor(canvas);
+        }
+        self.state
+            .handle_platform_output(window, &self.context, platform_output);
+        self.primitives = self.context.tessellate(output.shapes);
+        self.textures.append(output.textures_delta);
+        output.repaint_after.is_zero()
+    }
+}
+
+impl Overlay for Hud {
+    fn draw(&mut self, mut frame: OverlayFrame<'_>) {
+        if frame.device_recreated && self.renderer.take().is_some() {
+            // the font texture is only sent again when the fonts change.
+            self.context.set_fonts(egui::FontDefinitions::default());
+            self.context.request_repaint();
+        }
+        let renderer = self
+            .renderer
+            .get_or_insert_with(|| Renderer::new(frame.device, frame.format));
+        for (id, delta) in &self.textures.set {
+            renderer.set_texture(frame.device, frame.queue, *id, delta);
+        }
+        renderer.draw(
+            &mut frame,
+            &self.primitives,
+            self.context.pixels_per_point(),
+        );
+        for id in &self.textures.free {
+            renderer.textures.remove(id);
+        }
+        self.textures.clear();
+    }
+}
+
+/// The tools, and the size, opacity, kind and color of the brush.
+fn brush_window(context: &egui::Context, canvas: &mut Canvas, picker: &mut ColorPicker) {
+    egui::Window::new("Brush")
+        .anchor(Align2::LEFT_TOP, [8.0, 8.0])
+        .resizable(false)
+        .show(context, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (tool, name) in TOOLS {
+                    if ui.selectable_label(canvas.tool() == tool, name).clicked()
+                        && canvas.tool() != tool
+                    {
+                        canvas.set_tool(tool);
+                    }
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                for (kind, name) in BRUSH_KINDS {
+                    if ui
+                        .selectable_label(canvas.brush_kind() == kind, name)
+                        .clicked()
+                    {
+                        canvas.set_brush_kind(kind);
+                    }
+                }
+            });
+            let width = canvas.size().width as f32;
+            let mut size = canvas.brush_radius() * width;
+            let sizes = Brush::BRUSH_MIN * width..=Brush::BRUSH_MAX * width;
+            let slider = Slider::new(&mut size, sizes)
+                .logarithmic(true)
+                .suffix(" px")
+                .text("Size");
+            if ui.add(slider).changed() {
+                canvas.set_brush_radius(size / width);
+            }
+            let mut opacity = canvas.brush_opacity();
+            if ui
+                .add(Slider::new(&mut opacity, 0.0..=1.0).text("Opacity"))
+                .changed()
+            {
+                canvas.set_brush_opacity(opacity);
+            }
+            ui.horizontal(|ui| {
+                if swatch(ui, canvas.brush_color()).clicked() {
+                    canvas.color_wheel_toggle();
+                }
+                ui.label("Color");
+            });
+            picker.show(ui, canvas);
+        });
+}
+
+impl ColorPicker {
+    /// The channels of the brush color in the chosen model, and its hex
+    /// code. Both set the brush color as they change.
+    fn show(&mut self, ui: &mut Ui, canvas: &mut Canvas) {
+        ui.horizontal(|ui| {
+            for model in ColorModel::ALL {
+                if ui
+                    .selectable_label(self.model == model, model.name())
+                    .clicked()
+                    && self.model != model
+                {
+                    self.model = model;
+                    self.color = None;
+                }
+            }
+        });
+        if self.color != Some(canvas.brush_color()) {
+            self.channels = self.model.to_channels(canvas.brush_color());
+            self.color = Some(canvas.brush_color());
+        }
+        let mut changed = false;
+        for (channel, (name, max)) in self.channels.iter_mut().zip(self.model.channels()) {
+            let slider = Slider::new(channel, 0.0..=max)
+                .max_decimals(if max > 1.0 { 0 } else { 3 })
+                .text(name);
+            changed |= ui.add(slider).changed();
+        }
+        if changed {
+            canvas.set_brush_color(self.model.from_channels(self.channels));
+            self.color = Some(canvas.brush_color());
+        }
+        ui.horizontal(|ui| {
+            let edit = ui.add(TextEdit::singleline(&mut self.hex).desired_width(64.0));
+            if edit.changed() {
+                if let Some(color) = color::from_hex(&self.hex) {
+                    canvas.set_brush_color(color);
+                }
+            }
+            if !edit.has_focus() {
+                self.hex = color::to_hex(canvas.brush_color());
+            }
+            ui.label("Hex");
+        });
+    }
+}
+
+/// A rectangle of the linear `color`, clicked to pick another one.
+fn swatch(ui: &mut Ui, [r, g, b]: [f32; 3]) -> Response {
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(48.0, 20.0), Sense::click());
+    ui.painter()
+        .rect_filled(rect, 2.0, Color32::from(Rgba::from_rgb(r, g, b)));
+    response.on_hover_text("Toggle the color wheel")
+}
+
+/// Shows, hides and fades the paint and the paper.
+fn layers_window(context: &egui::Context, canvas: &mut Canvas) {
+    egui::Window::new("Layers")
+        .anchor(Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+        .resizable(false)
+        .show(context, |ui| {
+            for (layer, name) in LAYERS {
+                let style = canvas.layer_style(layer);
+                ui.horizontal(|ui| {
+                    let mut visible = style.visible;
+                    if ui.checkbox(&mut visible, name).changed() {
+                        canvas.set_layer_visible(layer, visible);
+                    }
+                    let mut opacity = style.opacity;
+                    if ui.add(Slider::new(&mut opacity, 0.0..=1.0)).changed() {
+                        canvas.set_layer_opacity(layer, opacity);
+                    }
+                });
+            }
+        });
+}
+
+/// The cursor of the tool, hidden while the brush outline stands in for it.
+fn canvas_cursor(canvas: &Canvas) -> CursorIcon {
+    if canvas.is_color_wheel_enabled() {
+        match canvas.color_wheel_grip() {
+            Some(Grip::Move { .. }) => CursorIcon::Grab,
+            Some(Grip::Resize) => CursorIcon::ResizeNwSe,
+            _ => CursorIcon::PointingHand,
+        }
+    } else if canvas.shows_brush_outline() {
+        CursorIcon::None
+    } else if canvas.tool() == Tool::Transform {
+        CursorIcon::Move
+    } else {
+        CursorIcon::Crosshair
+    }
+}
+
+/// Draws the triangles egui tessellates.
+struct Renderer {
+    pipeline: RenderPipeline,
+    /// size of the frame in points.
+    screen_buffer: Buffer,
+    screen_bind_group: BindGroup,
+    texture_layout: BindGroupLayout,
+    linear_sampler: Sampler,
+    nearest_sampler: Sampler,
+    textures: HashMap<TextureId, (Texture, BindGroup)>,
+}
+
+impl Renderer {
+    fn new(device: &Device, format: TextureFormat) -> Self {
+        let screen_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("hud screen layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let texture_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("hud texture layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let screen_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("hud screen buffer"),
+            size: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let screen_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("hud screen bind group"),
+            layout: &screen_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: screen_buffer.as_entire_binding(),
+            }],
+        });
+        let sampler = |filter| {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("hud sampler"),
+                mag_filter: filter,
+                min_filter: filter,
+                ..wgpu::SamplerDescriptor::default()
+            })
+        };
+        Self {
+            pipeline: Self::create_pipeline(device, format, &screen_layout, &texture_layout),
+            screen_buffer,
+            screen_bind_group,
+            texture_layout,
+            linear_sampler: sampler(wgpu::FilterMode::Linear),
+            nearest_sampler: sampler(wgpu::FilterMode::Nearest),
+            textures: HashMap::new(),
+        }
+    }
+
+    fn create_pipeline(
+        device: &Device,
+        format: TextureFormat,
+        screen_layout: &BindGroupLayout,
+        texture_layout: &BindGroupLayout,
+    ) -> RenderPipeline {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("hud shader"),
+            source: ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!("hud.wgsl"))),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("hud layout"),
+            bind_group_layouts: &[screen_layout, texture_layout],
+            push_constant_ranges: &[],
+        });
+        // egui colors are premultiplied.
+        let premultiplied = BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::OneMinusSrcAlpha,
+            operation: BlendOperation::Add,
+        };
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("hud pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x2,
+                        1 => Float32x2,
+                        2 => Unorm8x4,
+                    ],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(BlendState {
+                        color: premultiplied,
+                        alpha: premultiplied,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// Creates the texture `id` or updates a part of it.
+    fn set_texture(&mut self, device: &Device, queue: &Queue, id: TextureId, delta: &ImageDelta) {
+        let pixels: Vec<Color32> = match &delta.image {
+            ImageData::Color(image) => image.pixels.clone(),
+            ImageData::Font(image) => image.srgba_pixels(None).collect(),
+        };
+        let [width, height] = delta.image.size().map(|length| length as u32);
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let origin = delta.pos.map_or(Origin3d::ZERO, |[x, y]| Origin3d {
+            x: x as u32,
+            y: y as u32,
+            z: 0,
+        });
+        if delta.pos.is_none() {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some("hud texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let sampler = match delta.options.magnification {
+                TextureFilter::Linear => &self.linear_sampler,
+                TextureFilter::Nearest => &self.nearest_sampler,
+            };
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: Some("hud texture bind group"),
+                layout: &self.texture_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(
+                            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                        ),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            });
+            self.textures.insert(id, (texture, bind_group));
+        }
+        // a part of a texture created with a lost device is dropped.
+        let Some((texture, _)) = self.textures.get(&id) else {
+            return;
+        };
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&pixels),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(width * 4),
+                rows_per_image: None,
+            },
+            size,
+        );
+    }
+
+    /// Draws the meshes of `primitives` over the frame, clipped to their
+    /// rectangles.
+    fn draw(
+        &self,
+        frame: &mut OverlayFrame<'_>,
+        primitives: &[ClippedPrimitive],
+        pixels_per_point: f32,
+    ) {
+        let meshes: Vec<_> = primitives
+            .iter()
+            .filter_map(|clipped| match &clipped.primitive {
+                Primitive::Mesh(mesh) => Some((clipped.clip_rect, mesh)),
+                Primitive::Callback(_) => None,
+            })
+            .collect();
+        if meshes.is_empty() {
+            return;
+        }
+        let mut vertices: Vec<Vertex> = vec![];
+        let mut indices: Vec<u32> = vec![];
+        for (_, mesh) in &meshes {
+            let first_vertex = vertices.len() as u32;
+            indices.extend(mesh.indices.iter().map(|index| first_vertex + index));
+            vertices.extend_from_slice(&mesh.vertices);
+        }
+        let vertex_buffer = frame.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("hud vertex buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = frame.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("hud index buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: BufferUsages::INDEX,
+        });
+        let screen = [
+            frame.size.width as f32 / pixels_per_point,
+            frame.size.height as f32 / pixels_per_point,
+        ];
+        frame
+            .queue
+            .write_buffer(&self.screen_buffer, 0, bytemuck::cast_slice(&screen));
+        let mut rpass = frame.encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("hud pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: frame.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.screen_bind_group, &[]);
+        rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        rpass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
+        let mut first_index = 0;
+        for (clip_rect, mesh) in meshes {
+            let index_count = mesh.indices.len() as u32;
+            let texture = self.textures.get(&mesh.texture_id);
+            if let (Some([x, y, width, height]), Some((_, bind_group))) =
+                (scissor(clip_rect, pixels_per_point, frame.size), texture)
+            {
+                rpass.set_scissor_rect(x, y, width, height);
+                rpass.set_bind_group(1, bind_group, &[]);
+                rpass.draw_indexed(first_index..first_index + index_count, 0, 0..1);
+            }
+            first_index += index_count;
+        }
+    }
+}
+
+/// `clip_rect` in points as `[x, y, width, height]` pixels of a frame of
+/// `size`, `None` if none of it is inside.
+#[allow(clippy::cast_sign_loss)]
+fn scissor(clip_rect: Rect, pixels_per_point: f32, size: PhysicalSize) -> Option<[u32; 4]> {
+    let pixel = |points: f32, length: u32| {
+        (points * pixels_per_point)
+            .round()
+            .clamp(0.0, length as f32) as u32
+    };
+    let [left, right] = [clip_rect.min.x, clip_rect.max.x].map(|x| pixel(x, size.width));
+    let [top, bottom] = [clip_rect.min.y, clip_rect.max.y].map(|y| pixel(y, size.height));
+    (right > left && bottom > top).then_some([left, top, right - left, bottom - top])
+}
@@ -0,0 +1,730 @@
+use std::{collections::HashMap, fmt, fs, io, path::Path, str::FromStr};
+
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    brush::BrushKind, canvas::Canvas, symmetry::SymmetryMode, tool::Tool, transform::Resampling,
+};
+
+/// pixels the selection grows, shrinks or feathers by per key press.
+const SELECTION_STEP: usize = 2;
+
+/// What a key or mouse button does in the winit and web binaries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// held to paint, or to use the current tool.
+    Paint,
+    IncBrushSize,
+    DecBrushSize,
+    BrushKind(BrushKind),
+    Tool(Tool),
+    Commit,
+    Cancel,
+    Undo,
+    ToggleResampling,
+    SelectAll,
+    InvertSelection,
+    FeatherSelection,
+    GrowSelection,
+    ShrinkSelection,
+    CycleSymmetry,
+    MoreRadialCopies,
+    FewerRadialCopies,
+    MoveSymmetryCenter,
+    ToggleLighting,
+    ToggleColorWheel,
+    /// shows or hides the tool, brush and layer widgets.
+    ToggleHud,
+    Export {
+        with_height_map: bool,
+    },
+    ExportStrokes,
+    ExportSvg,
+    SaveOra,
+    SavePsd,
+    Copy,
+    Paste,
+    NewDocument,
+    CloseDocument,
+    NextDocument,
+    PreviousDocument,
+}
+
+/// Names of the actions in the config file.
+const ACTIONS: [(Action, &str); 41] = [
+    (Action::Paint, "paint"),
+    (Action::IncBrushSize, "inc_brush_size"),
+    (Action::DecBrushSize, "dec_brush_size"),
+    (Action::BrushKind(BrushKind::Round), "brush_round"),
+    (Action::BrushKind(BrushKind::Impasto), "brush_impasto"),
+    (Action::BrushKind(BrushKind::Pencil), "brush_pencil"),
+    (Action::BrushKind(BrushKind::Charcoal), "brush_charcoal"),
+    (Action::Tool(Tool::Brush), "tool_brush"),
+    (Action::Tool(Tool::RectSelect), "tool_rect_select"),
+    (Action::Tool(Tool::EllipseSelect), "tool_ellipse_select"),
+    (Action::Tool(Tool::Lasso), "tool_lasso"),
+    (Action::Tool(Tool::MagicWand), "tool_magic_wand"),
+    (Action::Tool(Tool::Transform), "tool_transform"),
+    (Action::Commit, "commit"),
+    (Action::Cancel, "cancel"),
+    (Action::Undo, "undo"),
+    (Action::ToggleResampling, "toggle_resampling"),
+    (Action::SelectAll, "select_all"),
+    (Action::InvertSelection, "invert_selection"),
+    (Action::FeatherSelection, "feather_selection"),
+    (Action::GrowSelection, "grow_selection"),
+    (Action::ShrinkSelection, "shrink_selection"),
+    (Action::CycleSymmetry, "cycle_symmetry"),
+    (Action::MoreRadialCopies, "more_radial_copies"),
+    (Action::FewerRadialCopies, "fewer_radial_copies"),
+    (Action::MoveSymmetryCenter, "move_symmetry_center"),
+    (Action::ToggleLighting, "toggle_lighting"),
+    (Action::ToggleColorWheel, "toggle_color_wheel"),
+    (Action::ToggleHud, "toggle_hud"),
+    (
+        Action::Export {
+            with_height_map: false,
+        },
+        "export",
+    ),
+    (
+        Action::Export {
+            with_height_map: true,
+        },
+        "export_with_height_map",
+    ),
+    (Action::ExportStrokes, "export_strokes"),
+    (Action::ExportSvg, "export_svg"),
+    (Action::SaveOra, "save_ora"),
+    (Action::SavePsd, "save_psd"),
+    (Action::Copy, "copy"),
+    (Action::Paste, "paste"),
+    (Action::NewDocument, "new_document"),
+    (Action::CloseDocument, "close_document"),
+    (Action::NextDocument, "next_document"),
+    (Action::PreviousDocument, "previous_document"),
+];
+
+impl Action {
+    /// Applies an action changing the painting or tools, the same in every
+    /// binary. `pointer` is where the mouse is in the window. Returns
+    /// whether the canvas needs to be redrawn, `None` for the actions left
+    /// to the binary, e.g. files, the clipboard and documents.
+    pub fn apply(self, canvas: &mut Canvas, pointer: Option<[f32; 2]>) -> Option<bool> {
+        match self {
+            Self::IncBrushSize => canvas.inc_brush_size(),
+            Self::DecBrushSize => canvas.dec_brush_size(),
+            Self::BrushKind(kind) => canvas.set_brush_kind(kind),
+            Self::Tool(tool) => canvas.set_tool(tool),
+            Self::Commit | Self::Cancel => {
+                if self == Self::Cancel {
+                    canvas.cancel_transform();
+                } else {
+                    canvas.commit_transform();
+                }
+                if canvas.tool() == Tool::Transform {
+                    canvas.set_tool(Tool::Brush);
+                }
+            }
+            Self::Undo => return Some(canvas.undo()),
+            Self::ToggleResampling => match canvas.resampling() {
+                Some(Resampling::Bilinear) => canvas.set_resampling(Resampling::Bicubic),
+                Some(Resampling::Bicubic) => canvas.set_resampling(Resampling::Bilinear),
+                None => {}
+            },
+            Self::SelectAll => canvas.select_all(),
+            Self::InvertSelection => canvas.invert_selection(),
+            Self::FeatherSelection => canvas.feather_selection(SELECTION_STEP),
+            Self::GrowSelection => canvas.grow_selection(SELECTION_STEP),
+            Self::ShrinkSelection => canvas.shrink_selection(SELECTION_STEP),
+            Self::CycleSymmetry => canvas.set_symmetry_mode(canvas.symmetry_mode().next()),
+            Self::MoreRadialCopies | Self::FewerRadialCopies => {
+                let SymmetryMode::Radial(count) = canvas.symmetry_mode() else {
+                    return Some(false);
+                };
+                canvas.set_symmetry_mode(SymmetryMode::Radial(if self == Self::MoreRadialCopies {
+                    count + 1
+                } else {
+                    count - 1
+                }));
+            }
+            Self::MoveSymmetryCenter => {
+                let Some(pos) = pointer else {
+                    return Some(false);
+                };
+                canvas.set_symmetry_center(pos);
+            }
+            Self::ToggleLighting => canvas.lighting_toggle(),
+            Self::ToggleColorWheel => canvas.color_wheel_toggle(),
+            _ => return None,
+        }
+        Some(true)
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(action, _)| *action == self)
+            .map_or("", |(_, name)| name)
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name)| *action_name == name)
+            .map(|(action, _)| *action)
+    }
+}
+
+/// Names of the keys in the config file.
+const KEYS: [(VirtualKeyCode, &str); 78] = [
+    (VirtualKeyCode::A, "a"),
+    (VirtualKeyCode::B, "b"),
+    (VirtualKeyCode::C, "c"),
+    (VirtualKeyCode::D, "d"),
+    (VirtualKeyCode::E, "e"),
+    (VirtualKeyCode::F, "f"),
+    (VirtualKeyCode::G, "g"),
+    (VirtualKeyCode::H, "h"),
+    (VirtualKeyCode::I, "i"),
+    (VirtualKeyCode::J, "j"),
+    (VirtualKeyCode::K, "k"),
+    (VirtualKeyCode::L, "l"),
+    (VirtualKeyCode::M, "m"),
+    (VirtualKeyCode::N, "n"),
+    (VirtualKeyCode::O, "o"),
+    (VirtualKeyCode::P, "p"),
+    (VirtualKeyCode::Q, "q"),
+    (VirtualKeyCode::R, "r"),
+    (VirtualKeyCode::S, "s"),
+    (VirtualKeyCode::T, "t"),
+    (VirtualKeyCode::U, "u"),
+    (VirtualKeyCode::V, "v"),
+    (VirtualKeyCode::W, "w"),
+    (VirtualKeyCode::X, "x"),
+    (VirtualKeyCode::Y, "y"),
+    (VirtualKeyCode::Z, "z"),
+    (VirtualKeyCode::Key0, "0"),
+    (VirtualKeyCode::Key1, "1"),
+    (VirtualKeyCode::Key2, "2"),
+    (VirtualKeyCode::Key3, "3"),
+    (VirtualKeyCode::Key4, "4"),
+    (VirtualKeyCode::Key5, "5"),
+    (VirtualKeyCode::Key6, "6"),
+    (VirtualKeyCode::Key7, "7"),
+    (VirtualKeyCode::Key8, "8"),
+    (VirtualKeyCode::Key9, "9"),
+    (VirtualKeyCode::F1, "f1"),
+    (VirtualKeyCode::F2, "f2"),
+    (VirtualKeyCode::F3, "f3"),
+    (VirtualKeyCode::F4, "f4"),
+    (VirtualKeyCode::F5, "f5"),
+    (VirtualKeyCode::F6, "f6"),
+    (VirtualKeyCode::F7, "f7"),
+    (VirtualKeyCode::F8, "f8"),
+    (VirtualKeyCode::F9, "f9"),
+    (VirtualKeyCode::F10, "f10"),
+    (VirtualKeyCode::F11, "f11"),
+    (VirtualKeyCode::F12, "f12"),
+    (VirtualKeyCode::Escape, "escape"),
+    (VirtualKeyCode::Return, "enter"),
+    (VirtualKeyCode::Space, "space"),
+    (VirtualKeyCode::Tab, "tab"),
+    (VirtualKeyCode::Back, "backspace"),
+    (VirtualKeyCode::Delete, "delete"),
+    (VirtualKeyCode::Insert, "insert"),
+    (VirtualKeyCode::Home, "home"),
+    (VirtualKeyCode::End, "end"),
+    (VirtualKeyCode::PageUp, "page_up"),
+    (VirtualKeyCode::PageDown, "page_down"),
+    (VirtualKeyCode::Left, "left"),
+    (VirtualKeyCode::Right, "right"),
+    (VirtualKeyCode::Up, "up"),
+    (VirtualKeyCode::Down, "down"),
+    (VirtualKeyCode::Plus, "plus"),
+    (VirtualKeyCode::Minus, "minus"),
+    (VirtualKeyCode::Equals, "equals"),
+    (VirtualKeyCode::Period, "period"),
+    (VirtualKeyCode::Comma, "comma"),
+    (VirtualKeyCode::Slash, "slash"),
+    (VirtualKeyCode::Backslash, "backslash"),
+    (VirtualKeyCode::Semicolon, "semicolon"),
+    (VirtualKeyCode::Apostrophe, "apostrophe"),
+    (VirtualKeyCode::Grave, "grave"),
+    (VirtualKeyCode::LBracket, "left_bracket"),
+    (VirtualKeyCode::RBracket, "right_bracket"),
+    (VirtualKeyCode::NumpadAdd, "numpad_add"),
+    (VirtualKeyCode::NumpadSubtract, "numpad_subtract"),
+    (VirtualKeyCode::NumpadEnter, "numpad_enter"),
+];
+
+/// Names of the mouse buttons, other buttons are `mouse_<number>`.
+const MOUSE_BUTTONS: [(usize, &str); 3] =
+    [(0, "mouse_left"), (1, "mouse_right"), (2, "mouse_middle")];
+
+/// A key or mouse button, mouse buttons numbered like `winit_input_helper`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(VirtualKeyCode),
+    Mouse(usize),
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Key(code) => {
+                let name = KEYS.iter().find(|(key, _)| *key == code);
+                write!(f, "{}", name.map_or("unknown", |(_, name)| name))
+            }
+            Self::Mouse(index) => match MOUSE_BUTTONS.iter().find(|(i, _)| *i == index) {
+                Some((_, name)) => write!(f, "{name}"),
+                None => write!(f, "mouse_{index}"),
+            },
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if let Some((code, _)) = KEYS.iter().find(|(_, key)| *key == name) {
+            return Ok(Self::Key(*code));
+        }
+        if let Some((index, _)) = MOUSE_BUTTONS.iter().find(|(_, button)| *button == name) {
+            return Ok(Self::Mouse(*index));
+        }
+        name.strip_prefix("mouse_")
+            .and_then(|index| index.parse().ok())
+            .filter(|index| (3..255).contains(index))
+            .map(Self::Mouse)
+            .ok_or_else(|| format!("unknown key {name}"))
+    }
+}
+
+/// Modifier keys held with an input.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Self = Self {
+        ctrl: false,
+        shift: false,
+        alt: false,
+    };
+}
+
+/// An input with its modifiers, written like `ctrl+shift+s`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Binding {
+    pub input: Input,
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    #[must_use]
+    pub const fn key(code: VirtualKeyCode) -> Self {
+        Self {
+            input: Input::Key(code),
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    #[must_use]
+    pub const fn mouse(index: usize) -> Self {
+        Self {
+            input: Input::Mouse(index),
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    #[must_use]
+    pub const fn ctrl(mut self) -> Self {
+        self.modifiers.ctrl = true;
+        self
+    }
+
+    #[must_use]
+    pub const fn shift(mut self) -> Self {
+        self.modifiers.shift = true;
+        self
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers { ctrl, shift, alt } = self.modifiers;
+        for (held, name) in [(ctrl, "ctrl"), (shift, "shift"), (alt, "alt")] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    /// Parses modifiers followed by a key, case insensitive.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim().to_ascii_lowercase();
+        let mut parts: Vec<_> = text.split('+').map(str::trim).collect();
+        let input = parts.pop().unwrap_or_default().parse()?;
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            let held = match part {
+                "ctrl" | "control" => &mut modifiers.ctrl,
+                "shift" => &mut modifiers.shift,
+                "alt" => &mut modifiers.alt,
+                _ => return Err(format!("unknown modifier {part} in {text}")),
+            };
+            if *held {
+                return Err(format!("{part} twice in {text}"));
+            }
+            *held = true;
+        }
+        Ok(Self { input, modifiers })
+    }
+}
+
+/// The bindings of the winit binary before any config is loaded.
+const DEFAULT_BINDINGS: [(Action, Binding); 41] = [
+    (Action::Paint, Binding::mouse(0)),
+    (Action::IncBrushSize, Binding::key(VirtualKeyCode::Plus)),
+    (Action::DecBrushSize, Binding::key(VirtualKeyCode::Minus)),
+    (
+        Action::BrushKind(BrushKind::Round),
+        Binding::key(VirtualKeyCode::Key1),
+    ),
+    (
+        Action::BrushKind(BrushKind::Impasto),
+        Binding::key(VirtualKeyCode::Key2),
+    ),
+    (
+        Action::BrushKind(BrushKind::Pencil),
+        Binding::key(VirtualKeyCode::Key3),
+    ),
+    (
+        Action::BrushKind(BrushKind::Charcoal),
+        Binding::key(VirtualKeyCode::Key4),
+    ),
+    (Action::Tool(Tool::Brush), Binding::key(VirtualKeyCode::B)),
+    (
+        Action::Tool(Tool::RectSelect),
+        Binding::key(VirtualKeyCode::R),
+    ),
+    (
+        Action::Tool(Tool::EllipseSelect),
+        Binding::key(VirtualKeyCode::O),
+    ),
+    (Action::Tool(Tool::Lasso), Binding::key(VirtualKeyCode::F)),
+    (
+        Action::Tool(Tool::MagicWand),
+        Binding::key(VirtualKeyCode::W),
+    ),
+    (
+        Action::Tool(Tool::Transform),
+        Binding::key(VirtualKeyCode::T),
+    ),
+    (Action::Commit, Binding::key(VirtualKeyCode::Return)),
+    (Action::Cancel, Binding::key(VirtualKeyCode::Escape)),
+    (Action::Undo, Binding::key(VirtualKeyCode::Z).ctrl()),
+    (Action::ToggleResampling, Binding::key(VirtualKeyCode::Q)),
+    (Action::SelectAll, Binding::key(VirtualKeyCode::D)),
+    (Action::InvertSelection, Binding::key(VirtualKeyCode::I)),
+    (Action::FeatherSelection, Binding::key(VirtualKeyCode::G)),
+    (
+        Action::GrowSelection,
+        Binding::key(VirtualKeyCode::RBracket),
+    ),
+    (
+        Action::ShrinkSelection,
+        Binding::key(VirtualKeyCode::LBracket),
+    ),
+    (Action::CycleSymmetry, Binding::key(VirtualKeyCode::M)),
+    (
+        Action::MoreRadialCopies,
+        Binding::key(VirtualKeyCode::Period),
+    ),
+    (
+        Action::FewerRadialCopies,
+        Binding::key(VirtualKeyCode::Comma),
+    ),
+    (Action::MoveSymmetryCenter, Binding::key(VirtualKeyCode::C)),
+    (Action::ToggleLighting, Binding::key(VirtualKeyCode::L)),
+    (
+        Action::ToggleColorWheel,
+        Binding::key(VirtualKeyCode::Space),
+    ),
+    (Action::ToggleHud, Binding::key(VirtualKeyCode::H)),
+    (
+        Action::Export {
+            with_height_map: false,
+        },
+        Binding::key(VirtualKeyCode::E),
+    ),
+    (
+        Action::Export {
+            with_height_map: true,
+        },
+        Binding::key(VirtualKeyCode::E).shift(),
+    ),
+    (
+        Action::ExportStrokes,
+        Binding::key(VirtualKeyCode::E).ctrl(),
+    ),
+    (Action::ExportSvg, Binding::key(VirtualKeyCode::V)),
+    (Action::SaveOra, Binding::key(VirtualKeyCode::S).ctrl()),
+    (
+        Action::SavePsd,
+        Binding::key(VirtualKeyCode::S).ctrl().shift(),
+    ),
+    (Action::Copy, Binding::key(VirtualKeyCode::C).ctrl()),
+    (Action::Paste, Binding::key(VirtualKeyCode::V).ctrl()),
+    (Action::NewDocument, Binding::key(VirtualKeyCode::N).ctrl()),
+    (
+        Action::CloseDocument,
+        Binding::key(VirtualKeyCode::W).ctrl(),
+    ),
+    (
+        Action::NextDocument,
+        Binding::key(VirtualKeyCode::Tab).ctrl(),
+    ),
+    (
+        Action::PreviousDocument,
+        Binding::key(VirtualKeyCode::Tab).ctrl().shift(),
+    ),
+];
+
+/// Which action each binding triggers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<Binding, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT_BINDINGS
+                .iter()
+                .map(|(action, binding)| (*binding, *action))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// The defaults with the actions in the `[bindings]` table of a toml
+    /// config rebound, e.g. `undo = "ctrl+z"`, `paint = ["mouse_left", "p"]`
+    /// or `export_svg = []` to unbind it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for invalid toml, unknown actions, keys or
+    /// modifiers, and for a binding used by two actions.
+    pub fn parse(config: &str) -> io::Result<Self> {
+        let table: toml::Table = config.parse().map_err(invalid)?;
+        let mut keymap = Self::default();
+        let Some(bindings) = table.get("bindings") else {
+            return Ok(keymap);
+        };
+        let bindings = bindings
+            .as_table()
+            .ok_or_else(|| invalid("bindings is not a table"))?;
+        let mut rebound = vec![];
+        for (name, value) in bindings {
+            let action =
+                Action::from_name(name).ok_or_else(|| invalid(format!("unknown action {name}")))?;
+            let texts = match value {
+                toml::Value::String(text) => vec![text.as_str()],
+                toml::Value::Array(values) => values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .ok_or_else(|| invalid(format!("{name} is not a list of keys")))
+                    })
+                    .collect::<io::Result<_>>()?,
+                _ => return Err(invalid(format!("{name} is not a key or a list of keys"))),
+            };
+            keymap.bindings.retain(|_, bound| *bound != action);
+            for text in texts {
+                rebound.push((action, text.parse::<Binding>().map_err(invalid)?));
+            }
+        }
+        for (action, binding) in rebound {
+            keymap.bind(binding, action)?;
+        }
+        Ok(keymap)
+    }
+
+    /// Reads a toml config, see [`Self::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is invalid.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Binds `binding` to `action`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binding is used by another action.
+    pub fn bind(&mut self, binding: Binding, action: Action) -> io::Result<()> {
+        match self.bindings.insert(binding, action) {
+            Some(other) if other != action => {
+                self.bindings.insert(binding, other);
+                Err(invalid(format!(
+                    "{binding} is bound to both {} and {}",
+                    other.name(),
+                    action.name()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The action `input` triggers with the `held` modifiers. A binding
+    /// without shift also applies with shift held, unless shift selects
+    /// another action.
+    #[must_use]
+    pub fn action(&self, input: Input, held: Modifiers) -> Option<Action> {
+        let binding = Binding {
+            input,
+            modifiers: held,
+        };
+        self.bindings.get(&binding).copied().or_else(|| {
+            let unshifted = Modifiers {
+                shift: false,
+                ..held
+            };
+            self.bindings
+                .get(&Binding {
+                    modifiers: unshifted,
+                    ..binding
+                })
+                .copied()
+        })
+    }
+
+    /// Every bound input, each once.
+    pub fn inputs(&self) -> impl Iterator<Item = Input> + '_ {
+        let mut inputs: Vec<_> = self.bindings.keys().map(|binding| binding.input).collect();
+        inputs.sort_by_key(ToString::to_string);
+        inputs.dedup();
+        inputs.into_iter()
+    }
+
+    /// The bindings of `action`.
+    pub fn bindings_of(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, bound)| **bound == action)
+            .map(|(binding, _)| *binding)
+    }
+}
+
+fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_the_old_keys() {
+        let keymap = Keymap::default();
+        let none = Modifiers::default();
+        let ctrl = Modifiers { ctrl: true, ..none };
+        let shift = Modifiers {
+            shift: true,
+            ..none
+        };
+        assert_eq!(
+            keymap.action(Input::Key(VirtualKeyCode::Space), none),
+            Some(Action::ToggleColorWheel)
+        );
+        assert_eq!(
+            keymap.action(Input::Key(VirtualKeyCode::Plus), shift),
+            Some(Action::IncBrushSize)
+        );
+        assert_eq!(
+            keymap.action(Input::Key(VirtualKeyCode::E), shift),
+            Some(Action::Export {
+                with_height_map: true
+            })
+        );
+        assert_eq!(
+            keymap.action(
+                Input::Key(VirtualKeyCode::E),
+                Modifiers {
+                    shift: true,
+                    ..ctrl
+                }
+            ),
+            Some(Action::ExportStrokes)
+        );
+        assert_eq!(
+            keymap.action(Input::Key(VirtualKeyCode::C), ctrl),
+            Some(Action::Copy)
+        );
+        assert_eq!(keymap.action(Input::Key(VirtualKeyCode::S), none), None);
+        assert_eq!(keymap.action(Input::Mouse(0), none), Some(Action::Paint));
+    }
+
+    #[test]
+    fn config_rebinds_actions() {
+        let keymap = Keymap::parse(
+            r#"
+            [bindings]
+            undo = ["ctrl+z", "Backspace"]
+            paint = "mouse_right"
+            export_svg = []
+            "#,
+        )
+        .unwrap();
+        let none = Modifiers::default();
+        assert_eq!(
+            keymap.action(Input::Key(VirtualKeyCode::Back), none),
+            Some(Action::Undo)
+        );
+        assert_eq!(keymap.action(Input::Mouse(0), none), None);
+        assert_eq!(keymap.action(Input::Mouse(1), none), Some(Action::Paint));
+        assert_eq!(keymap.action(Input::Key(VirtualKeyCode::V), none), None);
+        assert_eq!(keymap.bindings_of(Action::Undo).count(), 2);
+    }
+
+    #[test]
+    fn conflicts_are_errors() {
+        let err = Keymap::parse("[bindings]\nundo = \"space\"").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "space is bound to both toggle_color_wheel and undo"
+        );
+        assert!(Keymap::parse("[bindings]\nundo = \"hyper+z\"").is_err());
+        assert!(Keymap::parse("[bindings]\nredo = \"y\"").is_err());
+        assert!(Keymap::parse("[bindings]\nundo = \"ctrl+\"").is_err());
+    }
+
+    #[test]
+    fn bindings_read_back() {
+        for text in [
+            "ctrl+shift+s",
+            "alt+mouse_7",
+            "left_bracket",
+            "mouse_middle",
+        ] {
+            assert_eq!(text.parse::<Binding>().unwrap().to_string(), text);
+        }
+    }
+}
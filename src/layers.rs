@@ -0,0 +1,299 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use crate::{canvas::PhysicalSize, export, ora, psd};
+
+/// How a layer's colors mix with the layers below it, as in the W3C
+/// compositing spec used by `OpenRaster` and most painting applications.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    /// sum of the colors, clamped to white.
+    Add,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+impl BlendMode {
+    /// Mixes a `source` color over a `backdrop` color, both `0.0..=1.0`.
+    #[must_use]
+    pub fn blend(self, backdrop: [f32; 3], source: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::Hue => set_lum(set_sat(source, sat(backdrop)), lum(backdrop)),
+            Self::Saturation => set_lum(set_sat(backdrop, sat(source)), lum(backdrop)),
+            Self::Color => set_lum(source, lum(backdrop)),
+            Self::Luminosity => set_lum(backdrop, lum(source)),
+            separable => [0, 1, 2].map(|i| separable.blend_channel(backdrop[i], source[i])),
+        }
+    }
+
+    fn blend_channel(self, backdrop: f32, source: f32) -> f32 {
+        let multiply = |b: f32, s: f32| b * s;
+        let screen = |b: f32, s: f32| b.mul_add(-s, b + s);
+        let hard_light = |b: f32, s: f32| {
+            if s <= 0.5 {
+                multiply(b, 2.0 * s)
+            } else {
+                screen(b, 2.0f32.mul_add(s, -1.0))
+            }
+        };
+        match self {
+            Self::Multiply => multiply(backdrop, source),
+            Self::Screen => screen(backdrop, source),
+            Self::Overlay => hard_light(source, backdrop),
+            Self::Darken => backdrop.min(source),
+            Self::Lighten => backdrop.max(source),
+            Self::ColorDodge => {
+                if backdrop <= 0.0 {
+                    0.0
+                } else if source >= 1.0 {
+                    1.0
+                } else {
+                    (backdrop / (1.0 - source)).min(1.0)
+                }
+            }
+            Self::ColorBurn => {
+                if backdrop >= 1.0 {
+                    1.0
+                } else if source <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - backdrop) / source).min(1.0)
+                }
+            }
+            Self::HardLight => hard_light(backdrop, source),
+            Self::SoftLight => {
+                let darkened = if backdrop <= 0.25 {
+                    16.0f32.mul_add(backdrop, -12.0).mul_add(backdrop, 4.0) * backdrop
+                } else {
+                    backdrop.sqrt()
+                };
+                if source <= 0.5 {
+                    (2.0f32.mul_add(-source, 1.0) * backdrop).mul_add(-(1.0 - backdrop), backdrop)
+                } else {
+                    2.0f32
+                        .mul_add(source, -1.0)
+                        .mul_add(darkened - backdrop, backdrop)
+                }
+            }
+            Self::Difference => (backdrop - source).abs(),
+            Self::Exclusion => (2.0 * backdrop).mul_add(-source, backdrop + source),
+            Self::Add => (backdrop + source).min(1.0),
+            Self::Normal | Self::Hue | Self::Saturation | Self::Color | Self::Luminosity => source,
+        }
+    }
+}
+
+fn lum([r, g, b]: [f32; 3]) -> f32 {
+    0.3f32.mul_add(r, 0.59f32.mul_add(g, 0.11 * b))
+}
+
+fn set_lum(color: [f32; 3], lum_to: f32) -> [f32; 3] {
+    let delta = lum_to - lum(color);
+    let color = color.map(|channel| channel + delta);
+    let l = lum(color);
+    let min = color.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = color.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    color.map(|channel| {
+        if min < 0.0 {
+            l + (channel - l) * l / (l - min)
+        } else if max > 1.0 {
+            l + (channel - l) * (1.0 - l) / (max - l)
+        } else {
+            channel
+        }
+    })
+}
+
+fn sat(color: [f32; 3]) -> f32 {
+    let min = color.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = color.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    max - min
+}
+
+fn set_sat(color: [f32; 3], sat_to: f32) -> [f32; 3] {
+    let min = color.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = color.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    color.map(|channel| {
+        if max > min {
+            (channel - min) * sat_to / (max - min)
+        } else {
+            0.0
+        }
+    })
+}
+
+/// One layer of a [`LayeredImage`], straight (not premultiplied) sRGB rgba.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub size: PhysicalSize,
+    pub pixels: Vec<u8>,
+    /// position of the top left corner in the image.
+    pub offset: [i32; 2],
+    pub opacity: f32,
+    pub visible: bool,
+    pub blend_mode: BlendMode,
+}
+
+impl Layer {
+    /// A visible, opaque layer of normal blending at the top left.
+    #[must_use]
+    pub fn new(name: impl Into<String>, size: PhysicalSize, pixels: Vec<u8>) -> Self {
+        Self {
+            name: name.into(),
+            size,
+            pixels,
+            offset: [0, 0],
+            opacity: 1.0,
+            visible: true,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+
+    /// Loads a png image as a layer named after the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or decoded.
+    pub fn from_png(path: &Path) -> io::Result<Self> {
+        let (size, pixels) = export::decode_png(BufReader::new(File::open(path)?))?;
+        let name = path
+            .file_stem()
+            .map_or_else(Default::default, |stem| stem.to_string_lossy());
+        Ok(Self::new(name, size, pixels))
+    }
+}
+
+/// A stack of layers as exchanged with other painting applications.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayeredImage {
+    pub size: PhysicalSize,
+    /// bottom layer first.
+    pub layers: Vec<Layer>,
+}
+
+impl LayeredImage {
+    /// Composites the visible layers into straight sRGB rgba of `size`,
+    /// transparent where no layer covers it.
+    #[must_use]
+    pub fn flatten(&self) -> Vec<u8> {
+        self.flatten_at(self.size)
+    }
+
+    /// Like [`Self::flatten`] but cropped or extended to `size`.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn flatten_at(&self, size: PhysicalSize) -> Vec<u8> {
+        let (width, height) = (size.width as usize, size.height as usize);
+        let mut flat = vec![[0.0f32; 4]; width * height];
+        for layer in self
+            .layers
+            .iter()
+            .filter(|layer| layer.visible && layer.size.width > 0)
+        {
+            let opacity = layer.opacity.clamp(0.0, 1.0);
+            let layer_width = layer.size.width as usize;
+            for (layer_y, row) in layer.pixels.chunks_exact(layer_width * 4).enumerate() {
+                let Some(y) = Self::shifted(layer_y, layer.offset[1], height) else {
+                    continue;
+                };
+                for (layer_x, pixel) in row.chunks_exact(4).enumerate() {
+                    let Some(x) = Self::shifted(layer_x, layer.offset[0], width) else {
+                        continue;
+                    };
+                    let target = &mut flat[y * width + x];
+                    *target = Self::composite(*target, pixel, opacity, layer.blend_mode);
+                }
+            }
+        }
+        flat.iter()
+            .flatten()
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect()
+    }
+
+    /// `position` moved by `offset`, if it is still within `0..length`.
+    fn shifted(position: usize, offset: i32, length: usize) -> Option<usize> {
+        let shifted = position.checked_add_signed(isize::try_from(offset).ok()?)?;
+        (shifted < length).then_some(shifted)
+    }
+
+    /// Straight rgba `backdrop` with a `pixel` of a layer blended over it.
+    fn composite(backdrop: [f32; 4], pixel: &[u8], opacity: f32, mode: BlendMode) -> [f32; 4] {
+        let source = [0, 1, 2].map(|i| f32::from(pixel[i]) / 255.0);
+        let source_alpha = f32::from(pixel[3]) / 255.0 * opacity;
+        let [red, green, blue, backdrop_alpha] = backdrop;
+        let backdrop = [red, green, blue];
+        let alpha = backdrop_alpha.mul_add(1.0 - source_alpha, source_alpha);
+        if alpha <= 0.0 {
+            return [0.0; 4];
+        }
+        let blended = mode.blend(backdrop, source);
+        let [red, green, blue] = [0, 1, 2].map(|i| {
+            // the blend only applies where there is a backdrop.
+            let mixed = backdrop_alpha.mul_add(blended[i] - source[i], source[i]);
+            (source_alpha * mixed + backdrop_alpha * backdrop[i] * (1.0 - source_alpha)) / alpha
+        });
+        [red, green, blue, alpha]
+    }
+}
+
+/// A file read into layers, with what of it could not be kept.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Imported {
+    pub image: LayeredImage,
+    /// features of the file which were dropped or approximated.
+    pub warnings: Vec<String>,
+}
+
+/// Reads an `.ora` or `.psd` file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is invalid or of another
+/// format.
+pub fn load(path: &Path) -> io::Result<Imported> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("ora") => ora::load(path),
+        Some(ext) if ext.eq_ignore_ascii_case("psd") => psd::load(path),
+        _ => Err(unknown_format(path)),
+    }
+}
+
+/// Writes an `.ora` or `.psd` file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written or is of another format.
+pub fn save(path: &Path, image: &LayeredImage) -> io::Result<()> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("ora") => ora::save(path, image),
+        Some(ext) if ext.eq_ignore_ascii_case("psd") => psd::save(path, image),
+        _ => Err(unknown_format(path)),
+    }
+}
+
+fn unknown_format(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} is not an .ora or .psd file", path.display()),
+    )
+}
@@ -7,4 +7,29 @@
 
 pub mod brush;
 pub mod canvas;
+#[cfg(feature = "gui")]
+pub mod clipboard;
+pub mod color;
 pub mod colorwheel;
+pub mod error;
+pub mod event_log;
+pub mod export;
+#[cfg(feature = "gui")]
+pub mod hud;
+#[cfg(any(feature = "gui", feature = "webui"))]
+pub mod keymap;
+pub mod layers;
+pub mod ora;
+pub mod paper;
+pub mod psd;
+#[cfg(feature = "script")]
+pub mod script;
+pub mod selection;
+#[cfg(feature = "gui")]
+pub mod settings;
+pub mod stroke;
+pub mod svg;
+pub mod symmetry;
+pub mod timelapse;
+pub mod tool;
+pub mod transform;
@@ -0,0 +1,379 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufReader, Read, Seek, Write},
+    path::Path,
+};
+
+use quick_xml::{escape::escape, events::BytesStart, events::Event, Reader};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    canvas::PhysicalSize,
+    export,
+    layers::{BlendMode, Imported, Layer, LayeredImage},
+};
+
+/// [OpenRaster](https://www.openraster.org) names of the blend modes.
+const COMPOSITE_OPS: [(BlendMode, &str); 17] = [
+    (BlendMode::Normal, "svg:src-over"),
+    (BlendMode::Multiply, "svg:multiply"),
+    (BlendMode::Screen, "svg:screen"),
+    (BlendMode::Overlay, "svg:overlay"),
+    (BlendMode::Darken, "svg:darken"),
+    (BlendMode::Lighten, "svg:lighten"),
+    (BlendMode::ColorDodge, "svg:color-dodge"),
+    (BlendMode::ColorBurn, "svg:color-burn"),
+    (BlendMode::HardLight, "svg:hard-light"),
+    (BlendMode::SoftLight, "svg:soft-light"),
+    (BlendMode::Difference, "svg:difference"),
+    (BlendMode::Exclusion, "svg:exclusion"),
+    (BlendMode::Add, "svg:plus"),
+    (BlendMode::Hue, "svg:hue"),
+    (BlendMode::Saturation, "svg:saturation"),
+    (BlendMode::Color, "svg:color"),
+    (BlendMode::Luminosity, "svg:luminosity"),
+];
+const MIMETYPE: &str = "image/openraster";
+const THUMBNAIL_SIDE: u32 = 256;
+
+fn composite_op(mode: BlendMode) -> &'static str {
+    COMPOSITE_OPS
+        .iter()
+        .find(|(blend_mode, _)| *blend_mode == mode)
+        .map_or("svg:src-over", |(_, name)| name)
+}
+
+fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Writes `image` as an `OpenRaster` zip: `stack.xml`, one png per layer, the
+/// flattened `mergedimage.png` and a thumbnail.
+///
+/// # Errors
+///
+/// Returns an error if the writer fails.
+pub fn write(writer: impl Write + Seek, image: &LayeredImage) -> io::Result<()> {
+    let mut zip = ZipWriter::new(writer);
+    // pngs are compressed already, the mimetype must be stored first.
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+
+    zip.start_file("stack.xml", FileOptions::default())?;
+    writeln!(zip, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        zip,
+        r#"<image version="0.0.3" w="{}" h="{}">"#,
+        image.size.width, image.size.height
+    )?;
+    writeln!(zip, "<stack>")?;
+    // the stack lists the topmost layer first.
+    for (index, layer) in image.layers.iter().enumerate().rev() {
+        writeln!(
+            zip,
+            r#"<layer name="{}" src="data/layer{index}.png" x="{}" y="{}" opacity="{}" visibility="{}" composite-op="{}"/>"#,
+            escape(layer.name.as_str()),
+            layer.offset[0],
+            layer.offset[1],
+            layer.opacity.clamp(0.0, 1.0),
+            if layer.visible { "visible" } else { "hidden" },
+            composite_op(layer.blend_mode),
+        )?;
+    }
+    writeln!(zip, "</stack>\n</image>")?;
+
+    for (index, layer) in image.layers.iter().enumerate() {
+        zip.start_file(format!("data/layer{index}.png"), stored)?;
+        export::encode_png(&mut zip, layer.size, png::ColorType::Rgba, &layer.pixels)?;
+    }
+    let merged = image.flatten();
+    zip.start_file("mergedimage.png", stored)?;
+    export::encode_png(&mut zip, image.size, png::ColorType::Rgba, &merged)?;
+    let (thumbnail_size, thumbnail) = thumbnail(image.size, &merged);
+    zip.start_file("Thumbnails/thumbnail.png", stored)?;
+    export::encode_png(&mut zip, thumbnail_size, png::ColorType::Rgba, &thumbnail)?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// Nearest neighbor downscale to at most `THUMBNAIL_SIDE` pixels.
+fn thumbnail(size: PhysicalSize, pixels: &[u8]) -> (PhysicalSize, Vec<u8>) {
+    let scale = (THUMBNAIL_SIDE as f32 / size.width.max(size.height) as f32).min(1.0);
+    #[allow(clippy::cast_sign_loss)]
+    let fit = |length: u32| ((length as f32 * scale) as u32).max(1);
+    let thumbnail_size = PhysicalSize {
+        width: fit(size.width),
+        height: fit(size.height),
+    };
+    let pixels = (0..thumbnail_size.height)
+        .flat_map(|y| (0..thumbnail_size.width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let source_x = (x * size.width / thumbnail_size.width) as usize;
+            let source_y = (y * size.height / thumbnail_size.height) as usize;
+            let index = (source_y * size.width as usize + source_x) * 4;
+            pixels[index..index + 4].iter().copied()
+        })
+        .collect();
+    (thumbnail_size, pixels)
+}
+
+/// Attributes of a `<stack>` the layers inside it inherit.
+#[derive(Copy, Clone)]
+struct Group {
+    offset: [i32; 2],
+    opacity: f32,
+    visible: bool,
+}
+
+/// Reads the layers of an `OpenRaster` zip.
+///
+/// Nested stacks are merged into one list of layers, their opacity,
+/// visibility and position applied to the layers inside them. Unknown
+/// blend modes become normal blending, both are reported as warnings.
+///
+/// # Errors
+///
+/// Returns an error if the zip, `stack.xml` or a layer png is invalid.
+pub fn read(reader: impl Read + Seek) -> io::Result<Imported> {
+    let mut zip = ZipArchive::new(reader)?;
+    let mut xml = String::new();
+    zip.by_name("stack.xml")?.read_to_string(&mut xml)?;
+
+    let mut size = None;
+    let mut groups = vec![];
+    let mut layers = vec![];
+    let mut warnings = vec![];
+    let mut reader = Reader::from_str(&xml);
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(element) | Event::Empty(element)
+                if element.name().as_ref() == b"image" =>
+            {
+                size = Some(PhysicalSize {
+                    width: parse_attribute(&element, "w")?.ok_or_else(|| invalid("no w"))?,
+                    height: parse_attribute(&element, "h")?.ok_or_else(|| invalid("no h"))?,
+                });
+            }
+            Event::Start(element) if element.name().as_ref() == b"stack" => {
+                let parent = groups.last().copied().unwrap_or(Group {
+                    offset: [0, 0],
+                    opacity: 1.0,
+                    visible: true,
+                });
+                if !groups.is_empty() {
+                    warnings.push("nested stacks are merged into one list of layers".into());
+                }
+                groups.push(group_of(&element, parent)?);
+            }
+            Event::End(element) if element.name().as_ref() == b"stack" => {
+                groups.pop();
+            }
+            Event::Start(element) | Event::Empty(element)
+                if element.name().as_ref() == b"layer" =>
+            {
+                let group = groups
+                    .last()
+                    .copied()
+                    .ok_or_else(|| invalid("layer outside of a stack"))?;
+                layers.push(read_layer(&mut zip, &element, group, &mut warnings)?);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let mut seen = HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+    // the stack lists the topmost layer first.
+    layers.reverse();
+    let image = LayeredImage {
+        size: size.ok_or_else(|| invalid("no image element in stack.xml"))?,
+        layers,
+    };
+    Ok(Imported { image, warnings })
+}
+
+fn attribute(element: &BytesStart, name: &str) -> io::Result<Option<String>> {
+    element
+        .try_get_attribute(name)
+        .map_err(invalid)?
+        .map(|attribute| {
+            attribute
+                .unescape_value()
+                .map(std::borrow::Cow::into_owned)
+                .map_err(invalid)
+        })
+        .transpose()
+}
+
+fn parse_attribute<T: std::str::FromStr>(
+    element: &BytesStart,
+    name: &str,
+) -> io::Result<Option<T>> {
+    attribute(element, name)?
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("invalid {name} {value}")))
+        })
+        .transpose()
+}
+
+fn group_of(element: &BytesStart, parent: Group) -> io::Result<Group> {
+    let x: i32 = parse_attribute(element, "x")?.unwrap_or(0);
+    let y: i32 = parse_attribute(element, "y")?.unwrap_or(0);
+    let opacity: f32 = parse_attribute(element, "opacity")?.unwrap_or(1.0);
+    let offset = |parent: i32, offset: i32| {
+        parent
+            .checked_add(offset)
+            .ok_or_else(|| invalid("layer position out of range"))
+    };
+    Ok(Group {
+        offset: [offset(parent.offset[0], x)?, offset(parent.offset[1], y)?],
+        opacity: parent.opacity * opacity,
+        visible: parent.visible && attribute(element, "visibility")?.as_deref() != Some("hidden"),
+    })
+}
+
+fn read_layer(
+    zip: &mut ZipArchive<impl Read + Seek>,
+    element: &BytesStart,
+    group: Group,
+    warnings: &mut Vec<String>,
+) -> io::Result<Layer> {
+    let source = attribute(element, "src")?.ok_or_else(|| invalid("layer without src"))?;
+    let (size, pixels) = export::decode_png(BufReader::new(zip.by_name(&source)?))?;
+    let layer_group = group_of(element, group)?;
+    let op = attribute(element, "composite-op")?
+        .unwrap_or_else(|| composite_op(BlendMode::Normal).into());
+    let blend_mode = COMPOSITE_OPS
+        .iter()
+        .find(|(_, name)| *name == op)
+        .map_or_else(
+            || {
+                warnings.push(format!("blend mode {op} is drawn as normal"));
+                BlendMode::Normal
+            },
+            |(mode, _)| *mode,
+        );
+    Ok(Layer {
+        name: attribute(element, "name")?.unwrap_or_default(),
+        size,
+        pixels,
+        offset: layer_group.offset,
+        opacity: layer_group.opacity.clamp(0.0, 1.0),
+        visible: layer_group.visible,
+        blend_mode,
+    })
+}
+
+/// Writes `image` to an `.ora` file, see [`write`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn save(path: &Path, image: &LayeredImage) -> io::Result<()> {
+    let mut file = export::create_file(path)?;
+    write(&mut file, image)?;
+    file.flush()
+}
+
+/// Reads an `.ora` file, see [`read`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is invalid.
+pub fn load(path: &Path) -> io::Result<Imported> {
+    read(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const SIZE: PhysicalSize = PhysicalSize {
+        width: 3,
+        height: 2,
+    };
+
+    fn layer(name: &str, seed: u8) -> Layer {
+        let pixels = (0..SIZE.width * SIZE.height * 4)
+            .map(|index| seed.wrapping_add(index as u8))
+            .collect();
+        Layer::new(name, SIZE, pixels)
+    }
+
+    /// An ora zip of `stack`, with every layer reading `layer.png`.
+    fn zip_with_stack(stack: &str) -> Cursor<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        zip.start_file("stack.xml", FileOptions::default()).unwrap();
+        zip.write_all(stack.as_bytes()).unwrap();
+        zip.start_file("layer.png", FileOptions::default()).unwrap();
+        let pixels = layer("", 0).pixels;
+        export::encode_png(&mut zip, SIZE, png::ColorType::Rgba, &pixels).unwrap();
+        let mut cursor = zip.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut lower = layer("Lower & <older>", 10);
+        lower.opacity = 0.25;
+        lower.blend_mode = BlendMode::Screen;
+        let mut upper = layer("Upper", 99);
+        upper.offset = [-4, 7];
+        upper.visible = false;
+        upper.blend_mode = BlendMode::Add;
+        let image = LayeredImage {
+            size: SIZE,
+            layers: vec![lower, upper],
+        };
+        let mut ora = Cursor::new(vec![]);
+        write(&mut ora, &image).unwrap();
+        ora.set_position(0);
+        let imported = read(ora).unwrap();
+        assert_eq!(imported.warnings, Vec::<String>::new());
+        assert_eq!(imported.image, image);
+    }
+
+    #[test]
+    fn applies_nested_stacks() {
+        let ora = zip_with_stack(
+            r#"<image w="3" h="2"><stack>
+                <stack x="10" y="-5" opacity="0.5" visibility="hidden">
+                    <stack x="1" y="2"><layer src="layer.png" x="3" y="4" opacity="0.5"/></stack>
+                </stack>
+                <layer src="layer.png" composite-op="svg:dst-in"/>
+            </stack></image>"#,
+        );
+        let imported = read(ora).unwrap();
+        let [bottom, top] = &imported.image.layers[..] else {
+            panic!("expected 2 layers");
+        };
+        assert_eq!(bottom.blend_mode, BlendMode::Normal);
+        assert_eq!(top.offset, [14, 1]);
+        assert_eq!(top.opacity, 0.25);
+        assert!(!top.visible);
+        assert_eq!(
+            imported.warnings,
+            [
+                "nested stacks are merged into one list of layers",
+                "blend mode svg:dst-in is drawn as normal"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_offsets_out_of_range() {
+        let ora = zip_with_stack(&format!(
+            r#"<image w="3" h="2"><stack x="{}"><layer src="layer.png" x="1"/></stack></image>"#,
+            i32::MAX
+        ));
+        assert_eq!(read(ora).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fs::File, path::Path};
+
+use crate::canvas::PhysicalSize;
+
+/// Paper grain shared by the dry media brushes and the canvas background.
+#[derive(Clone)]
+pub struct Paper {
+    size: PhysicalSize,
+    /// height of the paper tooth, 0 in the valleys and 255 on the peaks.
+    grain: Vec<u8>,
+    color: [f32; 3],
+}
+
+impl Default for Paper {
+    fn default() -> Self {
+        Self::procedural(Self::PROCEDURAL_SIZE, 0)
+    }
+}
+
+impl Paper {
+    const PROCEDURAL_SIZE: u32 = 256;
+    const COLOR: [f32; 3] = [0.97, 0.96, 0.92];
+
+    /// Tileable fractal value noise, `size` should be a power of two.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn procedural(size: u32, seed: u32) -> Self {
+        let octaves = [(32, 0.4), (16, 0.25), (8, 0.2), (4, 0.1), (2, 0.05)];
+        let grain = (0..size * size)
+            .map(|index| {
+                let (x, y) = (index % size, index / size);
+                let height: f32 = octaves
+                    .iter()
+                    .map(|&(cell, weight)| weight * Self::value_noise(x, y, cell, size, seed))
+                    .sum();
+                (height * f32::from(u8::MAX)) as u8
+            })
+            .collect();
+        Self {
+            size: PhysicalSize {
+                width: size,
+                height: size,
+            },
+            grain,
+            color: Self::COLOR,
+        }
+    }
+
+    /// Loads the grain from the luminance of a png image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or decoded.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn from_png(path: &Path) -> std::io::Result<Self> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let channels = info.color_type.samples();
+        let grain = buffer[..info.buffer_size()]
+            .chunks(channels)
+            .map(|pixel| match pixel {
+                [red, green, blue, ..] => {
+                    let luminance = 0.299f32.mul_add(
+                        f32::from(*red),
+                        0.587f32.mul_add(f32::from(*green), 0.114 * f32::from(*blue)),
+                    );
+                    luminance as u8
+                }
+                [gray, ..] => *gray,
+                [] => 0,
+            })
+            .collect();
+        Ok(Self {
+            size: PhysicalSize {
+                width: info.width,
+                height: info.height,
+            },
+            grain,
+            color: Self::COLOR,
+        })
+    }
+
+    #[must_use]
+    pub const fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    #[must_use]
+    pub fn grain(&self) -> &[u8] {
+        &self.grain
+    }
+
+    #[must_use]
+    pub const fn color(&self) -> [f32; 3] {
+        self.color
+    }
+
+    pub const fn set_color(&mut self, color: [f32; 3]) {
+        self.color = color;
+    }
+
+    fn hash(x: u32, y: u32, seed: u32) -> f32 {
+        let mut hash = x
+            .wrapping_mul(0x2745_937F)
+            .wrapping_add(y.wrapping_mul(0x27D4_EB2F))
+            .wrapping_add(seed.wrapping_mul(0x1656_67B1));
+        hash = (hash ^ (hash >> 15)).wrapping_mul(0x85EB_CA6B);
+        hash = (hash ^ (hash >> 13)).wrapping_mul(0xC2B2_AE35);
+        (hash ^ (hash >> 16)) as f32 / u32::MAX as f32
+    }
+
+    /// Smoothly interpolated lattice noise wrapping around at `size`.
+    fn value_noise(x: u32, y: u32, cell: u32, size: u32, seed: u32) -> f32 {
+        let cells = (size / cell).max(1);
+        let (cell_x, cell_y) = (x / cell, y / cell);
+        let smooth = |t: f32| t * t * 2.0f32.mul_add(-t, 3.0);
+        let fx = smooth((x % cell) as f32 / cell as f32);
+        let fy = smooth((y % cell) as f32 / cell as f32);
+        let corner = |dx: u32, dy: u32| {
+            Self::hash(
+                (cell_x + dx) % cells,
+                (cell_y + dy) % cells,
+                seed.wrapping_add(cell),
+            )
+        };
+        let top = fx.mul_add(corner(1, 0) - corner(0, 0), corner(0, 0));
+        let bottom = fx.mul_add(corner(1, 1) - corner(0, 1), corner(0, 1));
+        fy.mul_add(bottom - top, top)
+    }
+}
@@ -0,0 +1,820 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    canvas::PhysicalSize,
+    export,
+    layers::{BlendMode, Imported, Layer, LayeredImage},
+};
+
+/// Photoshop keys of the blend modes.
+const BLEND_KEYS: [(BlendMode, &[u8; 4]); 17] = [
+    (BlendMode::Normal, b"norm"),
+    (BlendMode::Multiply, b"mul "),
+    (BlendMode::Screen, b"scrn"),
+    (BlendMode::Overlay, b"over"),
+    (BlendMode::Darken, b"dark"),
+    (BlendMode::Lighten, b"lite"),
+    (BlendMode::ColorDodge, b"div "),
+    (BlendMode::ColorBurn, b"idiv"),
+    (BlendMode::HardLight, b"hLit"),
+    (BlendMode::SoftLight, b"sLit"),
+    (BlendMode::Difference, b"diff"),
+    (BlendMode::Exclusion, b"smud"),
+    (BlendMode::Add, b"lddg"),
+    (BlendMode::Hue, b"hue "),
+    (BlendMode::Saturation, b"sat "),
+    (BlendMode::Color, b"colr"),
+    (BlendMode::Luminosity, b"lum "),
+];
+/// additional layer info of layers drawn from settings rather than pixels.
+const ADJUSTMENT_KEYS: [&[u8; 4]; 21] = [
+    b"levl", b"curv", b"brit", b"hue2", b"hue ", b"blnc", b"phfl", b"mixr", b"selc", b"grdm",
+    b"expA", b"vibA", b"nvrt", b"post", b"thrs", b"clrL", b"blwh", b"SoCo", b"GdFl", b"PtFl",
+    b"brst",
+];
+const SIGNATURE: &[u8; 4] = b"8BPS";
+const RESOURCE_SIGNATURE: &[u8; 4] = b"8BIM";
+const RGB_MODE: u16 = 3;
+const HIDDEN_FLAG: u8 = 0x02;
+const ALPHA_CHANNEL: i16 = -1;
+const RAW: u16 = 0;
+const RLE: u16 = 1;
+/// largest width and height of a psd.
+const MAX_SIDE: u32 = 30_000;
+
+fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn blend_key(mode: BlendMode) -> &'static [u8; 4] {
+    BLEND_KEYS
+        .iter()
+        .find(|(blend_mode, _)| *blend_mode == mode)
+        .map_or(b"norm", |(_, key)| key)
+}
+
+/// Big endian reads from a part of the file.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(length)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid("unexpected end of file"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        self.array().map(i16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        self.array().map(i32::from_be_bytes)
+    }
+
+    /// The next `u32` length prefixed section.
+    fn section(&mut self) -> io::Result<Self> {
+        let length = self.u32()? as usize;
+        self.bytes(length).map(Self::new)
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+/// A layer record, the pixels follow all records.
+struct Record {
+    layer: Layer,
+    channels: Vec<(i16, usize)>,
+    /// `lsct` type: 1 and 2 close a group, 3 opens one.
+    section: u32,
+}
+
+/// Reads the layers of an 8 bit RGB(A) Photoshop file, or the flattened
+/// image if it has none.
+///
+/// Layer groups are merged into one list of layers with their opacity and
+/// visibility applied to the layers inside them. Masks, clipping, layer
+/// effects, adjustment, text and smart object layers and blend modes
+/// without an equivalent are reported as warnings and drawn as their pixels.
+///
+/// # Errors
+///
+/// Returns an error if the file is not a valid psd or not 8 bit RGB.
+pub fn read(mut reader: impl Read) -> io::Result<Imported> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    let mut cursor = Cursor::new(&data);
+    if cursor.bytes(4)? != SIGNATURE {
+        return Err(invalid("not a psd file"));
+    }
+    if cursor.u16()? != 1 {
+        return Err(invalid("large documents (psb) are not supported"));
+    }
+    cursor.bytes(6)?;
+    let channels = cursor.u16()?;
+    let height = cursor.u32()?;
+    let width = cursor.u32()?;
+    let depth = cursor.u16()?;
+    let mode = cursor.u16()?;
+    if depth != 8 {
+        return Err(invalid(format!(
+            "{depth} bit channels are not supported, only 8"
+        )));
+    }
+    if mode != RGB_MODE {
+        return Err(invalid("only RGB color is supported"));
+    }
+    let size = PhysicalSize { width, height };
+    check_size(size)?;
+    // color mode data and image resources, e.g. guides and color profiles.
+    cursor.section()?;
+    cursor.section()?;
+    let mut warnings = vec![];
+    let mut layers = read_layer_section(cursor.section()?, &mut warnings)?;
+    if layers.is_empty() {
+        let pixels = read_merged(&mut cursor, size, channels)?;
+        layers.push(Layer::new("Background", size, pixels));
+    }
+    let mut seen = HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+    Ok(Imported {
+        image: LayeredImage { size, layers },
+        warnings,
+    })
+}
+
+fn read_layer_section(mut section: Cursor, warnings: &mut Vec<String>) -> io::Result<Vec<Layer>> {
+    if section.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut info = section.section()?;
+    if info.is_empty() {
+        return Ok(vec![]);
+    }
+    let count = info.i16()?.unsigned_abs();
+    let records = (0..count)
+        .map(|_| read_record(&mut info, warnings))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut layers: Vec<Layer> = vec![];
+    // start of the open groups, records are listed bottom first.
+    let mut groups = vec![];
+    for mut record in records {
+        let size = record.layer.size;
+        let planes = record
+            .channels
+            .iter()
+            .map(|&(id, length)| {
+                let mut channel = Cursor::new(info.bytes(length)?);
+                if id < ALPHA_CHANNEL {
+                    warnings.push(format!("the mask of {} is ignored", record.layer.name));
+                    return Ok((id, vec![]));
+                }
+                read_channel(&mut channel, size).map(|plane| (id, plane))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        match record.section {
+            1 | 2 => {
+                warnings.push("layer groups are merged into one list of layers".into());
+                let start = groups.pop().unwrap_or(0);
+                for layer in &mut layers[start..] {
+                    layer.opacity *= record.layer.opacity;
+                    layer.visible &= record.layer.visible;
+                }
+                continue;
+            }
+            3 => {
+                groups.push(layers.len());
+                continue;
+            }
+            _ => {}
+        }
+        record.layer.pixels = interleave(&planes, size);
+        layers.push(record.layer);
+    }
+    Ok(layers)
+}
+
+fn read_record(info: &mut Cursor, warnings: &mut Vec<String>) -> io::Result<Record> {
+    let [top, left, bottom, right] = [info.i32()?, info.i32()?, info.i32()?, info.i32()?];
+    let length = |start: i32, end: i32| {
+        end.checked_sub(start)
+            .map(|length| u32::try_from(length).unwrap_or(0))
+            .ok_or_else(|| invalid("layer bounds out of range"))
+    };
+    let size = PhysicalSize {
+        width: length(left, right)?,
+        height: length(top, bottom)?,
+    };
+    check_size(size)?;
+    let channel_count = info.u16()?;
+    let channels = (0..channel_count)
+        .map(|_| Ok((info.i16()?, info.u32()? as usize)))
+        .collect::<io::Result<Vec<_>>>()?;
+    if info.bytes(4)? != RESOURCE_SIGNATURE {
+        return Err(invalid("invalid layer record"));
+    }
+    let key: [u8; 4] = info.array()?;
+    let opacity = f32::from(info.u8()?) / 255.0;
+    let clipping = info.u8()?;
+    let flags = info.u8()?;
+    info.u8()?;
+    let mut extra = info.section()?;
+    extra.section()?;
+    extra.section()?;
+    let name_length = usize::from(extra.u8()?);
+    let mut name: String = extra
+        .bytes(name_length)?
+        .iter()
+        .map(|&byte| char::from(byte))
+        .collect();
+    // the name is padded to a multiple of 4 with its length byte.
+    extra.bytes((4 - (name_length + 1) % 4) % 4)?;
+
+    let mut section = 0;
+    let mut fill_opacity = 1.0;
+    while !extra.is_empty() {
+        let signature = extra.bytes(4)?;
+        if signature != RESOURCE_SIGNATURE && signature != b"8B64" {
+            break;
+        }
+        let info_key: [u8; 4] = extra.array()?;
+        let length = extra.u32()? as usize;
+        let mut block = Cursor::new(extra.bytes(length)?);
+        extra.bytes(length % 2)?;
+        match &info_key {
+            b"luni" => {
+                let units = (0..block.u32()?)
+                    .map(|_| block.u16())
+                    .collect::<io::Result<Vec<_>>>()?;
+                name = String::from_utf16_lossy(&units);
+            }
+            b"lsct" | b"lsdk" => section = block.u32()?,
+            b"iOpa" => fill_opacity = f32::from(block.u8()?) / 255.0,
+            b"lfx2" | b"lrFX" => warnings.push(format!("the layer effects of {name} are ignored")),
+            b"TySh" | b"tySh" => warnings.push(format!("text layer {name} is kept as pixels")),
+            b"SoLd" | b"PlLd" | b"SoLE" => {
+                warnings.push(format!("smart object {name} is kept as pixels"));
+            }
+            key if ADJUSTMENT_KEYS.contains(&key) => {
+                warnings.push(format!("adjustment layer {name} is kept as pixels"));
+            }
+            _ => {}
+        }
+    }
+    let blend_mode = BLEND_KEYS
+        .iter()
+        .find(|(_, blend_key)| **blend_key == key)
+        .map(|(mode, _)| *mode);
+    // groups pass the blending through, their layers keep their own.
+    if blend_mode.is_none() && section == 0 {
+        warnings.push(format!(
+            "blend mode {} of {name} is drawn as normal",
+            String::from_utf8_lossy(&key).trim_end()
+        ));
+    }
+    if clipping != 0 && section == 0 {
+        warnings.push(format!("clipping of {name} is ignored"));
+    }
+    Ok(Record {
+        layer: Layer {
+            name,
+            size,
+            pixels: vec![],
+            offset: [left, top],
+            opacity: opacity * fill_opacity,
+            visible: flags & HIDDEN_FLAG == 0,
+            blend_mode: blend_mode.unwrap_or_default(),
+        },
+        channels,
+        section,
+    })
+}
+
+fn check_size(size: PhysicalSize) -> io::Result<()> {
+    if size.width > MAX_SIDE || size.height > MAX_SIDE {
+        return Err(invalid(format!(
+            "{}x{} is larger than a psd can be",
+            size.width, size.height
+        )));
+    }
+    Ok(())
+}
+
+/// One channel of a layer, raw or `PackBits` compressed.
+fn read_channel(channel: &mut Cursor, size: PhysicalSize) -> io::Result<Vec<u8>> {
+    let compression = channel.u16()?;
+    read_planes(channel, size, 1, 1, compression).map(|mut planes| planes.remove(0))
+}
+
+/// The first `decoded` of `count` planes of `size` which share one
+/// compression header.
+fn read_planes(
+    cursor: &mut Cursor,
+    size: PhysicalSize,
+    count: usize,
+    decoded: usize,
+    compression: u16,
+) -> io::Result<Vec<Vec<u8>>> {
+    let (width, height) = (size.width as usize, size.height as usize);
+    match compression {
+        RAW => (0..decoded)
+            .map(|_| cursor.bytes(width * height).map(<[u8]>::to_vec))
+            .collect(),
+        RLE => {
+            // the row lengths of every plane come before the first row.
+            let row_lengths = (0..count * height)
+                .map(|_| cursor.u16().map(usize::from))
+                .collect::<io::Result<Vec<_>>>()?;
+            let mut rows = row_lengths.into_iter();
+            (0..decoded)
+                .map(|_| {
+                    let mut plane = vec![];
+                    for length in rows.by_ref().take(height) {
+                        unpack_bits(cursor.bytes(length)?, width, &mut plane)?;
+                    }
+                    Ok(plane)
+                })
+                .collect()
+        }
+        compression => Err(invalid(format!(
+            "zip compressed channels ({compression}) are not supported"
+        ))),
+    }
+}
+
+/// Decodes a `PackBits` row of `width` bytes.
+fn unpack_bits(mut packed: &[u8], width: usize, plane: &mut Vec<u8>) -> io::Result<()> {
+    let end = plane.len() + width;
+    while let [header, rest @ ..] = packed {
+        let header = i8::from_be_bytes([*header]);
+        if header >= 0 {
+            let length = header.unsigned_abs() as usize + 1;
+            let literal = rest
+                .get(..length)
+                .ok_or_else(|| invalid("invalid rle data"))?;
+            plane.extend_from_slice(literal);
+            packed = &rest[length..];
+        } else if header > i8::MIN {
+            let &[byte, ref rest @ ..] = rest else {
+                return Err(invalid("invalid rle data"));
+            };
+            plane.extend(std::iter::repeat_n(
+                byte,
+                header.unsigned_abs() as usize + 1,
+            ));
+            packed = rest;
+        } else {
+            packed = rest;
+        }
+    }
+    if plane.len() != end {
+        return Err(invalid("rle row of the wrong length"));
+    }
+    Ok(())
+}
+
+/// Straight rgba from the planes of the red, green, blue and alpha ids.
+fn interleave(planes: &[(i16, Vec<u8>)], size: PhysicalSize) -> Vec<u8> {
+    let pixels = size.width as usize * size.height as usize;
+    let plane = |id: i16| {
+        planes
+            .iter()
+            .find(|(plane_id, plane)| *plane_id == id && plane.len() == pixels)
+    };
+    let [red, green, blue, alpha] = [0, 1, 2, ALPHA_CHANNEL].map(plane);
+    (0..pixels)
+        .flat_map(|index| {
+            let value = |plane: Option<&(i16, Vec<u8>)>, default| {
+                plane.map_or(default, |(_, plane)| plane[index])
+            };
+            [
+                value(red, 0),
+                value(green, 0),
+                value(blue, 0),
+                value(alpha, u8::MAX),
+            ]
+        })
+        .collect()
+}
+
+/// The flattened image stored after the layers.
+fn read_merged(cursor: &mut Cursor, size: PhysicalSize, channels: u16) -> io::Result<Vec<u8>> {
+    let compression = cursor.u16()?;
+    let count = usize::from(channels);
+    let planes = read_planes(cursor, size, count, count.min(4), compression)?;
+    let ids = [0, 1, 2, ALPHA_CHANNEL];
+    let planes: Vec<_> = ids.into_iter().zip(planes).collect();
+    Ok(interleave(&planes, size))
+}
+
+/// Big endian writes into a buffer.
+#[derive(Default)]
+struct Buffer(Vec<u8>);
+
+impl Buffer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn i16(&mut self, value: i16) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.0.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    /// `section` prefixed with its `u32` length.
+    fn section(&mut self, section: &Self) -> io::Result<()> {
+        let length = u32::try_from(section.0.len()).map_err(|_| invalid("too large for a psd"))?;
+        self.u32(length);
+        self.bytes(&section.0);
+        Ok(())
+    }
+}
+
+/// The channel ids written per layer, alpha first like Photoshop.
+const WRITTEN_CHANNELS: [(i16, usize); 4] = [(ALPHA_CHANNEL, 3), (0, 0), (1, 1), (2, 2)];
+
+/// Writes `image` as an 8 bit RGBA Photoshop file with `PackBits` compressed
+/// channels and the flattened image after the layers.
+///
+/// # Errors
+///
+/// Returns an error if the writer fails or the image is too large.
+pub fn write(mut writer: impl Write, image: &LayeredImage) -> io::Result<()> {
+    let mut psd = Buffer::default();
+    psd.bytes(SIGNATURE);
+    psd.u16(1);
+    psd.bytes(&[0; 6]);
+    psd.u16(4);
+    psd.u32(image.size.height);
+    psd.u32(image.size.width);
+    psd.u16(8);
+    psd.u16(RGB_MODE);
+    // no color mode data and image resources.
+    psd.u32(0);
+    psd.u32(0);
+
+    let mut info = Buffer::default();
+    let count = i16::try_from(image.layers.len()).map_err(|_| invalid("too many layers"))?;
+    // negative, the merged image has transparency.
+    info.i16(-count);
+    let channels = image
+        .layers
+        .iter()
+        .map(|layer| {
+            WRITTEN_CHANNELS
+                .iter()
+                .map(|&(_, offset)| {
+                    let mut channel = Buffer::default();
+                    write_rle(&mut channel, &[plane(&layer.pixels, offset)], layer.size)?;
+                    Ok(channel)
+                })
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    for (layer, channels) in image.layers.iter().zip(&channels) {
+        write_record(&mut info, layer, channels)?;
+    }
+    for channel in channels.iter().flatten() {
+        info.bytes(&channel.0);
+    }
+    if info.0.len() % 2 == 1 {
+        info.u8(0);
+    }
+    let mut layer_section = Buffer::default();
+    layer_section.section(&info)?;
+    // no global layer mask.
+    layer_section.u32(0);
+    psd.section(&layer_section)?;
+
+    let merged = image.flatten();
+    let planes = [0, 1, 2, 3].map(|offset| plane(&merged, offset));
+    write_rle(&mut psd, &planes, image.size)?;
+    writer.write_all(&psd.0)
+}
+
+/// Every fourth byte of `pixels` from `offset`.
+fn plane(pixels: &[u8], offset: usize) -> Vec<u8> {
+    pixels.iter().skip(offset).step_by(4).copied().collect()
+}
+
+/// `planes` of `size` `PackBits` compressed, the lengths of all their rows
+/// first.
+fn write_rle(buffer: &mut Buffer, planes: &[Vec<u8>], size: PhysicalSize) -> io::Result<()> {
+    let (width, height) = (size.width as usize, size.height as usize);
+    let mut packed = vec![];
+    let mut row_lengths = vec![];
+    for plane in planes {
+        for y in 0..height {
+            let start = packed.len();
+            pack_bits(&plane[y * width..(y + 1) * width], &mut packed);
+            let length = u16::try_from(packed.len() - start)
+                .map_err(|_| invalid("layer too large for a psd"))?;
+            row_lengths.push(length);
+        }
+    }
+    buffer.u16(RLE);
+    for length in row_lengths {
+        buffer.u16(length);
+    }
+    buffer.bytes(&packed);
+    Ok(())
+}
+
+/// Encodes a row with `PackBits`, repeating runs of 3 or more bytes.
+fn pack_bits(mut row: &[u8], packed: &mut Vec<u8>) {
+    const MAX_LENGTH: usize = 128;
+    let run_at = |row: &[u8], start: usize| {
+        row[start..]
+            .iter()
+            .take(MAX_LENGTH)
+            .take_while(|&&byte| byte == row[start])
+            .count()
+    };
+    while !row.is_empty() {
+        let run = run_at(row, 0);
+        let length = if run >= 3 {
+            // the header is 1 - run as an i8.
+            packed.extend([(257 - run) as u8, row[0]]);
+            run
+        } else {
+            let literal = (1..row.len().min(MAX_LENGTH))
+                .find(|&start| run_at(row, start) >= 3)
+                .unwrap_or_else(|| row.len().min(MAX_LENGTH));
+            packed.push((literal - 1) as u8);
+            packed.extend_from_slice(&row[..literal]);
+            literal
+        };
+        row = &row[length..];
+    }
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn write_record(info: &mut Buffer, layer: &Layer, channels: &[Buffer]) -> io::Result<()> {
+    let too_large = |_| invalid("layer too large for a psd");
+    let out_of_range = || invalid("layer out of range for a psd");
+    let [left, top] = layer.offset;
+    let width = i32::try_from(layer.size.width).map_err(too_large)?;
+    let height = i32::try_from(layer.size.height).map_err(too_large)?;
+    info.i32(top);
+    info.i32(left);
+    info.i32(top.checked_add(height).ok_or_else(out_of_range)?);
+    info.i32(left.checked_add(width).ok_or_else(out_of_range)?);
+    info.u16(4);
+    for ((id, _), channel) in WRITTEN_CHANNELS.iter().zip(channels) {
+        info.i16(*id);
+        info.u32(u32::try_from(channel.0.len()).map_err(too_large)?);
+    }
+    info.bytes(RESOURCE_SIGNATURE);
+    info.bytes(blend_key(layer.blend_mode));
+    info.u8((layer.opacity.clamp(0.0, 1.0) * 255.0).round() as u8);
+    // no clipping, hidden flag, filler.
+    info.u8(0);
+    info.u8(if layer.visible { 0 } else { HIDDEN_FLAG });
+    info.u8(0);
+
+    let mut extra = Buffer::default();
+    // no mask and blending ranges.
+    extra.u32(0);
+    extra.u32(0);
+    // the pascal name is MacRoman, the unicode name follows.
+    let name: Vec<u8> = layer
+        .name
+        .chars()
+        .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+        .take(usize::from(u8::MAX))
+        .collect();
+    extra.u8(name.len() as u8);
+    extra.bytes(&name);
+    extra.bytes(&[0; 3][..(4 - (name.len() + 1) % 4) % 4]);
+    let mut unicode = Buffer::default();
+    let units: Vec<u16> = layer.name.encode_utf16().collect();
+    unicode.u32(u32::try_from(units.len()).map_err(|_| invalid("layer name too long"))?);
+    for unit in units {
+        unicode.u16(unit);
+    }
+    if unicode.0.len() % 4 != 0 {
+        unicode.bytes(&[0; 2]);
+    }
+    extra.bytes(RESOURCE_SIGNATURE);
+    extra.bytes(b"luni");
+    extra.section(&unicode)?;
+    info.section(&extra)
+}
+
+/// Writes `image` to a `.psd` file, see [`write`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn save(path: &Path, image: &LayeredImage) -> io::Result<()> {
+    let mut file = export::create_file(path)?;
+    write(&mut file, image)?;
+    file.flush()
+}
+
+/// Reads a `.psd` file, see [`read`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not supported.
+pub fn load(path: &Path) -> io::Result<Imported> {
+    read(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(name: &str, size: PhysicalSize, seed: u8) -> Layer {
+        // runs and literals, so the rows are packed both ways.
+        let pixels = (0..size.width * size.height * 4)
+            .map(|index| if index % 40 < 20 { seed } else { index as u8 })
+            .collect();
+        Layer::new(name, size, pixels)
+    }
+
+    #[test]
+    fn round_trips() {
+        let size = PhysicalSize {
+            width: 300,
+            height: 4,
+        };
+        let mut lower = layer("Lower", size, 7);
+        lower.opacity = 0.6;
+        lower.blend_mode = BlendMode::Multiply;
+        let mut upper = layer(
+            "Überzug ✓",
+            PhysicalSize {
+                width: 3,
+                height: 2,
+            },
+            200,
+        );
+        upper.offset = [-2, 5];
+        upper.visible = false;
+        upper.blend_mode = BlendMode::Luminosity;
+        let empty = Layer::new(
+            "Empty",
+            PhysicalSize {
+                width: 0,
+                height: 0,
+            },
+            vec![],
+        );
+        let image = LayeredImage {
+            size,
+            layers: vec![lower, upper, empty],
+        };
+        let mut psd = vec![];
+        write(&mut psd, &image).unwrap();
+        let imported = read(psd.as_slice()).unwrap();
+        assert_eq!(imported.warnings, Vec::<String>::new());
+        assert_eq!(imported.image, image);
+    }
+
+    #[test]
+    fn packs_bits() {
+        let row: Vec<u8> = [[1, 2].as_slice(), &[3; 200], &[4, 4, 5]].concat();
+        let mut packed = vec![];
+        pack_bits(&row, &mut packed);
+        assert_eq!(packed[..4], [1, 1, 2, 129]);
+        let mut unpacked = vec![];
+        unpack_bits(&packed, row.len(), &mut unpacked).unwrap();
+        assert_eq!(unpacked, row);
+    }
+
+    #[test]
+    fn reads_the_raw_merged_image() {
+        let mut psd = Buffer::default();
+        psd.bytes(SIGNATURE);
+        psd.u16(1);
+        psd.bytes(&[0; 6]);
+        psd.u16(3);
+        psd.u32(1);
+        psd.u32(2);
+        psd.u16(8);
+        psd.u16(RGB_MODE);
+        // no color mode data, image resources and layers.
+        psd.bytes(&[0; 12]);
+        psd.u16(RAW);
+        psd.bytes(&[1, 2, 3, 4, 5, 6]);
+        let imported = read(psd.0.as_slice()).unwrap();
+        assert_eq!(
+            imported.image.layers[0].pixels,
+            [1, 3, 5, 255, 2, 4, 6, 255]
+        );
+    }
+
+    #[test]
+    fn reads_rle_merged_images_with_extra_channels() {
+        let mut psd = Buffer::default();
+        psd.bytes(SIGNATURE);
+        psd.u16(1);
+        psd.bytes(&[0; 6]);
+        // red, green, blue, alpha and a spot channel.
+        psd.u16(5);
+        psd.u32(1);
+        psd.u32(2);
+        psd.u16(8);
+        psd.u16(RGB_MODE);
+        psd.bytes(&[0; 12]);
+        psd.u16(RLE);
+        let rows: Vec<_> = [[1, 2], [3, 4], [5, 6], [7, 8], [9, 10]]
+            .iter()
+            .map(|row| {
+                let mut packed = vec![];
+                pack_bits(row, &mut packed);
+                packed
+            })
+            .collect();
+        for row in &rows {
+            psd.u16(u16::try_from(row.len()).unwrap());
+        }
+        for row in &rows {
+            psd.bytes(row);
+        }
+        let imported = read(psd.0.as_slice()).unwrap();
+        assert_eq!(imported.image.layers[0].pixels, [1, 3, 5, 7, 2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn rejects_bounds_out_of_range() {
+        let mut image = LayeredImage {
+            size: PhysicalSize {
+                width: 2,
+                height: 2,
+            },
+            layers: vec![layer(
+                "Layer",
+                PhysicalSize {
+                    width: 2,
+                    height: 2,
+                },
+                0,
+            )],
+        };
+        let mut psd = vec![];
+        write(&mut psd, &image).unwrap();
+        // the bounds of the first record, after the header, the empty
+        // sections and the lengths and count of the layer info.
+        let top = 26 + 8 + 4 + 4 + 2;
+        psd[top..top + 4].copy_from_slice(&i32::MIN.to_be_bytes());
+        psd[top + 8..top + 12].copy_from_slice(&i32::MAX.to_be_bytes());
+        let err = read(psd.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        image.layers[0].offset = [i32::MAX, 0];
+        let err = write(&mut vec![], &image).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{cell::RefCell, f32::consts::TAU, path::Path, rc::Rc};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, AST, INT};
+
+use crate::{
+    canvas::Canvas,
+    event_log::{self, Event},
+    tool::Tool,
+};
+
+/// A failed script, with the position of the error.
+pub type ScriptError = Box<EvalAltResult>;
+
+/// Draws on a [`Canvas`] from [Rhai](https://rhai.rs) scripts.
+///
+/// Positions are window pixels like the mouse, colors are `0.0..=1.0`:
+///
+/// - `color(r, g, b)`, `brush_size(pixels)`, `brush("pencil")`, `pressure(p)`,
+///   `opacity(a)`
+/// - `stroke([[x, y], ...])`, `line(x0, y0, x1, y1)`
+/// - `rect(x, y, w, h)`, `ellipse(cx, cy, rx, ry)` and their `fill_` versions
+/// - `WIDTH` and `HEIGHT` of the canvas
+///
+/// The drawing is fed to the canvas as the same inputs as the mouse and
+/// keys, so it is recorded and replayed with them.
+pub struct Script {
+    engine: Engine,
+    /// variables kept between runs, e.g. console lines.
+    scope: Scope<'static>,
+    state: Rc<RefCell<State>>,
+}
+
+/// What the script functions draw, applied to the canvas afterwards.
+#[derive(Default)]
+struct State {
+    events: Vec<Event>,
+    /// canvas size in pixels.
+    size: [f32; 2],
+    /// stroke width in pixels, the spacing of filled shapes.
+    brush_size: f32,
+}
+
+impl State {
+    /// segments are at most this many pixels long along curves.
+    const CURVE_STEP: f32 = 4.0;
+    const MIN_SEGMENTS: f32 = 16.0;
+
+    fn stroke(&mut self, points: &[[f32; 2]]) {
+        let Some(&last) = points.last() else {
+            return;
+        };
+        self.events.extend(points.iter().map(|&pos| Event::MouseAt {
+            brush_down: true,
+            pos,
+        }));
+        self.events.push(Event::MouseAt {
+            brush_down: false,
+            pos: last,
+        });
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn ellipse_points(center: [f32; 2], radius: [f32; 2]) -> Vec<[f32; 2]> {
+        let circumference = TAU * radius[0].max(radius[1]);
+        let segments = (circumference / Self::CURVE_STEP)
+            .max(Self::MIN_SEGMENTS)
+            .round() as u32;
+        (0..=segments)
+            .map(|segment| {
+                let angle = TAU * segment as f32 / segments as f32;
+                [
+                    radius[0].mul_add(angle.cos(), center[0]),
+                    radius[1].mul_add(angle.sin(), center[1]),
+                ]
+            })
+            .collect()
+    }
+
+    /// Fills with horizontal strokes one brush width apart, `span` gives
+    /// the left and right end of the row at a height.
+    #[allow(clippy::cast_sign_loss)]
+    fn fill_rows(&mut self, top: f32, bottom: f32, span: impl Fn(f32) -> Option<[f32; 2]>) {
+        let step = self.brush_size.max(1.0);
+        let rows = ((bottom - top) / step).max(0.0).ceil() as usize;
+        for row in 0..rows {
+            let y = (row as f32 + 0.5).mul_add(step, top);
+            if let Some([left, right]) = span(y) {
+                self.stroke(&[[left, y], [right, y]]);
+            }
+        }
+    }
+}
+
+fn number(value: &Dynamic) -> Result<f32, ScriptError> {
+    value
+        .as_float()
+        .map(|value| value as f32)
+        .or_else(|_| value.as_int().map(|value| value as f32))
+        .map_err(|kind| format!("expected a number, got {kind}").into())
+}
+
+fn point(value: Dynamic) -> Result<[f32; 2], ScriptError> {
+    match value.try_cast::<Array>().as_deref() {
+        Some([x, y]) => Ok([number(x)?, number(y)?]),
+        _ => Err("expected a point [x, y]".into()),
+    }
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Script {
+    /// stops runaway scripts, e.g. `loop {}`, before the window hangs for
+    /// long.
+    const MAX_OPERATIONS: u64 = 5_000_000;
+    /// deep recursion fails before it overflows the stack.
+    const MAX_CALL_LEVELS: usize = 64;
+
+    #[must_use]
+    pub fn new() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(Self::MAX_OPERATIONS);
+        engine.set_max_call_levels(Self::MAX_CALL_LEVELS);
+        let state = Rc::new(RefCell::new(State::default()));
+        Self::register_brush(&mut engine, &state);
+        Self::register_shapes(&mut engine, &state);
+        Self {
+            engine,
+            scope: Scope::new(),
+            state,
+        }
+    }
+
+    fn register_brush(engine: &mut Engine, state: &Rc<RefCell<State>>) {
+        let color = Rc::clone(state);
+        engine.register_fn(
+            "color",
+            move |r: Dynamic, g: Dynamic, b: Dynamic| -> Result<(), ScriptError> {
+                let rgb = [number(&r)?, number(&g)?, number(&b)?];
+                color.borrow_mut().events.push(Event::BrushColor(rgb));
+                Ok(())
+            },
+        );
+        let size = Rc::clone(state);
+        engine.register_fn(
+            "brush_size",
+            move |pixels: Dynamic| -> Result<(), ScriptError> {
+                let pixels = number(&pixels)?;
+                let mut state = size.borrow_mut();
+                state.brush_size = pixels;
+                let radius = pixels / state.size[0];
+                state.events.push(Event::BrushRadius(radius));
+                Ok(())
+            },
+        );
+        let kind = Rc::clone(state);
+        engine.register_fn("brush", move |name: &str| -> Result<(), ScriptError> {
+            let brush_kind = event_log::parse_brush_kind(name).ok_or_else(|| {
+                format!("unknown brush {name}, try round, impasto, pencil or charcoal")
+            })?;
+            kind.borrow_mut().events.push(Event::BrushKind(brush_kind));
+            Ok(())
+        });
+        let opacity = Rc::clone(state);
+        engine.register_fn(
+            "opacity",
+            move |value: Dynamic| -> Result<(), ScriptError> {
+                let value = number(&value)?;
+                opacity.borrow_mut().events.push(Event::BrushOpacity(value));
+                Ok(())
+            },
+        );
+        let pressure = Rc::clone(state);
+        engine.register_fn(
+            "pressure",
+            move |value: Dynamic| -> Result<(), ScriptError> {
+                let value = number(&value)?;
+                pressure.borrow_mut().events.push(Event::Pressure(value));
+                Ok(())
+            },
+        );
+    }
+
+    fn register_shapes(engine: &mut Engine, state: &Rc<RefCell<State>>) {
+        let stroke = Rc::clone(state);
+        engine.register_fn("stroke", move |points: Array| -> Result<(), ScriptError> {
+            let points = points
+                .into_iter()
+                .map(point)
+                .collect::<Result<Vec<_>, _>>()?;
+            stroke.borrow_mut().stroke(&points);
+            Ok(())
+        });
+        let line = Rc::clone(state);
+        engine.register_fn(
+            "line",
+            move |x0: Dynamic, y0: Dynamic, x1: Dynamic, y1: Dynamic| -> Result<(), ScriptError> {
+                let points = [[number(&x0)?, number(&y0)?], [number(&x1)?, number(&y1)?]];
+                line.borrow_mut().stroke(&points);
+                Ok(())
+            },
+        );
+        let rect = Rc::clone(state);
+        engine.register_fn(
+            "rect",
+            move |x: Dynamic, y: Dynamic, w: Dynamic, h: Dynamic| -> Result<(), ScriptError> {
+                let [x, y, w, h] = [number(&x)?, number(&y)?, number(&w)?, number(&h)?];
+                let corners = [[x, y], [x + w, y], [x + w, y + h], [x, y + h], [x, y]];
+                rect.borrow_mut().stroke(&corners);
+                Ok(())
+            },
+        );
+        let fill_rect = Rc::clone(state);
+        engine.register_fn(
+            "fill_rect",
+            move |x: Dynamic, y: Dynamic, w: Dynamic, h: Dynamic| -> Result<(), ScriptError> {
+                let [x, y, w, h] = [number(&x)?, number(&y)?, number(&w)?, number(&h)?];
+                fill_rect
+                    .borrow_mut()
+                    .fill_rows(y, y + h, |_| Some([x, x + w]));
+                Ok(())
+            },
+        );
+        let ellipse = Rc::clone(state);
+        engine.register_fn(
+            "ellipse",
+            move |cx: Dynamic, cy: Dynamic, rx: Dynamic, ry: Dynamic| -> Result<(), ScriptError> {
+                let center = [number(&cx)?, number(&cy)?];
+                let radius = [number(&rx)?, number(&ry)?];
+                ellipse
+                    .borrow_mut()
+                    .stroke(&State::ellipse_points(center, radius));
+                Ok(())
+            },
+        );
+        let fill_ellipse = Rc::clone(state);
+        engine.register_fn(
+            "fill_ellipse",
+            move |cx: Dynamic, cy: Dynamic, rx: Dynamic, ry: Dynamic| -> Result<(), ScriptError> {
+                let [cx, cy, rx, ry] = [number(&cx)?, number(&cy)?, number(&rx)?, number(&ry)?];
+                fill_ellipse.borrow_mut().fill_rows(cy - ry, cy + ry, |y| {
+                    let t = (y - cy) / ry;
+                    let half = rx * t.mul_add(-t, 1.0).sqrt();
+                    (half > 0.0).then_some([cx - half, cx + half])
+                });
+                Ok(())
+            },
+        );
+    }
+
+    /// Runs a script, the drawing before an error is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the script does not parse or fails.
+    pub fn run(&mut self, canvas: &mut Canvas, source: &str) -> Result<(), ScriptError> {
+        let ast = self.engine.compile(source)?;
+        self.run_ast(canvas, &ast)
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, does not parse or fails.
+    pub fn run_file(&mut self, canvas: &mut Canvas, path: &Path) -> Result<(), ScriptError> {
+        let ast = self.engine.compile_file(path.into())?;
+        self.run_ast(canvas, &ast)
+    }
+
+    fn run_ast(&mut self, canvas: &mut Canvas, ast: &AST) -> Result<(), ScriptError> {
+        let size = canvas.size();
+        {
+            let mut state = self.state.borrow_mut();
+            state.size = [size.width as f32, size.height as f32];
+            state.brush_size = canvas.brush_radius() * state.size[0];
+        }
+        self.scope.set_or_push("WIDTH", INT::from(size.width));
+        self.scope.set_or_push("HEIGHT", INT::from(size.height));
+        let result = self.engine.run_ast_with_scope(&mut self.scope, ast);
+        let events = std::mem::take(&mut self.state.borrow_mut().events);
+        if !events.is_empty() {
+            // scripts paint with the brush, not pick colors or select.
+            if canvas.is_color_wheel_enabled() {
+                canvas.color_wheel_toggle();
+            }
+            if canvas.tool() != Tool::Brush {
+                canvas.set_tool(Tool::Brush);
+            }
+        }
+        for event in &events {
+            canvas.apply(event);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_runaway_scripts() {
+        let script = Script::new();
+        let err = script.engine.run("loop {}").unwrap_err();
+        assert!(
+            matches!(*err, EvalAltResult::ErrorTooManyOperations(_)),
+            "{err}"
+        );
+        let err = script
+            .engine
+            .run("fn deeper(n) { deeper(n + 1) } deeper(0)")
+            .unwrap_err();
+        assert!(
+            matches!(*err, EvalAltResult::ErrorStackOverflow(_)),
+            "{err}"
+        );
+    }
+}
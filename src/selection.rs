@@ -0,0 +1,347 @@
+use crate::canvas::PhysicalSize;
+
+/// Coverage mask painting is clipped to, one byte per canvas pixel.
+///
+/// Without an active selection every pixel is selected.
+pub struct Selection {
+    size: PhysicalSize,
+    mask: Vec<u8>,
+    active: bool,
+}
+
+impl Selection {
+    const SELECTED: u8 = u8::MAX;
+
+    #[must_use]
+    pub fn new(size: PhysicalSize) -> Self {
+        Self {
+            size,
+            mask: vec![Self::SELECTED; size.width as usize * size.height as usize],
+            active: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn size(&self) -> PhysicalSize {
+        self.size
+    }
+
+    #[must_use]
+    pub fn mask(&self) -> &[u8] {
+        &self.mask
+    }
+
+    #[must_use]
+    pub const fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Smallest pixel rectangle containing the selection, the whole canvas
+    /// without an active selection and `None` for an empty one.
+    #[must_use]
+    pub fn bounds(&self) -> Option<([f32; 2], [f32; 2])> {
+        let width = self.size.width as usize;
+        let mut selected = self
+            .mask
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value > 0)
+            .map(|(index, _)| (index % width, index / width));
+        let (x, y) = selected.next()?;
+        let (min_x, min_y, max_x, max_y) = selected.fold((x, y, x, y), |bounds, (x, y)| {
+            (bounds.0.min(x), bounds.1, bounds.2.max(x), y)
+        });
+        Some((
+            [min_x as f32, min_y as f32],
+            [(max_x + 1) as f32, (max_y + 1) as f32],
+        ))
+    }
+
+    pub fn select_all(&mut self) {
+        self.mask.fill(Self::SELECTED);
+        self.active = false;
+    }
+
+    /// Selects the rectangle spanned by two corners, in pixels.
+    pub fn select_rect(&mut self, from: [f32; 2], to: [f32; 2]) {
+        let (min, max) = Self::span(from, to);
+        self.fill(|x, y| x >= min[0] && x <= max[0] && y >= min[1] && y <= max[1]);
+    }
+
+    /// Selects the ellipse inscribed in the rectangle spanned by two corners.
+    pub fn select_ellipse(&mut self, from: [f32; 2], to: [f32; 2]) {
+        let (min, max) = Self::span(from, to);
+        let center = [f32::midpoint(min[0], max[0]), f32::midpoint(min[1], max[1])];
+        let radius = [
+            ((max[0] - min[0]) / 2.0).max(f32::EPSILON),
+            ((max[1] - min[1]) / 2.0).max(f32::EPSILON),
+        ];
+        self.fill(|x, y| {
+            let dx = (x - center[0]) / radius[0];
+            let dy = (y - center[1]) / radius[1];
+            dx.mul_add(dx, dy * dy) <= 1.0
+        });
+    }
+
+    /// Selects the inside (even-odd rule) of a closed freehand path.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn select_lasso(&mut self, path: &[[f32; 2]]) {
+        self.mask.fill(0);
+        self.active = true;
+        if path.len() < 3 {
+            return;
+        }
+        let width = self.size.width as usize;
+        let edges = path.iter().zip(path.iter().cycle().skip(1));
+        let edges: Vec<_> = edges.collect();
+        let mut crossings = vec![];
+        for (row, mask_row) in self.mask.chunks_mut(width).enumerate() {
+            let y = row as f32 + 0.5;
+            crossings.clear();
+            crossings.extend(
+                edges
+                    .iter()
+                    .filter(|(from, to)| (from[1] <= y) != (to[1] <= y))
+                    .map(|(from, to)| {
+                        let t = (y - from[1]) / (to[1] - from[1]);
+                        t.mul_add(to[0] - from[0], from[0])
+                    }),
+            );
+            crossings.sort_by(f32::total_cmp);
+            for span in crossings.chunks_exact(2) {
+                // first and last pixel whose center lies inside the span.
+                let start = (span[0] - 0.5).ceil().max(0.0) as usize;
+                let end = ((span[1] - 0.5).floor() + 1.0).clamp(0.0, width as f32) as usize;
+                if start < end {
+                    mask_row[start..end].fill(Self::SELECTED);
+                }
+            }
+        }
+    }
+
+    /// Magic wand: flood fills the pixels connected to `seed` whose color
+    /// differs from it by at most `tolerance` in every channel.
+    ///
+    /// `pixels` is the rgba image of the canvas.
+    pub fn select_color(&mut self, pixels: &[u8], seed: [f32; 2], tolerance: u8) {
+        self.mask.fill(0);
+        self.active = true;
+        let Some(seed) = self.pixel_index(seed) else {
+            return;
+        };
+        let width = self.size.width as usize;
+        let height = self.size.height as usize;
+        let color_at = |index: usize| &pixels[index * 4..index * 4 + 3];
+        let seed_color = color_at(seed);
+        let matches = |index: usize| {
+            color_at(index)
+                .iter()
+                .zip(seed_color)
+                .all(|(channel, seed)| channel.abs_diff(*seed) <= tolerance)
+        };
+        let mut stack = vec![seed];
+        self.mask[seed] = Self::SELECTED;
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % width, index / width);
+            let neighbours = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width),
+                (y + 1 < height).then(|| index + width),
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                if self.mask[neighbour] == 0 && matches(neighbour) {
+                    self.mask[neighbour] = Self::SELECTED;
+                    stack.push(neighbour);
+                }
+            }
+        }
+    }
+
+    pub fn invert(&mut self) {
+        for value in &mut self.mask {
+            *value = Self::SELECTED - *value;
+        }
+        self.active = true;
+    }
+
+    /// Softens the selection edge over roughly `radius` pixels.
+    pub fn feather(&mut self, radius: usize) {
+        if !self.active {
+            return;
+        }
+        // three box blurs approximate a gaussian.
+        for _ in 0..3 {
+            self.filter(radius, |window| {
+                let sum: usize = window.iter().map(|&value| usize::from(value)).sum();
+                (sum / window.len()) as u8
+            });
+        }
+    }
+
+    pub fn grow(&mut self, radius: usize) {
+        if self.active {
+            self.filter(radius, |window| window.iter().copied().max().unwrap_or(0));
+        }
+    }
+
+    pub fn shrink(&mut self, radius: usize) {
+        if self.active {
+            self.filter(radius, |window| window.iter().copied().min().unwrap_or(0));
+        }
+    }
+
+    const fn span(from: [f32; 2], to: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+        (
+            [from[0].min(to[0]), from[1].min(to[1])],
+            [from[0].max(to[0]), from[1].max(to[1])],
+        )
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn pixel_index(&self, pos: [f32; 2]) -> Option<usize> {
+        let (x, y) = (pos[0].floor(), pos[1].floor());
+        (x >= 0.0 && y >= 0.0 && x < self.size.width as f32 && y < self.size.height as f32)
+            .then(|| y as usize * self.size.width as usize + x as usize)
+    }
+
+    /// Replaces the mask, testing every pixel center.
+    fn fill(&mut self, inside: impl Fn(f32, f32) -> bool) {
+        let width = self.size.width as usize;
+        for (index, value) in self.mask.iter_mut().enumerate() {
+            let x = (index % width) as f32 + 0.5;
+            let y = (index / width) as f32 + 0.5;
+            *value = if inside(x, y) { Self::SELECTED } else { 0 };
+        }
+        self.active = true;
+    }
+
+    /// Separable filter over a `2 * radius + 1` window, rows then columns.
+    fn filter(&mut self, radius: usize, reduce: impl Fn(&[u8]) -> u8) {
+        let width = self.size.width as usize;
+        let height = self.size.height as usize;
+        if radius == 0 || width == 0 || height == 0 {
+            return;
+        }
+        let mut line = vec![];
+        let mut filter_line = |values: &mut dyn Iterator<Item = &mut u8>| {
+            let mut values: Vec<&mut u8> = values.collect();
+            line.clear();
+            line.extend(values.iter().map(|value| **value));
+            for (index, value) in values.iter_mut().enumerate() {
+                let start = index.saturating_sub(radius);
+                let end = (index + radius + 1).min(line.len());
+                **value = reduce(&line[start..end]);
+            }
+        };
+        for row in self.mask.chunks_mut(width) {
+            filter_line(&mut row.iter_mut());
+        }
+        for column in 0..width {
+            filter_line(&mut self.mask.iter_mut().skip(column).step_by(width));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selection(width: u32, height: u32) -> Selection {
+        Selection::new(PhysicalSize { width, height })
+    }
+
+    /// The mask as rows of `#` for selected, `+` for partly and `.` for
+    /// unselected pixels.
+    fn rows(selection: &Selection) -> Vec<String> {
+        selection
+            .mask()
+            .chunks(selection.size().width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|&value| match value {
+                        0 => '.',
+                        Selection::SELECTED => '#',
+                        _ => '+',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bounds_of_shapes() {
+        let mut selection = selection(6, 5);
+        assert_eq!(selection.bounds(), Some(([0.0, 0.0], [6.0, 5.0])));
+        selection.select_rect([4.2, 3.9], [1.0, 1.0]);
+        assert_eq!(
+            rows(&selection),
+            ["......", ".###..", ".###..", ".###..", "......"]
+        );
+        assert_eq!(selection.bounds(), Some(([1.0, 1.0], [4.0, 4.0])));
+        selection.invert();
+        assert_eq!(selection.bounds(), Some(([0.0, 0.0], [6.0, 5.0])));
+        selection.select_ellipse([0.0, 0.0], [5.0, 5.0]);
+        assert_eq!(
+            rows(&selection),
+            [".###..", "#####.", "#####.", "#####.", ".###.."]
+        );
+        // pixel centers on the slanted edge are inside.
+        selection.select_lasso(&[[1.0, 1.0], [5.0, 1.0], [1.0, 5.0]]);
+        assert_eq!(
+            rows(&selection),
+            ["......", ".####.", ".###..", ".##...", ".#...."]
+        );
+        selection.select_lasso(&[]);
+        assert!(selection.is_active());
+        assert_eq!(selection.bounds(), None);
+    }
+
+    #[test]
+    fn grows_shrinks_and_feathers() {
+        let mut selection = selection(5, 5);
+        selection.grow(1);
+        selection.feather(1);
+        assert!(!selection.is_active());
+        assert!(selection
+            .mask()
+            .iter()
+            .all(|&value| value == Selection::SELECTED));
+        selection.select_rect([2.0, 2.0], [3.0, 3.0]);
+        selection.grow(1);
+        assert_eq!(
+            rows(&selection),
+            [".....", ".###.", ".###.", ".###.", "....."]
+        );
+        selection.shrink(1);
+        assert_eq!(
+            rows(&selection),
+            [".....", ".....", "..#..", ".....", "....."]
+        );
+        selection.grow(1);
+        selection.feather(1);
+        let mask = selection.mask();
+        assert!(mask[12] < Selection::SELECTED && mask[12] > mask[11]);
+        assert!(mask[0] > 0 && mask[0] < mask[6]);
+        assert_eq!(rows(&selection)[2], "+++++");
+    }
+
+    #[test]
+    fn magic_wand_fills_connected_colors() {
+        let mut selection = selection(4, 2);
+        let red = [250, 0, 0, 255];
+        let dark_red = [230, 10, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let pixels = [
+            red, dark_red, blue, red, //
+            blue, red, blue, red,
+        ]
+        .concat();
+        selection.select_color(&pixels, [0.5, 0.5], 24);
+        assert_eq!(rows(&selection), ["##..", ".#.."]);
+        selection.select_color(&pixels, [0.5, 0.5], 0);
+        assert_eq!(rows(&selection), ["#...", "...."]);
+        selection.select_color(&pixels, [-1.0, 0.5], 24);
+        assert_eq!(selection.bounds(), None);
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    brush::BrushKind,
+    canvas::{Canvas, PhysicalSize},
+    event_log,
+    tool::Tool,
+};
+
+/// The colors of the window decorations.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// follows the desktop.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+const THEMES: [(Theme, &str); 3] = [
+    (Theme::System, "system"),
+    (Theme::Light, "light"),
+    (Theme::Dark, "dark"),
+];
+
+/// What the winit binary remembers between launches, `None` where it keeps
+/// its default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    /// inner size in pixels, never zero.
+    pub window_size: Option<PhysicalSize>,
+    /// outer position on the desktop in pixels.
+    pub window_position: Option<[i32; 2]>,
+    pub brush_color: Option<[f32; 3]>,
+    pub brush_radius: Option<f32>,
+    pub brush_kind: Option<BrushKind>,
+    pub brush_opacity: Option<f32>,
+    pub tool: Option<Tool>,
+    pub theme: Theme,
+    /// opened and saved files, the most recent first.
+    pub recent_files: Vec<PathBuf>,
+}
+
+impl Settings {
+    /// files kept in [`Self::recent_files`].
+    pub const MAX_RECENT_FILES: usize = 10;
+
+    /// `rusty_paint/settings.toml` in the config directory of the user, e.g.
+    /// `~/.config` on Linux, `None` if there is no such directory.
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rusty_paint").join("settings.toml"))
+    }
+
+    /// Reads the settings from toml, e.g.
+    ///
+    /// ```toml
+    /// theme = "dark"
+    /// tool = "lasso"
+    /// recent_files = ["/home/me/cat.ora"]
+    ///
+    /// [window]
+    /// size = [800, 600]
+    /// position = [40, 30]
+    ///
+    /// [brush]
+    /// color = [1.0, 0.5, 0.0]
+    /// radius = 0.01
+    /// kind = "pencil"
+    /// opacity = 0.8
+    /// ```
+    ///
+    /// with every entry optional.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for invalid toml, or entries of the wrong type or
+    /// with unknown names.
+    pub fn parse(config: &str) -> io::Result<Self> {
+        let table: toml::Table = config.parse().map_err(invalid)?;
+        let window = sub_table(&table, "window")?;
+        let brush = sub_table(&table, "brush")?;
+        let size = window
+            .get("size")
+            .map(|value| numbers(value, "window.size", integer))
+            .transpose()?;
+        let recent_files = match table.get("recent_files") {
+            None => vec![],
+            Some(value) => value
+                .as_array()
+                .and_then(|values| {
+                    values
+                        .iter()
+                        .map(|value| value.as_str().map(PathBuf::from))
+                        .collect()
+                })
+                .ok_or_else(|| invalid("recent_files is not a list of paths"))?,
+        };
+        Ok(Self {
+            // a minimized window has no size, the default is used instead.
+            window_size: size
+                .filter(|&[width, height]| width > 0 && height > 0)
+                .map(|[width, height]| PhysicalSize { width, height }),
+            window_position: window
+                .get("position")
+                .map(|value| numbers(value, "window.position", integer))
+                .transpose()?,
+            brush_color: brush
+                .get("color")
+                .map(|value| numbers(value, "brush.color", float))
+                .transpose()?,
+            brush_radius: brush
+                .get("radius")
+                .map(|value| float(value).ok_or_else(|| invalid("brush.radius is not a number")))
+                .transpose()?,
+            brush_kind: brush
+                .get("kind")
+                .map(|value| named(value, "brush.kind", event_log::parse_brush_kind))
+                .transpose()?,
+            brush_opacity: brush
+                .get("opacity")
+                .map(|value| float(value).ok_or_else(|| invalid("brush.opacity is not a number")))
+                .transpose()?,
+            tool: table
+                .get("tool")
+                .map(|value| named(value, "tool", event_log::parse_tool))
+                .transpose()?,
+            theme: table
+                .get("theme")
+                .map(|value| named(value, "theme", |name| from_name(&THEMES, name)))
+                .transpose()?
+                .unwrap_or_default(),
+            recent_files,
+        })
+    }
+
+    /// Reads a toml file, see [`Self::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is invalid.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the settings as toml, creating the directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or file cannot be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_string())
+    }
+
+    /// Sets the brush and tool of `canvas` to the remembered ones.
+    pub fn apply(&self, canvas: &mut Canvas) {
+        if let Some(color) = self.brush_color {
+            canvas.set_brush_color(color);
+        }
+        if let Some(radius) = self.brush_radius {
+            canvas.set_brush_radius(radius);
+        }
+        if let Some(kind) = self.brush_kind {
+            canvas.set_brush_kind(kind);
+        }
+        if let Some(opacity) = self.brush_opacity {
+            canvas.set_brush_opacity(opacity);
+        }
+        if let Some(tool) = self.tool {
+            canvas.set_tool(tool);
+        }
+    }
+
+    /// Remembers the brush and tool of `canvas`. The transform tool is
+    /// remembered as the brush, as it moves the painting of one session.
+    pub const fn remember(&mut self, canvas: &Canvas) {
+        self.brush_color = Some(canvas.brush_color());
+        self.brush_radius = Some(canvas.brush_radius());
+        self.brush_kind = Some(canvas.brush_kind());
+        self.brush_opacity = Some(canvas.brush_opacity());
+        self.tool = Some(match canvas.tool() {
+            Tool::Transform => Tool::Brush,
+            tool => tool,
+        });
+    }
+
+    /// Moves `path` to the front of the recent files, forgetting the oldest
+    /// beyond [`Self::MAX_RECENT_FILES`].
+    pub fn add_recent_file(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(Self::MAX_RECENT_FILES);
+    }
+}
+
+impl fmt::Display for Settings {
+    /// Writes the toml [`Settings::parse`] reads back.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = toml::Table::new();
+        table.insert("theme".into(), name_of(&THEMES, self.theme).into());
+        if let Some(tool) = self.tool {
+            table.insert("tool".into(), event_log::tool_name(tool).into());
+        }
+        let recent_files: Vec<_> = self
+            .recent_files
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        table.insert("recent_files".into(), recent_files.into());
+        let mut window = toml::Table::new();
+        if let Some(size) = self.window_size {
+            window.insert("size".into(), vec![size.width, size.height].into());
+        }
+        if let Some(position) = self.window_position {
+            window.insert("position".into(), position.to_vec().into());
+        }
+        table.insert("window".into(), window.into());
+        let mut brush = toml::Table::new();
+        if let Some(color) = self.brush_color {
+            brush.insert("color".into(), color.map(f64::from).to_vec().into());
+        }
+        if let Some(radius) = self.brush_radius {
+            brush.insert("radius".into(), f64::from(radius).into());
+        }
+        if let Some(kind) = self.brush_kind {
+            brush.insert("kind".into(), event_log::brush_kind_name(kind).into());
+        }
+        if let Some(opacity) = self.brush_opacity {
+            brush.insert("opacity".into(), f64::from(opacity).into());
+        }
+        table.insert("brush".into(), brush.into());
+        write!(f, "{table}")
+    }
+}
+
+/// The table under `key`, empty if there is none.
+fn sub_table(table: &toml::Table, key: &str) -> io::Result<toml::Table> {
+    table.get(key).map_or_else(
+        || Ok(toml::Table::new()),
+        |value| {
+            value
+                .as_table()
+                .cloned()
+                .ok_or_else(|| invalid(format!("{key} is not a table")))
+        },
+    )
+}
+
+/// A list of `N` numbers read with `number`.
+fn numbers<T, const N: usize>(
+    value: &toml::Value,
+    key: &str,
+    number: impl Fn(&toml::Value) -> Option<T>,
+) -> io::Result<[T; N]> {
+    value
+        .as_array()
+        .and_then(|values| values.iter().map(number).collect::<Option<Vec<_>>>())
+        .and_then(|values| values.try_into().ok())
+        .ok_or_else(|| invalid(format!("{key} is not a list of {N} numbers")))
+}
+
+fn integer<T: TryFrom<i64>>(value: &toml::Value) -> Option<T> {
+    value
+        .as_integer()
+        .and_then(|integer| T::try_from(integer).ok())
+}
+
+/// Integers are read as floats too, e.g. `color = [1, 0, 0]`.
+const fn float(value: &toml::Value) -> Option<f32> {
+    match value {
+        toml::Value::Float(float) => Some(*float as f32),
+        toml::Value::Integer(integer) => Some(*integer as f32),
+        _ => None,
+    }
+}
+
+fn named<T>(value: &toml::Value, key: &str, parse: impl Fn(&str) -> Option<T>) -> io::Result<T> {
+    let name = value
+        .as_str()
+        .ok_or_else(|| invalid(format!("{key} is not a name")))?;
+    parse(name).ok_or_else(|| invalid(format!("unknown {key} {name}")))
+}
+
+fn name_of<T: PartialEq + Copy>(names: &[(T, &'static str)], value: T) -> &'static str {
+    names
+        .iter()
+        .find(|(v, _)| *v == value)
+        .map_or("", |(_, name)| name)
+}
+
+fn from_name<T: Copy>(names: &[(T, &str)], name: &str) -> Option<T> {
+    names.iter().find(|(_, n)| *n == name).map(|(v, _)| *v)
+}
+
+fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let mut settings = Settings {
+            window_size: Some(PhysicalSize {
+                width: 800,
+                height: 600,
+            }),
+            window_position: Some([-40, 30]),
+            brush_color: Some([0.1, 0.5, 1.0]),
+            brush_radius: Some(0.0123),
+            brush_kind: Some(BrushKind::Charcoal),
+            brush_opacity: Some(0.75),
+            tool: Some(Tool::Lasso),
+            theme: Theme::Dark,
+            recent_files: vec![],
+        };
+        settings.add_recent_file(Path::new("missing/a.ora"));
+        settings.add_recent_file(Path::new("missing/b.psd"));
+        settings.add_recent_file(Path::new("missing/a.ora"));
+        assert_eq!(
+            settings.recent_files,
+            [
+                PathBuf::from("missing/a.ora"),
+                PathBuf::from("missing/b.psd")
+            ]
+        );
+        assert_eq!(Settings::parse(&settings.to_string()).unwrap(), settings);
+        assert_eq!(
+            Settings::parse(&Settings::default().to_string()).unwrap(),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        assert!(Settings::parse("theme = \"pink\"").is_err());
+        assert!(Settings::parse("[window]\nsize = [800]").is_err());
+        assert!(Settings::parse("[window]\nsize = [-1, 600]").is_err());
+        assert!(Settings::parse("[brush]\nkind = 3").is_err());
+        assert_eq!(
+            Settings::parse("[window]\nsize = [0, 0]")
+                .unwrap()
+                .window_size,
+            None
+        );
+        assert_eq!(
+            Settings::parse("[brush]\ncolor = [1, 0, 0]")
+                .unwrap()
+                .brush_color,
+            Some([1.0, 0.0, 0.0])
+        );
+    }
+}
@@ -1,22 +1,59 @@
 struct VertexInput {
     @location(0) color: vec3<f32>,
     @location(1) position: vec2<f32>,
+    @location(2) height: f32,
+    @location(3) across: f32,
+    @location(4) dryness: f32,
+    @location(5) pressure: f32,
+    @location(6) opacity: f32,
 };
 
 struct VertexOutput {
   @builtin(position) position: vec4<f32>,
   @location(1) color : vec4<f32>,
+  @location(2) height: f32,
+  @location(3) across: f32,
+  @location(4) dryness: f32,
+  @location(5) pressure: f32,
+  @location(6) opacity: f32,
 };
 
+struct FragmentOutput {
+  @location(0) color: vec4<f32>,
+  @location(1) height: vec4<f32>,
+};
+
+@group(0) @binding(0) var paper_texture: texture_2d<f32>;
+@group(0) @binding(1) var paper_sampler: sampler;
+@group(1) @binding(0) var selection_texture: texture_2d<f32>;
+
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
   var out: VertexOutput;
   out.position = vec4<f32>(model.position, 0.5, 0.5);
   out.color = vec4<f32>(model.color, 1.0);
+  out.height = model.height;
+  out.across = model.across;
+  out.dryness = model.dryness;
+  out.pressure = model.pressure;
+  out.opacity = model.opacity;
     return out;
 }
 
 @fragment
-fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-  return input.color;
+fn fs_main(input: VertexOutput) -> FragmentOutput {
+  var out: FragmentOutput;
+  let paper_uv = input.position.xy / vec2<f32>(textureDimensions(paper_texture));
+  let tooth = textureSample(paper_texture, paper_sampler, paper_uv).r;
+  // harder pressure pushes the pigment further down into the valleys.
+  let threshold = 1.0 - input.pressure;
+  let grain_coverage = smoothstep(threshold - 0.2, threshold + 0.2, tooth);
+  let soft_edge = mix(1.0, 1.0 - input.across * input.across, 0.5);
+  let selected = textureLoad(selection_texture, vec2<i32>(input.position.xy), 0).r;
+  let coverage = mix(1.0, grain_coverage * soft_edge, input.dryness) * input.opacity * selected;
+  out.color = vec4<f32>(input.color.rgb, coverage);
+  // ridge shaped profile, thickest in the middle of the stroke.
+  let height = input.height * (1.0 - input.across * input.across) * selected;
+  out.height = vec4<f32>(height, 0.0, 0.0, 1.0);
+  return out;
 }
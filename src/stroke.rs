@@ -0,0 +1,33 @@
+use crate::brush::{BrushKind, Point};
+
+/// A point along a stroke path, in canvas coordinates.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub pos: [f32; 2],
+    pub pressure: f32,
+}
+
+/// A stroke as a vector path, painted by tessellating it at any resolution.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    pub color: [f32; 3],
+    /// stroke width as a fraction of the canvas width.
+    pub radius: f32,
+    pub kind: BrushKind,
+    /// coverage of the paint, `1.0` is opaque.
+    pub opacity: f32,
+    pub samples: Vec<Sample>,
+}
+
+impl Stroke {
+    /// Triangles of the segments ending at the samples from `from` on, the
+    /// whole stroke for `from` 0 or 1.
+    #[must_use]
+    pub fn tessellate(&self, from: usize) -> Vec<Point> {
+        self.samples
+            .windows(2)
+            .skip(from.saturating_sub(1))
+            .flat_map(|segment| Point::segment(self, segment[0], segment[1]))
+            .collect()
+    }
+}
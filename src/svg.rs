@@ -0,0 +1,180 @@
+use std::io::{self, Write};
+
+use crate::{
+    brush::BrushKind,
+    canvas::PhysicalSize,
+    color,
+    stroke::{Sample, Stroke},
+};
+
+/// Writes `strokes` as SVG paths over a `background` rectangle, in pixels
+/// of a canvas of `size`.
+///
+/// Colors are linear like the brush and written as sRGB, dry media are
+/// drawn translucent as the paper grain shows through them.
+///
+/// # Errors
+///
+/// Returns an error if the writer fails.
+pub fn write_svg(
+    mut writer: impl Write,
+    strokes: &[Stroke],
+    size: PhysicalSize,
+    background: [f32; 3],
+) -> io::Result<()> {
+    let (width, height) = (size.width, size.height);
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )?;
+    writeln!(
+        writer,
+        r#"<rect width="{width}" height="{height}" fill="{}"/>"#,
+        color::to_hex(background)
+    )?;
+    for stroke in strokes.iter().filter(|stroke| stroke.samples.len() > 1) {
+        let path = path_data(&stroke.samples, size);
+        let stroke_width = number(stroke.radius * width as f32);
+        write!(
+            writer,
+            r#"<path d="{path}" fill="none" stroke="{}" stroke-width="{stroke_width}" stroke-linejoin="round""#,
+            color::to_hex(stroke.color)
+        )?;
+        let opacity = opacity(stroke.kind) * stroke.opacity;
+        if opacity < 1.0 {
+            write!(writer, r#" stroke-opacity="{}""#, number(opacity))?;
+        }
+        writeln!(writer, "/>")?;
+    }
+    writeln!(writer, "</svg>")
+}
+
+/// Average coverage of a stroke, the grain breaks up dry media.
+const fn opacity(kind: BrushKind) -> f32 {
+    match kind {
+        BrushKind::Round | BrushKind::Impasto => 1.0,
+        BrushKind::Pencil => 0.8,
+        BrushKind::Charcoal => 0.6,
+    }
+}
+
+/// `M x y L x y ...` in pixels, canvas coordinates have y up.
+fn path_data(samples: &[Sample], size: PhysicalSize) -> String {
+    let points: Vec<String> = samples
+        .iter()
+        .map(|sample| {
+            let x = (sample.pos[0] + 0.5) * size.width as f32;
+            let y = (0.5 - sample.pos[1]) * size.height as f32;
+            format!("{} {}", number(x), number(y))
+        })
+        .collect();
+    format!("M {}", points.join(" L "))
+}
+
+/// Rounded to hundredths of a pixel without trailing zeros.
+fn number(value: f32) -> String {
+    let rounded = format!("{value:.2}");
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => "0".into(),
+        trimmed => trimmed.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: PhysicalSize = PhysicalSize {
+        width: 200,
+        height: 100,
+    };
+
+    fn sample(pos: [f32; 2]) -> Sample {
+        Sample { pos, pressure: 0.5 }
+    }
+
+    fn svg(strokes: &[Stroke], background: [f32; 3]) -> String {
+        let mut svg = vec![];
+        write_svg(&mut svg, strokes, SIZE, background).unwrap();
+        String::from_utf8(svg).unwrap()
+    }
+
+    #[test]
+    fn empty_painting_is_the_background() {
+        assert_eq!(
+            svg(&[], [1.0, 1.0, 1.0]),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 200 100">
+<rect width="200" height="100" fill="#ffffff"/>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn strokes_become_paths_in_pixels() {
+        let strokes = [
+            Stroke {
+                color: [1.0, 0.0, 0.0],
+                radius: 0.05,
+                kind: BrushKind::Round,
+                opacity: 1.0,
+                samples: vec![
+                    sample([-0.5, 0.5]),
+                    sample([0.0, 0.0]),
+                    sample([0.25, -0.5]),
+                ],
+            },
+            Stroke {
+                color: [0.0, 0.0, 1.0],
+                radius: 0.0125,
+                kind: BrushKind::Charcoal,
+                opacity: 0.5,
+                samples: vec![sample([0.1, 0.2]), sample([0.3, 0.4])],
+            },
+        ];
+        assert_eq!(
+            svg(&strokes, [0.0, 0.0, 0.0]),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 200 100">
+<rect width="200" height="100" fill="#000000"/>
+<path d="M 0 0 L 100 50 L 150 100" fill="none" stroke="#ff0000" stroke-width="10" stroke-linejoin="round"/>
+<path d="M 120 30 L 160 10" fill="none" stroke="#0000ff" stroke-width="2.5" stroke-linejoin="round" stroke-opacity="0.3"/>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn single_samples_are_left_out() {
+        let dot = Stroke {
+            color: [0.0; 3],
+            radius: 0.01,
+            kind: BrushKind::Round,
+            opacity: 1.0,
+            samples: vec![sample([0.0, 0.0])],
+        };
+        assert!(!svg(&[dot], [1.0; 3]).contains("<path"));
+    }
+
+    #[test]
+    fn colors_are_written_as_srgb() {
+        let stroke = Stroke {
+            color: [0.2, -1.0, 2.0],
+            radius: 0.01,
+            kind: BrushKind::Round,
+            opacity: 1.0,
+            samples: vec![sample([0.0, 0.0]), sample([0.5, 0.5])],
+        };
+        let svg = svg(&[stroke], [0.0, 0.5, 1.0]);
+        assert!(svg.contains(r##"<rect width="200" height="100" fill="#00bcff"/>"##));
+        assert!(svg.contains(r##"fill="none" stroke="#7c00ff""##));
+    }
+
+    #[test]
+    fn numbers_are_short() {
+        assert_eq!(number(12.0), "12");
+        assert_eq!(number(0.126), "0.13");
+        assert_eq!(number(-0.001), "0");
+        assert_eq!(number(3.456), "3.46");
+    }
+}
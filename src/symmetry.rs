@@ -0,0 +1,208 @@
+use std::f32::consts::TAU;
+
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymmetryMode {
+    #[default]
+    Off,
+    /// mirror left and right of a vertical axis.
+    Vertical,
+    /// mirror above and below a horizontal axis.
+    Horizontal,
+    /// mirror across both axes.
+    Both,
+    /// repeat the stroke rotated around the center (mandala).
+    Radial(u32),
+}
+
+impl SymmetryMode {
+    const DEFAULT_RADIAL: u32 = 6;
+
+    /// The mode following `self` when cycling through all modes.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Vertical,
+            Self::Vertical => Self::Horizontal,
+            Self::Horizontal => Self::Both,
+            Self::Both => Self::Radial(Self::DEFAULT_RADIAL),
+            Self::Radial(_) => Self::Off,
+        }
+    }
+}
+
+/// Repeats every stroke across the configured axes.
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Symmetry {
+    mode: SymmetryMode,
+    center: [f32; 2],
+}
+
+impl Symmetry {
+    const MIN_RADIAL: u32 = 2;
+    const MAX_RADIAL: u32 = 32;
+    /// long enough to cross the canvas from anywhere on it.
+    const GUIDE_LENGTH: f32 = 4.0;
+
+    #[must_use]
+    pub const fn mode(&self) -> SymmetryMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: SymmetryMode) {
+        self.mode = match mode {
+            SymmetryMode::Radial(count) => {
+                SymmetryMode::Radial(count.clamp(Self::MIN_RADIAL, Self::MAX_RADIAL))
+            }
+            mode => mode,
+        };
+    }
+
+    #[must_use]
+    pub const fn center(&self) -> [f32; 2] {
+        self.center
+    }
+
+    /// `center` is in canvas coordinates, like the stroke points.
+    pub const fn set_center(&mut self, center: [f32; 2]) {
+        self.center = center;
+    }
+
+    /// Affine transforms (2x3, row major) of the copies besides the original.
+    fn copies(&self) -> Vec<[[f32; 3]; 2]> {
+        let [cx, cy] = self.center;
+        let across_vertical = [[-1.0, 0.0, 2.0 * cx], [0.0, 1.0, 0.0]];
+        let across_horizontal = [[1.0, 0.0, 0.0], [0.0, -1.0, 2.0 * cy]];
+        let across_center = [[-1.0, 0.0, 2.0 * cx], [0.0, -1.0, 2.0 * cy]];
+        match self.mode {
+            SymmetryMode::Off => vec![],
+            SymmetryMode::Vertical => vec![across_vertical],
+            SymmetryMode::Horizontal => vec![across_horizontal],
+            SymmetryMode::Both => vec![across_vertical, across_horizontal, across_center],
+            SymmetryMode::Radial(count) => (1..count)
+                .map(|step| {
+                    let (sin, cos) = (TAU * step as f32 / count as f32).sin_cos();
+                    [
+                        [cos, -sin, cx - cos.mul_add(cx, -sin * cy)],
+                        [sin, cos, cy - sin.mul_add(cx, cos * cy)],
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// The position itself followed by all of its mirrored copies.
+    #[must_use]
+    pub fn map(&self, pos: [f32; 2]) -> Vec<[f32; 2]> {
+        let [x, y] = pos;
+        let copies = self.copies().into_iter().map(|[row_x, row_y]| {
+            [
+                row_x[0].mul_add(x, row_x[1].mul_add(y, row_x[2])),
+                row_y[0].mul_add(x, row_y[1].mul_add(y, row_y[2])),
+            ]
+        });
+        std::iter::once(pos).chain(copies).collect()
+    }
+
+    /// Line list end points of the guide overlay, empty when off.
+    #[must_use]
+    pub fn guide_lines(&self) -> Vec<[f32; 2]> {
+        let [cx, cy] = self.center;
+        let length = Self::GUIDE_LENGTH;
+        let vertical = [[cx, cy - length], [cx, cy + length]];
+        let horizontal = [[cx - length, cy], [cx + length, cy]];
+        match self.mode {
+            SymmetryMode::Off => vec![],
+            SymmetryMode::Vertical => vertical.to_vec(),
+            SymmetryMode::Horizontal => horizontal.to_vec(),
+            SymmetryMode::Both => [vertical, horizontal].concat(),
+            SymmetryMode::Radial(count) => (0..count)
+                .flat_map(|step| {
+                    let (sin, cos) = (TAU * step as f32 / count as f32).sin_cos();
+                    [[cx, cy], [sin.mul_add(length, cx), cos.mul_add(length, cy)]]
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[[f32; 2]], b: &[[f32; 2]]) {
+        assert_eq!(a.len(), b.len(), "{a:?} != {b:?}");
+        assert!(
+            a.iter()
+                .flatten()
+                .zip(b.iter().flatten())
+                .all(|(a, b)| (a - b).abs() < 1e-5),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    fn symmetry(mode: SymmetryMode, center: [f32; 2]) -> Symmetry {
+        let mut symmetry = Symmetry::default();
+        symmetry.set_mode(mode);
+        symmetry.set_center(center);
+        symmetry
+    }
+
+    #[test]
+    fn modes_cycle() {
+        let mut mode = SymmetryMode::Off;
+        let mut modes = vec![];
+        for _ in 0..5 {
+            mode = mode.next();
+            modes.push(mode);
+        }
+        assert_eq!(
+            modes,
+            [
+                SymmetryMode::Vertical,
+                SymmetryMode::Horizontal,
+                SymmetryMode::Both,
+                SymmetryMode::Radial(6),
+                SymmetryMode::Off,
+            ]
+        );
+    }
+
+    #[test]
+    fn mirrors_across_the_center() {
+        let pos = [0.3, 0.5];
+        let center = [0.1, 0.2];
+        assert_close(&symmetry(SymmetryMode::Off, center).map(pos), &[pos]);
+        assert_close(
+            &symmetry(SymmetryMode::Vertical, center).map(pos),
+            &[pos, [-0.1, 0.5]],
+        );
+        assert_close(
+            &symmetry(SymmetryMode::Horizontal, center).map(pos),
+            &[pos, [0.3, -0.1]],
+        );
+        assert_close(
+            &symmetry(SymmetryMode::Both, center).map(pos),
+            &[pos, [-0.1, 0.5], [0.3, -0.1], [-0.1, -0.1]],
+        );
+        assert_eq!(symmetry(SymmetryMode::Both, center).guide_lines().len(), 4);
+        assert!(symmetry(SymmetryMode::Off, center).guide_lines().is_empty());
+    }
+
+    #[test]
+    fn radial_copies_rotate_around_the_center() {
+        let radial = symmetry(SymmetryMode::Radial(4), [1.0, 1.0]);
+        assert_close(
+            &radial.map([2.0, 1.0]),
+            &[[2.0, 1.0], [1.0, 2.0], [0.0, 1.0], [1.0, 0.0]],
+        );
+        assert_eq!(radial.guide_lines().len(), 8);
+        assert_eq!(
+            symmetry(SymmetryMode::Radial(1), [0.0; 2]).mode(),
+            SymmetryMode::Radial(2)
+        );
+        assert_eq!(
+            symmetry(SymmetryMode::Radial(100), [0.0; 2]).mode(),
+            SymmetryMode::Radial(32)
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::{io, path::Path, time::Duration};
+
+use crate::{
+    canvas::{Canvas, PhysicalSize},
+    event_log::EventLog,
+    export,
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimelapseFormat {
+    Gif,
+    Apng,
+    /// one numbered png per frame, see [`export::frame_path`].
+    PngSequence,
+}
+
+impl TimelapseFormat {
+    /// `.gif` and `.apng` files are animated, anything else becomes png
+    /// frames.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gif") => Self::Gif,
+            Some("apng") => Self::Apng,
+            _ => Self::PngSequence,
+        }
+    }
+}
+
+/// Renders a recorded session progressively, one frame every `speed / fps`
+/// seconds of painting.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Timelapse {
+    pub format: TimelapseFormat,
+    pub fps: u16,
+    /// seconds of painting per second of time-lapse.
+    pub speed: f32,
+    /// frame size, the session is scaled to it.
+    pub size: PhysicalSize,
+}
+
+impl Timelapse {
+    /// GIF delays are in hundredths of a second.
+    const GIF_TICKS: u16 = 100;
+    /// `NeuQuant` sampling, 1 is the best and slowest quantization.
+    const GIF_QUANTIZE_SPEED: i32 = 10;
+
+    /// Session time of each frame, the last one shows the finished painting.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn frame_times(&self, log: &EventLog) -> Vec<Duration> {
+        let end = log
+            .events()
+            .last()
+            .map_or(Duration::ZERO, |timed| timed.time);
+        let interval = self.speed / f32::from(self.fps.max(1));
+        if !interval.is_finite() || interval <= 0.0 {
+            return vec![end];
+        }
+        let frames = (end.as_secs_f32() / interval).ceil() as usize + 1;
+        (0..frames)
+            .map(|frame| Duration::from_secs_f32(frame as f32 * interval).min(end))
+            .collect()
+    }
+
+    /// Replays `log` on a fresh `canvas` and passes the rgba pixels of each
+    /// frame to `frame`.
+    ///
+    /// Window resizes are left out so all frames have the chosen size, see
+    /// [`EventLog::resized`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of `frame`.
+    pub fn render(
+        &self,
+        canvas: &mut Canvas,
+        log: &EventLog,
+        mut frame: impl FnMut(&[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let log = log.resized(self.size);
+        if canvas.size() != self.size {
+            canvas.resize_window(self.size);
+        }
+        let mut events = log.events().iter().peekable();
+        for time in self.frame_times(&log) {
+            while let Some(timed) = events.next_if(|timed| timed.time <= time) {
+                canvas.apply(&timed.event);
+            }
+            frame(&canvas.pixels()?)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the frames into `path` in the chosen format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the speed is not a positive number, a file cannot
+    /// be written or the frames are too large for a GIF.
+    pub fn export(&self, canvas: &mut Canvas, log: &EventLog, path: &Path) -> io::Result<()> {
+        self.check_speed()?;
+        match self.format {
+            TimelapseFormat::Gif => self.export_gif(canvas, log, path),
+            TimelapseFormat::Apng => self.export_apng(canvas, log, path),
+            TimelapseFormat::PngSequence => {
+                let mut index = 0;
+                self.render(canvas, log, |pixels| {
+                    let file = export::create_file(&export::frame_path(path, index))?;
+                    index += 1;
+                    export::encode_png(file, self.size, png::ColorType::Rgba, pixels)
+                })
+            }
+        }
+    }
+
+    fn check_speed(&self) -> io::Result<()> {
+        if self.speed.is_finite() && self.speed > 0.0 {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("speed {} is not a positive number", self.speed),
+            ))
+        }
+    }
+
+    fn export_gif(&self, canvas: &mut Canvas, log: &EventLog, path: &Path) -> io::Result<()> {
+        let too_large = |_| io::Error::new(io::ErrorKind::InvalidInput, "too large for a gif");
+        let width = u16::try_from(self.size.width).map_err(too_large)?;
+        let height = u16::try_from(self.size.height).map_err(too_large)?;
+        let mut encoder = gif::Encoder::new(export::create_file(path)?, width, height, &[])
+            .map_err(io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        let delay = (Self::GIF_TICKS / self.fps.max(1)).max(1);
+        self.render(canvas, log, |pixels| {
+            let mut pixels = pixels.to_vec();
+            let mut frame =
+                gif::Frame::from_rgba_speed(width, height, &mut pixels, Self::GIF_QUANTIZE_SPEED);
+            frame.delay = delay;
+            encoder.write_frame(&frame).map_err(io::Error::other)
+        })
+    }
+
+    fn export_apng(&self, canvas: &mut Canvas, log: &EventLog, path: &Path) -> io::Result<()> {
+        let frames = u32::try_from(self.frame_times(log).len()).unwrap_or(u32::MAX);
+        let mut encoder = png::Encoder::new(
+            export::create_file(path)?,
+            self.size.width,
+            self.size.height,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames, 0)?;
+        encoder.set_frame_delay(1, self.fps.max(1))?;
+        let mut writer = encoder.write_header()?;
+        self.render(canvas, log, |pixels| Ok(writer.write_image_data(pixels)?))?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_log::Event;
+
+    #[test]
+    fn rejects_invalid_speeds() {
+        let size = PhysicalSize {
+            width: 8,
+            height: 8,
+        };
+        let mut log = EventLog::new(size);
+        log.push(Duration::from_secs(3), Event::Undo);
+        let mut timelapse = Timelapse {
+            format: TimelapseFormat::Gif,
+            fps: 2,
+            speed: 1.0,
+            size,
+        };
+        assert!(timelapse.check_speed().is_ok());
+        assert_eq!(timelapse.frame_times(&log).len(), 7);
+        for speed in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            timelapse.speed = speed;
+            let err = timelapse.check_speed().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(timelapse.frame_times(&log), [Duration::from_secs(3)]);
+        }
+    }
+
+    #[test]
+    fn frames_are_pngs() {
+        let path = Path::new("out/run.mp4");
+        assert_eq!(
+            TimelapseFormat::from_path(path),
+            TimelapseFormat::PngSequence
+        );
+        assert_eq!(export::frame_path(path, 7), Path::new("out/run_0007.png"));
+        assert_eq!(
+            export::height_map_path(Path::new("out/run.jpg")),
+            Path::new("out/run_height.jpg")
+        );
+    }
+}
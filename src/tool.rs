@@ -0,0 +1,24 @@
+/// What dragging the mouse over the canvas does.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tool {
+    #[default]
+    Brush,
+    RectSelect,
+    EllipseSelect,
+    /// freehand selection.
+    Lasso,
+    /// selects connected pixels of similar color.
+    MagicWand,
+    /// moves, scales and rotates the selection (or everything).
+    Transform,
+}
+
+impl Tool {
+    #[must_use]
+    pub const fn is_selection(self) -> bool {
+        matches!(
+            self,
+            Self::RectSelect | Self::EllipseSelect | Self::Lasso | Self::MagicWand
+        )
+    }
+}
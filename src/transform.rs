@@ -0,0 +1,316 @@
+use wgpu::{vertex_attr_array, VertexAttribute, VertexBufferLayout, VertexStepMode};
+
+use crate::canvas::PhysicalSize;
+
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resampling {
+    #[default]
+    Bilinear,
+    Bicubic,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FloatingVertex {
+    pos: [f32; 2],
+    uv: [f32; 2],
+    /// 1.0 selects bicubic resampling in the shader.
+    bicubic: f32,
+}
+
+impl FloatingVertex {
+    const ATTRIBUTES: [VertexAttribute; 3] = vertex_attr_array![
+        0 => Float32x2, 1 => Float32x2, 2 => Float32
+    ];
+
+    #[must_use]
+    pub const fn desc<'a>() -> VertexBufferLayout<'a> {
+        VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Drag {
+    Move {
+        start: [f32; 2],
+        translate: [f32; 2],
+    },
+    /// `start` is relative to the center, before rotation.
+    Scale {
+        start: [f32; 2],
+        scale: [f32; 2],
+    },
+    Rotate {
+        start_angle: f32,
+        rotation: f32,
+    },
+}
+
+/// Placement of lifted (floating) pixels, all positions are in pixels.
+///
+/// A source pixel `p` ends up at `center + translate + R(rotation) * (scale * (p - center))`.
+#[derive(Clone, Debug)]
+pub struct Transform {
+    min: [f32; 2],
+    max: [f32; 2],
+    translate: [f32; 2],
+    scale: [f32; 2],
+    rotation: f32,
+    resampling: Resampling,
+    drag: Option<Drag>,
+    /// the pixels were pasted, they have no place to go back to.
+    pasted: bool,
+}
+
+impl Transform {
+    const HANDLE_RADIUS: f32 = 8.0;
+    /// distance of the rotation handle above the top edge.
+    const ROTATE_OFFSET: f32 = 32.0;
+    const MIN_SCALE: f32 = 0.01;
+
+    /// Starts at the identity for the pixels within `min..max`.
+    #[must_use]
+    pub const fn new(min: [f32; 2], max: [f32; 2]) -> Self {
+        Self {
+            min,
+            max,
+            translate: [0.0; 2],
+            scale: [1.0; 2],
+            rotation: 0.0,
+            resampling: Resampling::Bilinear,
+            drag: None,
+            pasted: false,
+        }
+    }
+
+    /// Like [`Self::new`] for pixels pasted within `min..max`.
+    #[must_use]
+    pub const fn pasted(min: [f32; 2], max: [f32; 2]) -> Self {
+        Self {
+            pasted: true,
+            ..Self::new(min, max)
+        }
+    }
+
+    #[must_use]
+    pub const fn is_pasted(&self) -> bool {
+        self.pasted
+    }
+
+    #[must_use]
+    pub const fn resampling(&self) -> Resampling {
+        self.resampling
+    }
+
+    pub const fn set_resampling(&mut self, resampling: Resampling) {
+        self.resampling = resampling;
+    }
+
+    /// Back to where the pixels were lifted from.
+    pub const fn reset(&mut self) {
+        self.translate = [0.0; 2];
+        self.scale = [1.0; 2];
+        self.rotation = 0.0;
+        self.drag = None;
+    }
+
+    const fn center(&self) -> [f32; 2] {
+        [
+            f32::midpoint(self.min[0], self.max[0]),
+            f32::midpoint(self.min[1], self.max[1]),
+        ]
+    }
+
+    fn moved_center(&self) -> [f32; 2] {
+        let center = self.center();
+        [center[0] + self.translate[0], center[1] + self.translate[1]]
+    }
+
+    fn rotate([x, y]: [f32; 2], angle: f32) -> [f32; 2] {
+        let (sin, cos) = angle.sin_cos();
+        [x.mul_add(cos, -y * sin), x.mul_add(sin, y * cos)]
+    }
+
+    /// Where the source pixel `pos` is drawn.
+    #[must_use]
+    pub fn apply(&self, pos: [f32; 2]) -> [f32; 2] {
+        let center = self.center();
+        let scaled = [
+            (pos[0] - center[0]) * self.scale[0],
+            (pos[1] - center[1]) * self.scale[1],
+        ];
+        let [x, y] = Self::rotate(scaled, self.rotation);
+        let moved_center = self.moved_center();
+        [x + moved_center[0], y + moved_center[1]]
+    }
+
+    /// Top left, top right, bottom right and bottom left corner.
+    fn corners(&self) -> [[f32; 2]; 4] {
+        let (min, max) = (self.min, self.max);
+        [
+            [min[0], min[1]],
+            [max[0], min[1]],
+            [max[0], max[1]],
+            [min[0], max[1]],
+        ]
+        .map(|corner| self.apply(corner))
+    }
+
+    fn rotate_handle(&self) -> [f32; 2] {
+        let top = self.apply([self.center()[0], self.min[1]]);
+        let [dx, dy] = Self::rotate([0.0, -Self::ROTATE_OFFSET], self.rotation);
+        [top[0] + dx, top[1] + dy]
+    }
+
+    /// `pos` relative to the moved center, undoing the rotation.
+    fn local(&self, pos: [f32; 2]) -> [f32; 2] {
+        let moved_center = self.moved_center();
+        Self::rotate(
+            [pos[0] - moved_center[0], pos[1] - moved_center[1]],
+            -self.rotation,
+        )
+    }
+
+    fn is_near(pos: [f32; 2], handle: [f32; 2]) -> bool {
+        (pos[0] - handle[0]).abs() <= Self::HANDLE_RADIUS
+            && (pos[1] - handle[1]).abs() <= Self::HANDLE_RADIUS
+    }
+
+    /// Starts dragging the handle under `pos`, returns false if nothing was hit.
+    pub fn press(&mut self, pos: [f32; 2]) -> bool {
+        let local = self.local(pos);
+        let half = [
+            (self.max[0] - self.min[0]) * self.scale[0].abs() / 2.0,
+            (self.max[1] - self.min[1]) * self.scale[1].abs() / 2.0,
+        ];
+        self.drag = if Self::is_near(pos, self.rotate_handle()) {
+            let moved_center = self.moved_center();
+            Some(Drag::Rotate {
+                start_angle: f32::atan2(pos[1] - moved_center[1], pos[0] - moved_center[0]),
+                rotation: self.rotation,
+            })
+        } else if self
+            .corners()
+            .iter()
+            .any(|&corner| Self::is_near(pos, corner))
+        {
+            Some(Drag::Scale {
+                start: local,
+                scale: self.scale,
+            })
+        } else if local[0].abs() <= half[0] && local[1].abs() <= half[1] {
+            Some(Drag::Move {
+                start: pos,
+                translate: self.translate,
+            })
+        } else {
+            None
+        };
+        self.drag.is_some()
+    }
+
+    /// Updates the dragged handle, returns false when nothing is dragged.
+    pub fn drag_to(&mut self, pos: [f32; 2]) -> bool {
+        match self.drag {
+            Some(Drag::Move { start, translate }) => {
+                self.translate = [
+                    translate[0] + pos[0] - start[0],
+                    translate[1] + pos[1] - start[1],
+                ];
+            }
+            Some(Drag::Scale { start, scale }) => {
+                let local = self.local(pos);
+                for axis in 0..2 {
+                    if start[axis].abs() > f32::EPSILON {
+                        let new_scale = scale[axis] * local[axis] / start[axis];
+                        self.scale[axis] = if new_scale.abs() < Self::MIN_SCALE {
+                            Self::MIN_SCALE.copysign(new_scale)
+                        } else {
+                            new_scale
+                        };
+                    }
+                }
+            }
+            Some(Drag::Rotate {
+                start_angle,
+                rotation,
+            }) => {
+                let moved_center = self.moved_center();
+                let angle = f32::atan2(pos[1] - moved_center[1], pos[0] - moved_center[0]);
+                self.rotation = rotation + angle - start_angle;
+            }
+            None => return false,
+        }
+        true
+    }
+
+    pub const fn release(&mut self) {
+        self.drag = None;
+    }
+
+    /// Two triangles drawing the floating pixels onto a canvas of `size`.
+    #[must_use]
+    pub fn vertices(&self, size: PhysicalSize) -> [FloatingVertex; 6] {
+        let (width, height) = (size.width as f32, size.height as f32);
+        let bicubic = if self.resampling == Resampling::Bicubic {
+            1.0
+        } else {
+            0.0
+        };
+        let (min, max) = (self.min, self.max);
+        let vertex = |source: [f32; 2]| {
+            let [x, y] = self.apply(source);
+            FloatingVertex {
+                pos: [
+                    (x / width).mul_add(2.0, -1.0),
+                    (y / height).mul_add(-2.0, 1.0),
+                ],
+                uv: [source[0] / width, source[1] / height],
+                bicubic,
+            }
+        };
+        let [top_left, top_right, bottom_right, bottom_left] = [
+            [min[0], min[1]],
+            [max[0], min[1]],
+            [max[0], max[1]],
+            [min[0], max[1]],
+        ]
+        .map(vertex);
+        [
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_right,
+            bottom_left,
+            top_left,
+        ]
+    }
+
+    /// Line list of the bounding box and its handles, in pixels.
+    #[must_use]
+    pub fn outline(&self) -> Vec<[f32; 2]> {
+        let corners = self.corners();
+        let square = |[x, y]: [f32; 2]| {
+            let radius = Self::HANDLE_RADIUS / 2.0;
+            let points = [
+                [x - radius, y - radius],
+                [x + radius, y - radius],
+                [x + radius, y + radius],
+                [x - radius, y + radius],
+            ];
+            (0..4).flat_map(move |index| [points[index], points[(index + 1) % 4]])
+        };
+        let rotate_handle = self.rotate_handle();
+        let top = self.apply([self.center()[0], self.min[1]]);
+        (0..4)
+            .flat_map(|index| [corners[index], corners[(index + 1) % 4]])
+            .chain(corners.into_iter().flat_map(square))
+            .chain([top, rotate_handle])
+            .chain(square(rotate_handle))
+            .collect()
+    }
+}
@@ -0,0 +1,122 @@
+const corners_constants = array<vec4<f32>, 4>(
+    vec4<f32>(1.0,-1.0,0.0,1.0),
+    vec4<f32>(1.0,1.0,0.0,1.0),
+    vec4<f32>(-1.0,-1.0,0.0,1.0),
+    vec4<f32>(-1.0,1.0,0.0,1.0),
+);
+
+@group(0) @binding(0) var color_texture: texture_2d<f32>;
+@group(0) @binding(1) var height_texture: texture_2d<f32>;
+@group(0) @binding(2) var canvas_sampler: sampler;
+@group(1) @binding(0) var selection_texture: texture_2d<f32>;
+
+struct PaintOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) height: vec4<f32>,
+};
+
+struct FloatingInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) bicubic: f32,
+};
+
+struct FloatingOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) bicubic: f32,
+};
+
+@vertex
+fn vs_fullscreen(
+    @builtin(vertex_index) in_vertex_index: u32
+) -> @builtin(position) vec4<f32> {
+    // https://github.com/gfx-rs/naga/issues/1910
+    var corners = corners_constants;
+    return corners[in_vertex_index];
+}
+
+// copies the selected paint into the floating textures.
+@fragment
+fn fs_lift(@builtin(position) position: vec4<f32>) -> PaintOutput {
+    let pixel = vec2<i32>(position.xy);
+    let selected = textureLoad(selection_texture, pixel, 0).r;
+    var out: PaintOutput;
+    out.color = textureLoad(color_texture, pixel, 0) * selected;
+    out.height = textureLoad(height_texture, pixel, 0) * selected;
+    return out;
+}
+
+// erases the selected paint, blended with (zero, one minus source alpha).
+@fragment
+fn fs_cut(@builtin(position) position: vec4<f32>) -> PaintOutput {
+    let selected = textureLoad(selection_texture, vec2<i32>(position.xy), 0).r;
+    var out: PaintOutput;
+    out.color = vec4<f32>(0.0, 0.0, 0.0, selected);
+    out.height = vec4<f32>(0.0, 0.0, 0.0, selected);
+    return out;
+}
+
+@vertex
+fn vs_floating(in: FloatingInput) -> FloatingOutput {
+    var out: FloatingOutput;
+    out.position = vec4<f32>(in.position, 0.0, 1.0);
+    out.uv = in.uv;
+    out.bicubic = in.bicubic;
+    return out;
+}
+
+// catmull-rom weights of the four texels around t.
+fn cubic_weights(t: f32) -> vec4<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    return vec4<f32>(
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2,
+    );
+}
+
+fn sample_bicubic(texture: texture_2d<f32>, uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(texture));
+    let position = uv * size - 0.5;
+    let base = floor(position);
+    let weights_x = cubic_weights(position.x - base.x);
+    let weights_y = cubic_weights(position.y - base.y);
+    let last = vec2<i32>(size) - 1;
+    var sum = vec4<f32>(0.0);
+    for (var y = 0; y < 4; y = y + 1) {
+        for (var x = 0; x < 4; x = x + 1) {
+            let pixel = clamp(vec2<i32>(base) + vec2<i32>(x - 1, y - 1), vec2<i32>(0), last);
+            sum = sum + textureLoad(texture, pixel, 0) * weights_x[x] * weights_y[y];
+        }
+    }
+    return clamp(sum, vec4<f32>(0.0), vec4<f32>(1.0));
+}
+
+fn resample(texture: texture_2d<f32>, uv: vec2<f32>, bicubic: f32) -> vec4<f32> {
+    let bilinear = textureSampleLevel(texture, canvas_sampler, uv, 0.0);
+    return select(bilinear, sample_bicubic(texture, uv), bicubic > 0.5);
+}
+
+fn floating_color(in: FloatingOutput) -> vec4<f32> {
+    let color = resample(color_texture, in.uv, in.bicubic);
+    // bicubic overshoot must not break the premultiplied alpha.
+    return vec4<f32>(min(color.rgb, vec3<f32>(color.a)), color.a);
+}
+
+// floating pixels pasted back, blended as premultiplied alpha.
+@fragment
+fn fs_commit(in: FloatingOutput) -> PaintOutput {
+    var out: PaintOutput;
+    out.color = floating_color(in);
+    let height = resample(height_texture, in.uv, in.bicubic).r;
+    out.height = vec4<f32>(height, 0.0, 0.0, out.color.a);
+    return out;
+}
+
+@fragment
+fn fs_preview(in: FloatingOutput) -> @location(0) vec4<f32> {
+    return floating_color(in);
+}